@@ -0,0 +1,73 @@
+#ifndef NIMDG_H
+#define NIMDG_H
+
+/* Generated by cbindgen from src/api/mod.rs, don't edit manually */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define NIMDG_OK 0
+
+#define NIMDG_INVALID_ARGUMENT 1
+
+#define NIMDG_WRONG_ENTITY 2
+
+#define NIMDG_TABLE_NOT_FOUND 3
+
+#define NIMDG_TABLE_NOT_CREATED 4
+
+#define NIMDG_ENTITY_NOT_FOUND 5
+
+#define NIMDG_UNDEFINED_TRANSACTION 6
+
+#define NIMDG_TRANSACTION_FAILED 7
+
+#define NIMDG_READ_ONLY_TRANSACTION 8
+
+#define NIMDG_TRANSACTION_PREPARED 9
+
+#define NIMDG_TRANSACTION_NOT_PREPARED 10
+
+#define NIMDG_TRANSACTION_KILLED 11
+
+#define NIMDG_OPERATION_FAILED 12
+
+#define NIMDG_INTERNAL_ERROR 13
+
+typedef struct nimdg_db nimdg_db;
+
+nimdg_db *nimdg_db_new(void);
+
+void nimdg_db_free(nimdg_db *db);
+
+int32_t nimdg_table_create(const nimdg_db *db, const char *description);
+
+int32_t nimdg_tx_start(const nimdg_db *db, const char *lock_mode, uint64_t *tx_id);
+
+int32_t nimdg_tx_commit(const nimdg_db *db, uint64_t tx_id);
+
+int32_t nimdg_tx_rollback(const nimdg_db *db, uint64_t tx_id);
+
+int32_t nimdg_get(const nimdg_db *db,
+                  uint64_t tx_id,
+                  const char *table_name,
+                  const char *key,
+                  char **value);
+
+int32_t nimdg_put(const nimdg_db *db,
+                  uint64_t tx_id,
+                  const char *table_name,
+                  const char *key,
+                  const char *value);
+
+int32_t nimdg_remove(const nimdg_db *db,
+                     uint64_t tx_id,
+                     const char *table_name,
+                     const char *key,
+                     bool *removed);
+
+void nimdg_string_free(char *string);
+
+const char *nimdg_last_error(void);
+
+#endif /* NIMDG_H */
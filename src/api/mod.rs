@@ -1,11 +1,250 @@
+// C ABI of data base. Keys, values and descriptions of tables are passed as json strings.
+// Functions return NIMDG_OK or error code, message of last error of thread is got by nimdg_last_error.
+// Strings returned by library are freed by nimdg_string_free
+#![allow(non_camel_case_types)]
 
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic;
+use std::ptr;
+use std::str::FromStr;
 
+use data_base::json::JsonValue;
+
+use data_base::{DataBaseManager, PersistenceError};
+use data_base::meta::TableDescriptionView;
+use data_base::transaction::LockMode;
+
+pub const NIMDG_OK: i32 = 0;
+pub const NIMDG_INVALID_ARGUMENT: i32 = 1; // null pointer, not utf-8 string or wrong json
+pub const NIMDG_WRONG_ENTITY: i32 = 2; // key or value doesn't match description of table
+pub const NIMDG_TABLE_NOT_FOUND: i32 = 3;
+pub const NIMDG_TABLE_NOT_CREATED: i32 = 4;
+pub const NIMDG_ENTITY_NOT_FOUND: i32 = 5;
+pub const NIMDG_UNDEFINED_TRANSACTION: i32 = 6;
+pub const NIMDG_TRANSACTION_FAILED: i32 = 7;
+pub const NIMDG_READ_ONLY_TRANSACTION: i32 = 8;
+pub const NIMDG_TRANSACTION_PREPARED: i32 = 9;
+pub const NIMDG_TRANSACTION_NOT_PREPARED: i32 = 10;
+pub const NIMDG_TRANSACTION_KILLED: i32 = 11;
+pub const NIMDG_OPERATION_FAILED: i32 = 12; // entry processor, batch or savepoint failed
+pub const NIMDG_INTERNAL_ERROR: i32 = 13;
+
+// Opaque handle of data base for C
+pub struct nimdg_db {
+    data_base_manager: DataBaseManager,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+type FfiResult<T> = Result<T, (i32, String)>;
+
+fn error_code(error: &PersistenceError) -> i32 {
+    match *error {
+        PersistenceError::IoEntity(_) => NIMDG_WRONG_ENTITY,
+        PersistenceError::TableNotFound(_) => NIMDG_TABLE_NOT_FOUND,
+        PersistenceError::EntityNotFound(_) => NIMDG_ENTITY_NOT_FOUND,
+        PersistenceError::UndefinedTransaction(_) => NIMDG_UNDEFINED_TRANSACTION,
+        PersistenceError::TransactionAlreadyStarted(_) |
+        PersistenceError::TransactionFailed(_) |
+        PersistenceError::LockFailed(_) |
+        PersistenceError::LockTimeout(_, _) |
+        PersistenceError::WrongTransaction(_, _) => NIMDG_TRANSACTION_FAILED,
+        PersistenceError::ReadOnlyTransaction(_) => NIMDG_READ_ONLY_TRANSACTION,
+        PersistenceError::TransactionPrepared(_) => NIMDG_TRANSACTION_PREPARED,
+        PersistenceError::TransactionNotPrepared(_) => NIMDG_TRANSACTION_NOT_PREPARED,
+        PersistenceError::TransactionKilled(_) => NIMDG_TRANSACTION_KILLED,
+        PersistenceError::ProcessorFailed(_) |
+        PersistenceError::BatchFailed(_, _) |
+        PersistenceError::SavepointNotFound(_) |
+        PersistenceError::ReservedSavepoint(_) |
+        PersistenceError::ChangesEvicted(_) |
+        PersistenceError::ImportFailed(_, _, _) => NIMDG_OPERATION_FAILED,
+        PersistenceError::Undefined(_) |
+        PersistenceError::PreparedLogFailed(_) => NIMDG_INTERNAL_ERROR,
+    }
+}
+
+fn from_persistence<T>(result: Result<T, PersistenceError>) -> FfiResult<T> {
+    result.map_err(|error| (error_code(&error), error.to_string()))
+}
+
+fn set_last_error(message: Option<String>) {
+    LAST_ERROR.with(|last_error| {
+        *last_error.borrow_mut() = message.map(|message| CString::new(message.replace('\0', " ")).unwrap());
+    });
+}
+
+// Panic must not unwind into C, it is reported as internal error
+fn call<F>(operation: F) -> i32
+    where F: FnOnce() -> FfiResult<()>
+{
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(operation))
+        .unwrap_or_else(|_| Err((NIMDG_INTERNAL_ERROR, String::from("Panic in nimdg"))));
+    match result {
+        Ok(_) => {
+            set_last_error(None);
+            NIMDG_OK
+        }
+        Err((code, message)) => {
+            set_last_error(Some(message));
+            code
+        }
+    }
+}
+
+unsafe fn get_db<'a>(db: *const nimdg_db) -> FfiResult<&'a DataBaseManager> {
+    db.as_ref()
+        .map(|db| &db.data_base_manager)
+        .ok_or((NIMDG_INVALID_ARGUMENT, String::from("Data base is null")))
+}
+
+unsafe fn get_str<'a>(string: *const c_char, name: &str) -> FfiResult<&'a str> {
+    if string.is_null() {
+        return Err((NIMDG_INVALID_ARGUMENT, format!("{} is null", name)));
+    }
+    CStr::from_ptr(string).to_str().map_err(|_| (NIMDG_INVALID_ARGUMENT, format!("{} is not utf-8", name)))
+}
+
+unsafe fn get_json(string: *const c_char, name: &str) -> FfiResult<JsonValue> {
+    let string = try!(get_str(string, name));
+    JsonValue::from_str(string).map_err(|err| (NIMDG_INVALID_ARGUMENT, format!("{} is wrong json: {}", name, err)))
+}
+
+/** Create empty data base
+ * return - null, if data base is not created */
 #[no_mangle]
-pub extern tx_start() -> u32 {
+pub extern "C" fn nimdg_db_new() -> *mut nimdg_db {
+    let mut db = ptr::null_mut();
+    call(|| {
+        let data_base_manager = try!(DataBaseManager::new().map_err(|err| (NIMDG_INTERNAL_ERROR, err)));
+        db = Box::into_raw(Box::new(nimdg_db { data_base_manager: data_base_manager }));
+        Ok(())
+    });
+    db
+}
 
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_db_free(db: *mut nimdg_db) {
+    if !db.is_null() {
+        drop(Box::from_raw(db));
+    }
 }
 
+/** Create table by json description, as for POST /meta/table */
 #[no_mangle]
-pub extern tx_commit(tx_id: u32) -> bool {
-    
-}
\ No newline at end of file
+pub unsafe extern "C" fn nimdg_table_create(db: *const nimdg_db, description: *const c_char) -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        let description = try!(get_json(description, "Table description"));
+        let view = try!(TableDescriptionView::from_json(&description).map_err(|err| (NIMDG_INVALID_ARGUMENT, err.to_string())));
+        data_base_manager.add_table(view).map(|_| ()).map_err(|err| (NIMDG_TABLE_NOT_CREATED, err))
+    })
+}
+
+/** Start transaction, lock_mode is optimistic, pessimistic or read_only, null - pessimistic */
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_tx_start(db: *const nimdg_db, lock_mode: *const c_char, tx_id: *mut u64) -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        let lock_mode = if lock_mode.is_null() {
+            LockMode::Pessimistic
+        } else {
+            try!(LockMode::from_str(try!(get_str(lock_mode, "Lock mode"))).map_err(|err| (NIMDG_INVALID_ARGUMENT, err)))
+        };
+        let tx_id = try!(tx_id.as_mut().ok_or((NIMDG_INVALID_ARGUMENT, String::from("Tx id is null"))));
+        *tx_id = try!(from_persistence(data_base_manager.tx_start(lock_mode)));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_tx_commit(db: *const nimdg_db, tx_id: u64) -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        from_persistence(data_base_manager.tx_stop(&tx_id))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_tx_rollback(db: *const nimdg_db, tx_id: u64) -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        from_persistence(data_base_manager.tx_rollback(&tx_id))
+    })
+}
+
+/** Get value by key, tx_id 0 - in own transaction.
+ * value is set to json of value, that is freed by nimdg_string_free, or to null, if value is not found */
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_get(db: *const nimdg_db,
+                                   tx_id: u64,
+                                   table_name: *const c_char,
+                                   key: *const c_char,
+                                   value: *mut *mut c_char)
+                                   -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        let table_name = String::from(try!(get_str(table_name, "Table name")));
+        let key = try!(get_json(key, "Key"));
+        let value = try!(value.as_mut().ok_or((NIMDG_INVALID_ARGUMENT, String::from("Value is null"))));
+        *value = match try!(from_persistence(data_base_manager.get_data(&tx_id, &table_name, &key))) {
+            Some(found) => CString::new(found.to_string()).unwrap().into_raw(),
+            None => ptr::null_mut(),
+        };
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_put(db: *const nimdg_db,
+                                   tx_id: u64,
+                                   table_name: *const c_char,
+                                   key: *const c_char,
+                                   value: *const c_char)
+                                   -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        let table_name = String::from(try!(get_str(table_name, "Table name")));
+        let key = try!(get_json(key, "Key"));
+        let value = try!(get_json(value, "Value"));
+        from_persistence(data_base_manager.add_data(&tx_id, &table_name, &key, &value))
+    })
+}
+
+/** Remove value by key, removed is set to true, if value existed. removed can be null */
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_remove(db: *const nimdg_db,
+                                      tx_id: u64,
+                                      table_name: *const c_char,
+                                      key: *const c_char,
+                                      removed: *mut bool)
+                                      -> i32 {
+    call(|| {
+        let data_base_manager = try!(get_db(db));
+        let table_name = String::from(try!(get_str(table_name, "Table name")));
+        let key = try!(get_json(key, "Key"));
+        let existed = try!(from_persistence(data_base_manager.remove_data(&tx_id, &table_name, &key)));
+        if let Some(removed) = removed.as_mut() {
+            *removed = existed;
+        }
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nimdg_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/** Message of error of last failed call in this thread, null if last call succeeded.
+ * Message is valid till next call of library in this thread */
+#[no_mangle]
+pub extern "C" fn nimdg_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
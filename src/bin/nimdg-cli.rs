@@ -0,0 +1,103 @@
+extern crate nimdg;
+extern crate rustyline;
+
+use std::cell::RefCell;
+use std::env;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+
+use nimdg::cli::{Command, Schema, Shell};
+use nimdg::cli::completion::ShellCompleter;
+use nimdg::cli::http::RestClient;
+
+const DEFAULT_URL: &'static str = "http://localhost:4300";
+const HISTORY_FILE: &'static str = ".nimdg_history";
+const TOKEN_VAR: &'static str = "NIMDG_TOKEN";
+
+const USAGE: &'static str = "Usage: nimdg-cli [--url <url of nimdg server>] [--token <api token> | --user <name:password>]
+
+Interactive shell of nimdg, type help for list of commands. Token is also read from NIMDG_TOKEN";
+
+fn rest_client(args: &[String]) -> Result<RestClient, String> {
+    let mut url = String::from(DEFAULT_URL);
+    let mut token = env::var(TOKEN_VAR).ok();
+    let mut user = None;
+    let mut options = args.iter();
+    while let Some(option) = options.next() {
+        let value = try!(options.next().ok_or(format!("Value of option {} not found\n{}", option, USAGE)));
+        match option.as_str() {
+            "--url" => url = value.clone(),
+            "--token" => token = Some(value.clone()),
+            "--user" => user = Some(value.clone()),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+    }
+    let client = try!(RestClient::new(&url));
+    match (user, token) {
+        (Some(user), _) => {
+            let mut parts = user.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(password)) => Ok(client.with_user(name, password)),
+                _ => Err(format!("User must be set as name:password\n{}", USAGE)),
+            }
+        }
+        (None, Some(token)) => Ok(client.with_token(&token)),
+        (None, None) => Ok(client),
+    }
+}
+
+fn history_path() -> PathBuf {
+    env::home_dir().unwrap_or(PathBuf::from(".")).join(HISTORY_FILE)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let client = try!(rest_client(args));
+    let schema = Rc::new(RefCell::new(Schema::new(client.clone())));
+    let mut shell = Shell::new(client, schema.clone());
+    let mut editor = Editor::<ShellCompleter>::new();
+    editor.set_completer(Some(ShellCompleter::new(schema)));
+    let history = history_path();
+    let _ = editor.load_history(&history);
+    loop {
+        match editor.readline(&shell.prompt()) {
+            Ok(line) => {
+                editor.add_history_entry(&line);
+                match Command::parse(&line) {
+                    Ok(Some(Command::Exit)) => break,
+                    Ok(Some(command)) => {
+                        match shell.execute(command) {
+                            Ok(output) => println!("{}", output),
+                            Err(err) => println!("Error: {}", err),
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(err) => println!("Error: {}", err),
+                }
+            }
+            // Ctrl-C drops current line, Ctrl-D exits
+            Err(ReadlineError::Interrupted) => (),
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(format!("Cannot read line: {}", err)),
+        }
+    }
+    let _ = editor.save_history(&history);
+    shell.close()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Err(err) = run(&args) {
+        let _ = writeln!(io::stderr(), "{}", err);
+        process::exit(1);
+    }
+}
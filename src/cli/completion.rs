@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rustyline;
+use rustyline::completion::Completer;
+
+use cli::Schema;
+
+const COMMANDS: &'static [&'static str] = &["begin", "commit", "rollback", "get", "put", "delete", "scan", "import", "export",
+                                            "tables", "describe", "create", "help", "exit"];
+const LOCK_MODES: &'static [&'static str] = &["optimistic", "pessimistic", "read_only"];
+const TABLE_COMMANDS: &'static [&'static str] = &["get", "put", "delete", "scan", "import", "export", "describe"];
+const ENTITY_COMMANDS: &'static [&'static str] = &["get", "put", "delete"];
+
+// Completion of commands, names of tables and names of fields in keys and values
+pub struct ShellCompleter {
+    schema: Rc<RefCell<Schema>>,
+}
+
+impl ShellCompleter {
+    pub fn new(schema: Rc<RefCell<Schema>>) -> ShellCompleter {
+        ShellCompleter { schema: schema }
+    }
+}
+
+impl Completer for ShellCompleter {
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        let schema = &self.schema;
+        Ok(complete_line(line,
+                         pos,
+                         || schema.borrow_mut().table_names(),
+                         |table_name| schema.borrow_mut().field_names(table_name)))
+    }
+}
+
+fn matching(candidates: Vec<String>, prefix: &str) -> Vec<String> {
+    candidates.into_iter().filter(|candidate| candidate.starts_with(prefix)).collect()
+}
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| String::from(*word)).collect()
+}
+
+/** Candidates for word before position of cursor. Tables and fields are got only when they are needed
+ * return - start of replaced word and candidates */
+pub fn complete_line<T, F>(line: &str, pos: usize, tables: T, fields: F) -> (usize, Vec<String>)
+    where T: FnOnce() -> Vec<String>,
+          F: FnOnce(&str) -> Vec<String>
+{
+    let prefix = &line[..pos];
+    let previous: Vec<&str> = prefix.split_whitespace().collect();
+    let (index, word) = if prefix.is_empty() || prefix.ends_with(char::is_whitespace) {
+        (previous.len(), "")
+    } else {
+        (previous.len() - 1, previous[previous.len() - 1])
+    };
+    let start = pos - word.len();
+    let command = previous.first().cloned().unwrap_or("");
+    match index {
+        0 => (start, matching(words(COMMANDS), word)),
+        1 if command == "begin" => (start, matching(words(LOCK_MODES), word)),
+        1 if command == "create" => (start, matching(words(&["table"]), word)),
+        1 if TABLE_COMMANDS.contains(&command) => (start, matching(tables(), word)),
+        _ if index >= 2 && ENTITY_COMMANDS.contains(&command) => {
+            // Name of field in json object, possibly started by quote
+            let token_start = prefix.rfind(|symbol: char| symbol == '{' || symbol == ',' || symbol.is_whitespace())
+                .map(|index| index + 1)
+                .unwrap_or(0);
+            let name = prefix[token_start..].trim_left_matches('"');
+            if !name.chars().all(|symbol| symbol.is_alphanumeric() || symbol == '_') {
+                return (pos, Vec::new());
+            }
+            let candidates = matching(fields(previous[1]), name).into_iter().map(|field| format!("\"{}\": ", field)).collect();
+            (token_start, candidates)
+        }
+        _ => (pos, Vec::new()),
+    }
+}
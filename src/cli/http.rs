@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rustc_serialize::base64::{ToBase64, STANDARD};
+
+use hyper;
+use hyper::Url;
+use hyper::header::{Authorization, ContentType};
+use hyper::method::Method;
+use hyper::status::StatusCode;
+
+use data_base::bulk::BulkFormat;
+use data_base::json::JsonValue;
+use data_base::transaction::LockMode;
+
+/** Client of REST api. Path of request is passed by segments, that are percent-encoded,
+ * so keys in json can be used as segments */
+#[derive(Clone)]
+pub struct RestClient {
+    base: Url,
+    http: Arc<hyper::Client>,
+    authorization: Option<String>, // value of header Authorization
+}
+
+impl RestClient {
+    pub fn new(address: &str) -> Result<RestClient, String> {
+        let mut base = try!(Url::parse(address).map_err(|err| format!("Wrong address {}: {}", address, err)));
+        {
+            let mut segments = try!(base.path_segments_mut().map_err(|_| format!("Wrong address {}", address)));
+            segments.pop_if_empty().extend(&["api", "v1"]);
+        }
+        Ok(RestClient {
+            base: base,
+            http: Arc::new(hyper::Client::new()),
+            authorization: None,
+        })
+    }
+
+    // API token of server with authentication
+    pub fn with_token(mut self, token: &str) -> RestClient {
+        self.authorization = Some(format!("Bearer {}", token));
+        self
+    }
+
+    // User of HTTP Basic authentication
+    pub fn with_user(mut self, name: &str, password: &str) -> RestClient {
+        self.authorization = Some(format!("Basic {}", format!("{}:{}", name, password).as_bytes().to_base64(STANDARD)));
+        self
+    }
+
+    /** Send request
+     * return - status and json of response */
+    pub fn request(&self, method: Method, segments: &[&str], body: Option<&JsonValue>) -> Result<(StatusCode, JsonValue), String> {
+        let mut url = self.base.clone();
+        url.path_segments_mut().unwrap().extend(segments);
+        let body = body.map(|body| body.to_string());
+        let mut request = self.http.request(method, url.clone());
+        if let Some(ref authorization) = self.authorization {
+            request = request.header(Authorization(authorization.clone()));
+        }
+        if let Some(ref body) = body {
+            request = request.header(ContentType::json()).body(body.as_str());
+        }
+        let mut response = try!(request.send().map_err(|err| format!("Request {} failed: {}", url, err)));
+        let mut text = String::new();
+        try!(response.read_to_string(&mut text).map_err(|err| format!("Cannot read response of {}: {}", url, err)));
+        let json = try!(JsonValue::from_str(&text).map_err(|err| format!("Wrong response of {}: {}: {}", url, err, text)));
+        Ok((response.status, json))
+    }
+
+    // Successful response or error with message of server
+    fn call(&self, method: Method, segments: &[&str], body: Option<&JsonValue>) -> Result<JsonValue, String> {
+        let (status, json) = try!(self.request(method, segments, body));
+        if status.is_success() {
+            Ok(json)
+        } else {
+            Err(error_message(status, json))
+        }
+    }
+
+    pub fn tx_start(&self, lock_mode: &LockMode) -> Result<u64, String> {
+        let tx_id = try!(self.call(Method::Post, &["tx", lock_mode.name(), "start"], None));
+        tx_id.as_u64().ok_or(format!("Wrong id of transaction {}", tx_id))
+    }
+
+    pub fn tx_commit(&self, lock_mode: &LockMode, tx_id: u64) -> Result<(), String> {
+        self.call(Method::Delete, &["tx", lock_mode.name(), "stop", &tx_id.to_string()], None).map(|_| ())
+    }
+
+    pub fn tx_rollback(&self, tx_id: u64) -> Result<(), String> {
+        self.call(Method::Delete, &["tx", "rollback", &tx_id.to_string()], None).map(|_| ())
+    }
+
+    pub fn get(&self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<Option<JsonValue>, String> {
+        let (status, json) = try!(self.request(Method::Get, &["get", table_name, &tx_id.to_string(), &key.to_string()], None));
+        match status {
+            StatusCode::NotFound if is_entity_not_found(&json) => Ok(None),
+            status if status.is_success() => Ok(Some(json)),
+            status => Err(error_message(status, json)),
+        }
+    }
+
+    pub fn put(&self, tx_id: u64, table_name: &str, key: &JsonValue, value: &JsonValue) -> Result<(), String> {
+        let mut data = BTreeMap::new();
+        data.insert(String::from("key"), key.clone());
+        data.insert(String::from("value"), value.clone());
+        let mut body = BTreeMap::new();
+        body.insert(String::from("tx_id"), JsonValue::U64(tx_id));
+        body.insert(String::from("data"), JsonValue::Object(data));
+        self.call(Method::Post, &["put", table_name], Some(&JsonValue::Object(body))).map(|_| ())
+    }
+
+    /** Delete value by key
+     * return - true, if value existed */
+    pub fn delete(&self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<bool, String> {
+        let (status, json) = try!(self.request(Method::Delete, &["delete", table_name, &tx_id.to_string(), &key.to_string()], None));
+        match status {
+            StatusCode::NotFound if is_entity_not_found(&json) => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(error_message(status, json)),
+        }
+    }
+
+    // Entities of scan are pairs [key, value]
+    pub fn scan(&self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<JsonValue>, String> {
+        let entities = try!(self.call(Method::Get,
+                                      &["get", table_name, &tx_id.to_string(), &start.to_string(), &count.to_string()],
+                                      None));
+        match entities {
+            JsonValue::Array(entities) => Ok(entities),
+            entities => Err(format!("Wrong response of scan {}", entities)),
+        }
+    }
+
+    // Descriptions of tables by names
+    pub fn tables(&self) -> Result<BTreeMap<String, JsonValue>, String> {
+        match try!(self.call(Method::Get, &["info"], None)) {
+            JsonValue::Object(tables) => Ok(tables),
+            tables => Err(format!("Wrong response of tables {}", tables)),
+        }
+    }
+
+    pub fn describe(&self, table_name: &str) -> Result<JsonValue, String> {
+        self.call(Method::Get, &["meta", "table", table_name], None)
+    }
+
+    pub fn create_table(&self, description: &JsonValue) -> Result<String, String> {
+        let result = try!(self.call(Method::Post, &["meta", "table"], Some(description)));
+        Ok(result.as_str().map(String::from).unwrap_or(result.to_string()))
+    }
+
+    // Report of import: counts of imported rows and batches and rejected rows with lines
+    pub fn import(&self, table_name: &str, format: &BulkFormat, batch_size: Option<u32>, data: String) -> Result<JsonValue, String> {
+        let mut body = BTreeMap::new();
+        body.insert(String::from("format"), JsonValue::String(String::from(format.name())));
+        if let Some(batch_size) = batch_size {
+            body.insert(String::from("batch_size"), JsonValue::U64(batch_size as u64));
+        }
+        body.insert(String::from("data"), JsonValue::String(data));
+        self.call(Method::Post, &["bulk", "import", table_name], Some(&JsonValue::Object(body)))
+    }
+
+    /** Export committed entities of table
+     * return - count of entities and exported text */
+    pub fn export(&self, table_name: &str, format: &BulkFormat) -> Result<(u64, String), String> {
+        let result = try!(self.call(Method::Get, &["bulk", "export", table_name, format.name()], None));
+        match (result.find("count").and_then(|count| count.as_u64()), result.find("data").and_then(|data| data.as_str())) {
+            (Some(count), Some(data)) => Ok((count, String::from(data))),
+            _ => Err(format!("Wrong response of export {}", result)),
+        }
+    }
+}
+
+// Not found status is returned also for unknown table and transaction
+fn is_entity_not_found(json: &JsonValue) -> bool {
+    json.find("code").and_then(|code| code.as_str()) == Some("entity_not_found")
+}
+
+// Error of server is { "code", "message", .. }
+fn error_message(status: StatusCode, json: JsonValue) -> String {
+    match (json.find("code").and_then(|code| code.as_str()), json.find("message").and_then(|message| message.as_str())) {
+        (Some(code), Some(message)) => return format!("{} {}: {}", status, code, message),
+        _ => (),
+    }
+    match json {
+        JsonValue::String(message) => format!("{}: {}", status, message),
+        json => format!("{}: {}", status, json),
+    }
+}
@@ -0,0 +1,518 @@
+// Interactive shell of nimdg over REST api. Keys, values and descriptions of tables
+// are written as json, results are printed as tables
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+use data_base::bulk::BulkFormat;
+use data_base::json::JsonValue;
+use data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use self::http::RestClient;
+
+pub mod completion;
+pub mod http;
+
+const DEFAULT_SCAN_COUNT: u32 = 20;
+
+pub const HELP: &'static str = "Commands:
+  begin [optimistic|pessimistic|read_only]  start transaction, next commands are executed in it
+  commit                                    commit transaction
+  rollback                                  rollback transaction
+  get <table> <key>                         get value by key, key is json
+  put <table> <key> <value>                 put value by key
+  delete <table> <key>                      delete value by key
+  scan <table> [start] [count]              list entities of table
+  import <table> <file> [batch size]        import file of JSON lines or, for .csv, of CSV with header
+  export <table> <file>                     export committed entities to file of JSON lines or CSV
+  tables                                    list tables
+  describe <table>                          fields of key and value of table
+  create table <description>                create table by json description, as for POST /meta/table
+  help                                      this help
+  exit                                      exit shell";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Begin(LockMode),
+    Commit,
+    Rollback,
+    Get(String, JsonValue),
+    Put(String, JsonValue, JsonValue),
+    Delete(String, JsonValue),
+    Scan(String, u32, u32),
+    Import(String, String, Option<u32>),
+    Export(String, String),
+    Tables,
+    Describe(String),
+    CreateTable(JsonValue),
+    Help,
+    Exit,
+}
+
+impl Command {
+    /** Parse line of shell
+     * return - None for empty line */
+    pub fn parse(line: &str) -> Result<Option<Command>, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let (name, args) = split_word(line);
+        let command = match name {
+            "begin" => {
+                match split_word(args) {
+                    ("", _) => Command::Begin(LockMode::Pessimistic),
+                    (mode, "") => Command::Begin(try!(LockMode::from_str(mode))),
+                    _ => return Err(String::from("Usage: begin [optimistic|pessimistic|read_only]")),
+                }
+            }
+            "commit" => try!(no_args(Command::Commit, args)),
+            "rollback" => try!(no_args(Command::Rollback, args)),
+            "get" | "delete" => {
+                let (table_name, key) = try!(table_and_rest(name, args));
+                let (key, rest) = try!(split_json(key));
+                if !rest.is_empty() {
+                    return Err(format!("Unexpected {} after key", rest));
+                }
+                if name == "get" {
+                    Command::Get(table_name, key)
+                } else {
+                    Command::Delete(table_name, key)
+                }
+            }
+            "put" => {
+                let (table_name, rest) = try!(table_and_rest(name, args));
+                let (key, rest) = try!(split_json(rest));
+                let (value, rest) = try!(split_json(rest));
+                if !rest.is_empty() {
+                    return Err(format!("Unexpected {} after value", rest));
+                }
+                Command::Put(table_name, key, value)
+            }
+            "scan" => {
+                let mut words = args.split_whitespace();
+                let table_name = try!(words.next().ok_or(String::from("Usage: scan <table> [start] [count]")));
+                let start = try!(words.next().map_or(Ok(0), |start| start.parse().map_err(|_| format!("Wrong start {}", start))));
+                let count = try!(words.next()
+                    .map_or(Ok(DEFAULT_SCAN_COUNT), |count| count.parse().map_err(|_| format!("Wrong count {}", count))));
+                if let Some(word) = words.next() {
+                    return Err(format!("Unexpected {}", word));
+                }
+                Command::Scan(String::from(table_name), start, count)
+            }
+            "import" => {
+                let mut words = args.split_whitespace();
+                match (words.next(), words.next(), words.next(), words.next()) {
+                    (Some(table_name), Some(path), batch_size, None) => {
+                        let batch_size = try!(batch_size.map_or(Ok(None), |batch_size| {
+                            batch_size.parse().map(Some).map_err(|_| format!("Wrong batch size {}", batch_size))
+                        }));
+                        Command::Import(String::from(table_name), String::from(path), batch_size)
+                    }
+                    _ => return Err(String::from("Usage: import <table> <file> [batch size]")),
+                }
+            }
+            "export" => {
+                let mut words = args.split_whitespace();
+                match (words.next(), words.next(), words.next()) {
+                    (Some(table_name), Some(path), None) => Command::Export(String::from(table_name), String::from(path)),
+                    _ => return Err(String::from("Usage: export <table> <file>")),
+                }
+            }
+            "tables" => try!(no_args(Command::Tables, args)),
+            "describe" => {
+                match split_word(args) {
+                    (table_name, "") if !table_name.is_empty() => Command::Describe(String::from(table_name)),
+                    _ => return Err(String::from("Usage: describe <table>")),
+                }
+            }
+            "create" => {
+                let (what, description) = split_word(args);
+                if what != "table" {
+                    return Err(String::from("Usage: create table <description>"));
+                }
+                let (description, rest) = try!(split_json(description));
+                if !rest.is_empty() {
+                    return Err(format!("Unexpected {} after description", rest));
+                }
+                Command::CreateTable(description)
+            }
+            "help" => Command::Help,
+            "exit" | "quit" => Command::Exit,
+            _ => return Err(format!("Unknown command {}, type help for list of commands", name)),
+        };
+        Ok(Some(command))
+    }
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_left();
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], text[end..].trim_left()),
+        None => (text, ""),
+    }
+}
+
+fn no_args(command: Command, args: &str) -> Result<Command, String> {
+    if args.is_empty() {
+        Ok(command)
+    } else {
+        Err(format!("Unexpected {}", args))
+    }
+}
+
+fn table_and_rest<'a>(command: &str, args: &'a str) -> Result<(String, &'a str), String> {
+    match split_word(args) {
+        ("", _) => Err(format!("Table of {} not found", command)),
+        (table_name, rest) => Ok((String::from(table_name), rest)),
+    }
+}
+
+/** Read first json value of text. Objects, arrays and strings may contain spaces,
+ * other values end by space
+ * return - value and rest of text */
+pub fn split_json(text: &str) -> Result<(JsonValue, &str), String> {
+    let text = text.trim_left();
+    if text.is_empty() {
+        return Err(String::from("Json not found"));
+    }
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut end = None;
+    for (index, symbol) in text.char_indices() {
+        if in_string {
+            match symbol {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            if !in_string && depth == 0 {
+                end = Some(index + 1);
+                break;
+            }
+            continue;
+        }
+        match symbol {
+            '"' => in_string = true,
+            '{' | '[' => depth += 1,
+            '}' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    end = Some(index + 1);
+                    break;
+                }
+            }
+            symbol if symbol.is_whitespace() && depth == 0 => {
+                end = Some(index);
+                break;
+            }
+            _ => (),
+        }
+    }
+    let end = end.unwrap_or(text.len());
+    let json = try!(JsonValue::from_str(&text[..end]).map_err(|err| format!("Wrong json {}: {}", &text[..end], err)));
+    Ok((json, text[end..].trim_left()))
+}
+
+// Strings are printed without quotes
+fn cell(json: &JsonValue) -> String {
+    match *json {
+        JsonValue::String(ref string) => string.clone(),
+        JsonValue::Null => String::new(),
+        ref json => json.to_string(),
+    }
+}
+
+/** Format rows as table with header:
+ * id | full_name
+ * ---+----------
+ * 1  | John Doe */
+pub fn format_table(headers: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in rows {
+        for (index, value) in row.iter().enumerate().take(widths.len()) {
+            widths[index] = ::std::cmp::max(widths[index], value.chars().count());
+        }
+    }
+    let format_row = |row: &[String]| -> String {
+        // Empty cells at end of row are not printed
+        let filled = row.iter().take(widths.len()).rposition(|value| !value.is_empty()).map_or(0, |index| index + 1);
+        let cells: Vec<String> = widths.iter()
+            .zip(row.iter())
+            .take(filled)
+            .map(|(width, value)| format!("{}{}", value, " ".repeat(width - value.chars().count())))
+            .collect();
+        String::from(cells.join(" | ").trim_right())
+    };
+    let mut lines = vec![format_row(headers)];
+    lines.push(widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<String>>().join("-+-"));
+    for row in rows {
+        lines.push(format_row(&row[..]));
+    }
+    lines.join("\n")
+}
+
+/** Table of entities, columns are fields of keys and then fields of values.
+ * Field of value with name of key field is shown as value.field */
+pub fn format_entities(entities: &[(JsonValue, JsonValue)]) -> String {
+    let mut key_fields: Vec<String> = Vec::new();
+    let mut value_fields: Vec<String> = Vec::new();
+    for &(ref key, ref value) in entities {
+        for (fields, json) in vec![(&mut key_fields, key), (&mut value_fields, value)] {
+            if let Some(object) = json.as_object() {
+                for field in object.keys() {
+                    if !fields.contains(field) {
+                        fields.push(field.clone());
+                    }
+                }
+            }
+        }
+    }
+    let rows: Vec<Vec<String>> = entities.iter()
+        .map(|&(ref key, ref value)| {
+            key_fields.iter()
+                .map(|field| key.find(field).map(cell).unwrap_or(String::new()))
+                .chain(value_fields.iter().map(|field| value.find(field).map(cell).unwrap_or(String::new())))
+                .collect()
+        })
+        .collect();
+    let headers: Vec<String> = key_fields.iter()
+        .cloned()
+        .chain(value_fields.iter().map(|field| {
+            if key_fields.contains(field) {
+                format!("value.{}", field)
+            } else {
+                field.clone()
+            }
+        }))
+        .collect();
+    let count = format!("({} {})", entities.len(), if entities.len() == 1 { "entity" } else { "entities" });
+    format!("{}\n{}", format_table(&headers, &rows), count)
+}
+
+// Json of table is { "name": .., "key": { field: type }, "value": { field: type }, "lock_mode": .. }
+pub fn format_description(description: &JsonValue) -> String {
+    let mut rows = Vec::new();
+    for part in &["key", "value"] {
+        if let Some(fields) = description.find(part).and_then(|fields| fields.as_object()) {
+            for (field, type_name) in fields.iter() {
+                rows.push(vec![String::from(*part), field.clone(), cell(type_name)]);
+            }
+        }
+    }
+    let headers = vec![String::from("part"), String::from("field"), String::from("type")];
+    let lock_mode = description.find("lock_mode").map(cell).unwrap_or(String::new());
+    format!("{}\nlock mode: {}", format_table(&headers, &rows), lock_mode)
+}
+
+// Format of file for import and export is chosen by extension
+pub fn file_format(path: &str) -> BulkFormat {
+    if path.ends_with(".csv") {
+        BulkFormat::Csv
+    } else {
+        BulkFormat::JsonLines
+    }
+}
+
+/** Counts of import and table of rejected rows:
+ * line | error */
+pub fn format_import_report(report: &JsonValue) -> String {
+    let count = |name: &str| report.find(name).and_then(|count| count.as_u64()).unwrap_or(0);
+    let mut res = format!("Imported {} rows in {} batches", count("imported"), count("batches"));
+    let rejected = report.find("rejected").and_then(|rejected| rejected.as_array()).cloned().unwrap_or(Vec::new());
+    if !rejected.is_empty() {
+        let rows: Vec<Vec<String>> = rejected.iter()
+            .map(|row| {
+                vec![row.find("line").map(cell).unwrap_or(String::new()),
+                     row.find("error").map(cell).unwrap_or(String::new())]
+            })
+            .collect();
+        res = format!("{}, rejected {} rows:\n{}",
+                      res,
+                      rows.len(),
+                      format_table(&[String::from("line"), String::from("error")], &rows));
+    }
+    res
+}
+
+/** Names of tables and fields, that are loaded from server on demand
+ * and used by completion */
+pub struct Schema {
+    client: RestClient,
+    tables: Option<Vec<String>>,
+    fields: BTreeMap<String, Vec<String>>,
+}
+
+impl Schema {
+    pub fn new(client: RestClient) -> Schema {
+        Schema {
+            client: client,
+            tables: None,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn table_names(&mut self) -> Vec<String> {
+        if self.tables.is_none() {
+            self.tables = self.client.tables().ok().map(|tables| tables.keys().cloned().collect());
+        }
+        self.tables.clone().unwrap_or(Vec::new())
+    }
+
+    // Fields of key and value
+    pub fn field_names(&mut self, table_name: &str) -> Vec<String> {
+        if !self.fields.contains_key(table_name) {
+            if let Ok(description) = self.client.describe(table_name) {
+                let mut names = Vec::new();
+                for part in &["key", "value"] {
+                    if let Some(fields) = description.find(part).and_then(|fields| fields.as_object()) {
+                        names.extend(fields.keys().cloned());
+                    }
+                }
+                self.fields.insert(String::from(table_name), names);
+            }
+        }
+        self.fields.get(table_name).cloned().unwrap_or(Vec::new())
+    }
+
+    pub fn invalidate(&mut self) {
+        self.tables = None;
+        self.fields.clear();
+    }
+}
+
+/** State of shell: connection and current transaction.
+ * Without transaction every command is executed in own transaction */
+pub struct Shell {
+    client: RestClient,
+    schema: Rc<RefCell<Schema>>,
+    tx: Option<(u64, LockMode)>,
+}
+
+impl Shell {
+    pub fn new(client: RestClient, schema: Rc<RefCell<Schema>>) -> Shell {
+        Shell {
+            client: client,
+            schema: schema,
+            tx: None,
+        }
+    }
+
+    pub fn prompt(&self) -> String {
+        match self.tx {
+            Some((tx_id, ref lock_mode)) => format!("nimdg [tx {} {}]> ", tx_id, lock_mode.name()),
+            None => String::from("nimdg> "),
+        }
+    }
+
+    fn tx_id(&self) -> u64 {
+        self.tx.as_ref().map(|&(tx_id, _)| tx_id).unwrap_or(DEFAULT_TX_ID)
+    }
+
+    /** Execute command
+     * return - text to print */
+    pub fn execute(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Begin(lock_mode) => {
+                if let Some((tx_id, _)) = self.tx {
+                    return Err(format!("Transaction {} is already started", tx_id));
+                }
+                let tx_id = try!(self.client.tx_start(&lock_mode));
+                self.tx = Some((tx_id, lock_mode));
+                Ok(format!("Transaction {} started", tx_id))
+            }
+            Command::Commit => {
+                let (tx_id, lock_mode) = try!(self.tx.take().ok_or(String::from("Transaction is not started")));
+                try!(self.client.tx_commit(&lock_mode, tx_id));
+                Ok(format!("Transaction {} committed", tx_id))
+            }
+            Command::Rollback => {
+                let (tx_id, _) = try!(self.tx.take().ok_or(String::from("Transaction is not started")));
+                try!(self.client.tx_rollback(tx_id));
+                Ok(format!("Transaction {} rolled back", tx_id))
+            }
+            Command::Get(table_name, key) => {
+                match try!(self.client.get(self.tx_id(), &table_name, &key)) {
+                    Some(value) => Ok(format_entities(&[(key, value)])),
+                    None => Ok(format!("Entity with key {} not found", key)),
+                }
+            }
+            Command::Put(table_name, key, value) => {
+                try!(self.client.put(self.tx_id(), &table_name, &key, &value));
+                Ok(String::from("Done"))
+            }
+            Command::Delete(table_name, key) => {
+                if try!(self.client.delete(self.tx_id(), &table_name, &key)) {
+                    Ok(String::from("Deleted"))
+                } else {
+                    Ok(format!("Entity with key {} not found", key))
+                }
+            }
+            Command::Scan(table_name, start, count) => {
+                let entities = try!(self.client.scan(self.tx_id(), &table_name, start, count));
+                let entities: Vec<(JsonValue, JsonValue)> = try!(entities.into_iter()
+                    .map(|entity| match entity {
+                        // Entity of scan is pair of key and value
+                        JsonValue::Array(mut pair) => {
+                            if pair.len() != 2 {
+                                return Err(format!("Wrong entity of scan {:?}", pair));
+                            }
+                            let value = pair.pop().unwrap();
+                            Ok((pair.pop().unwrap(), value))
+                        }
+                        entity => Err(format!("Wrong entity of scan {}", entity)),
+                    })
+                    .collect());
+                Ok(format_entities(&entities))
+            }
+            Command::Import(table_name, path, batch_size) => {
+                let mut data = String::new();
+                try!(File::open(&path)
+                    .and_then(|mut file| file.read_to_string(&mut data))
+                    .map_err(|err| format!("Cannot read {}: {}", path, err)));
+                let report = try!(self.client.import(&table_name, &file_format(&path), batch_size, data));
+                Ok(format_import_report(&report))
+            }
+            Command::Export(table_name, path) => {
+                let (count, data) = try!(self.client.export(&table_name, &file_format(&path)));
+                try!(File::create(&path)
+                    .and_then(|mut file| file.write_all(data.as_bytes()))
+                    .map_err(|err| format!("Cannot write {}: {}", path, err)));
+                Ok(format!("Exported {} entities to {}", count, path))
+            }
+            Command::Tables => {
+                let tables = try!(self.client.tables());
+                let rows: Vec<Vec<String>> = tables.iter()
+                    .map(|(name, description)| {
+                        vec![name.clone(), description.find("lock_mode").map(cell).unwrap_or(String::new())]
+                    })
+                    .collect();
+                Ok(format_table(&[String::from("table"), String::from("lock mode")], &rows))
+            }
+            Command::Describe(table_name) => {
+                let description = try!(self.client.describe(&table_name));
+                Ok(format_description(&description))
+            }
+            Command::CreateTable(description) => {
+                let result = try!(self.client.create_table(&description));
+                self.schema.borrow_mut().invalidate();
+                Ok(result)
+            }
+            Command::Help => Ok(String::from(HELP)),
+            Command::Exit => Ok(String::new()),
+        }
+    }
+
+    // Transaction, left open on exit, is rolled back
+    pub fn close(&mut self) -> Result<(), String> {
+        match self.tx.take() {
+            Some((tx_id, _)) => self.client.tx_rollback(tx_id),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// Typed client of nimdg over binary protocol.
+// Keys and values are serde structures, that are converted to json of table types
+extern crate serde;
+extern crate serde_json;
+
+use std::fmt;
+use std::fmt::Display;
+use std::net::ToSocketAddrs;
+use std::sync::Mutex;
+
+use self::serde::{Serialize, Deserialize};
+use data_base::json::JsonValue;
+
+use data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+use protocol::client::BinaryClient;
+
+#[derive(Debug)]
+pub enum ClientError {
+    Connection(String), // server is not available
+    Request(String), // operation failed on server or connection was broken
+    Serialization(String), // key or value doesn't match type
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ClientError::Connection(ref error) => write!(f, "Connection error: {}", error),
+            ClientError::Request(ref error) => write!(f, "Request error: {}", error),
+            ClientError::Serialization(ref error) => write!(f, "Serialization error: {}", error),
+        }
+    }
+}
+
+/** Connection to server. Operations of client are executed in own transactions,
+ * operations of Transaction are executed in transaction started by begin */
+pub struct Client {
+    connection: Mutex<BinaryClient>,
+}
+
+/** Transaction is committed only by commit, dropped transaction is rolled back */
+pub struct Transaction<'a> {
+    client: &'a Client,
+    tx_id: u64,
+    finished: bool,
+}
+
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> Result<Client, ClientError> {
+        let connection = try!(BinaryClient::connect(address).map_err(ClientError::Connection));
+        Ok(Client::with_connection(connection))
+    }
+
+    // Client over connected binary client, for example over TLS
+    pub fn with_connection(connection: BinaryClient) -> Client {
+        Client { connection: Mutex::new(connection) }
+    }
+
+    // Pessimistic transaction with repeatable read, as transactions of REST API by default
+    pub fn begin(&self) -> Result<Transaction, ClientError> {
+        self.begin_with(LockMode::Pessimistic, IsolationLevel::RepeatableRead)
+    }
+
+    pub fn begin_with(&self, lock_mode: LockMode, isolation_level: IsolationLevel) -> Result<Transaction, ClientError> {
+        let tx_id = try!(self.call(|connection| connection.tx_start(lock_mode, isolation_level)));
+        Ok(Transaction {
+            client: self,
+            tx_id: tx_id,
+            finished: false,
+        })
+    }
+
+    pub fn get<K: Serialize, V: Deserialize>(&self, table_name: &str, key: &K) -> Result<Option<V>, ClientError> {
+        self.tx_get(DEFAULT_TX_ID, table_name, key)
+    }
+
+    pub fn put<K: Serialize, V: Serialize>(&self, table_name: &str, key: &K, value: &V) -> Result<(), ClientError> {
+        self.tx_put(DEFAULT_TX_ID, table_name, key, value)
+    }
+
+    /** Remove entity by key
+     * return - true, if entity existed */
+    pub fn remove<K: Serialize>(&self, table_name: &str, key: &K) -> Result<bool, ClientError> {
+        self.tx_remove(DEFAULT_TX_ID, table_name, key)
+    }
+
+    pub fn scan<K: Deserialize, V: Deserialize>(&self, table_name: &str, start: u32, count: u32) -> Result<Vec<(K, V)>, ClientError> {
+        self.tx_scan(DEFAULT_TX_ID, table_name, start, count)
+    }
+
+    // Requests of transactions are sent by one connection in turn
+    fn call<T, F>(&self, operation: F) -> Result<T, ClientError>
+        where F: FnOnce(&mut BinaryClient) -> Result<T, String>
+    {
+        let mut connection = self.connection.lock().unwrap();
+        operation(&mut connection).map_err(ClientError::Request)
+    }
+
+    fn tx_get<K: Serialize, V: Deserialize>(&self, tx_id: u64, table_name: &str, key: &K) -> Result<Option<V>, ClientError> {
+        let key = to_json(key);
+        match try!(self.call(|connection| connection.get(tx_id, table_name, &key))) {
+            Some(value) => from_json(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn tx_put<K: Serialize, V: Serialize>(&self, tx_id: u64, table_name: &str, key: &K, value: &V) -> Result<(), ClientError> {
+        let (key, value) = (to_json(key), to_json(value));
+        self.call(|connection| connection.put(tx_id, table_name, &key, &value))
+    }
+
+    fn tx_remove<K: Serialize>(&self, tx_id: u64, table_name: &str, key: &K) -> Result<bool, ClientError> {
+        let key = to_json(key);
+        self.call(|connection| connection.remove(tx_id, table_name, &key))
+    }
+
+    fn tx_scan<K: Deserialize, V: Deserialize>(&self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<(K, V)>, ClientError> {
+        let entities = try!(self.call(|connection| connection.scan(tx_id, table_name, start, count)));
+        entities.into_iter()
+            .map(|entity| match entity {
+                // Entity of list is pair of key and value
+                JsonValue::Array(mut pair) => {
+                    if pair.len() != 2 {
+                        return Err(ClientError::Serialization(format!("Wrong entity of list {:?}", pair)));
+                    }
+                    let value = pair.pop().unwrap();
+                    let key = pair.pop().unwrap();
+                    Ok((try!(from_json(key)), try!(from_json(value))))
+                }
+                entity => Err(ClientError::Serialization(format!("Wrong entity of list {}", entity))),
+            })
+            .collect()
+    }
+}
+
+impl<'a> Transaction<'a> {
+    pub fn id(&self) -> u64 {
+        self.tx_id
+    }
+
+    pub fn get<K: Serialize, V: Deserialize>(&self, table_name: &str, key: &K) -> Result<Option<V>, ClientError> {
+        self.client.tx_get(self.tx_id, table_name, key)
+    }
+
+    pub fn put<K: Serialize, V: Serialize>(&self, table_name: &str, key: &K, value: &V) -> Result<(), ClientError> {
+        self.client.tx_put(self.tx_id, table_name, key, value)
+    }
+
+    pub fn remove<K: Serialize>(&self, table_name: &str, key: &K) -> Result<bool, ClientError> {
+        self.client.tx_remove(self.tx_id, table_name, key)
+    }
+
+    pub fn scan<K: Deserialize, V: Deserialize>(&self, table_name: &str, start: u32, count: u32) -> Result<Vec<(K, V)>, ClientError> {
+        self.client.tx_scan(self.tx_id, table_name, start, count)
+    }
+
+    pub fn commit(mut self) -> Result<(), ClientError> {
+        self.finished = true;
+        let tx_id = self.tx_id;
+        self.client.call(|connection| connection.tx_commit(tx_id))
+    }
+
+    pub fn rollback(mut self) -> Result<(), ClientError> {
+        self.finished = true;
+        let tx_id = self.tx_id;
+        self.client.call(|connection| connection.tx_rollback(tx_id))
+    }
+}
+
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let tx_id = self.tx_id;
+            if let Err(err) = self.client.call(|connection| connection.tx_rollback(tx_id)) {
+                error!("Rollback of dropped tx {} failed: {}", tx_id, err);
+            }
+        }
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> JsonValue {
+    serde_json::to_value(value)
+}
+
+fn from_json<T: Deserialize>(json: JsonValue) -> Result<T, ClientError> {
+    serde_json::from_value(json.clone()).map_err(|err| ClientError::Serialization(format!("Cannot read {}: {}", json, err)))
+}
@@ -1,21 +0,0 @@
-extern crate iron;
-
-use rustless::Extensible;
-use rustless;
-
-use data_base::DataBaseManager;
-
-pub struct AppDataBase;
-impl iron::typemap::Key for AppDataBase {
-    type Value = DataBaseManager;
-}
-
-pub trait DataBaseExtension: rustless::Extensible {
-    fn get_data_base_manager(&self) -> &DataBaseManager;
-}
-
-impl DataBaseExtension for rustless::Application {
-    fn get_data_base_manager(&self) -> &DataBaseManager {
-        self.ext().get::<AppDataBase>().unwrap()
-    }
-}
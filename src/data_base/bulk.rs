@@ -0,0 +1,289 @@
+// Bulk import and export of tables as JSON lines of {"key": .., "value": ..}
+// or as CSV with header of field names
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use data_base::json::{JsonValue, ToJson};
+use data_base::meta::{TableDescription, TypeDescription};
+use data_base::IoEntityError;
+
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkFormat {
+    JsonLines,
+    Csv,
+}
+
+// Row, that was not imported, line is counted from 1
+#[derive(Debug)]
+pub struct RejectedRow {
+    pub line: usize,
+    pub error: IoEntityError,
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub batches: usize, // committed transactions
+    pub rejected: Vec<RejectedRow>,
+}
+
+// Column of CSV is field of key or of value
+struct Column {
+    key: bool,
+    name: String,
+    type_desc: Arc<Box<TypeDescription>>,
+}
+
+/** Reader of key/value pairs. Errors of rows are returned with rows,
+ * errors of input and of CSV header stop reading */
+pub struct RowReader<'a, R: BufRead> {
+    input: R,
+    format: BulkFormat,
+    description: &'a TableDescription,
+    line: usize,
+    columns: Option<Vec<Column>>,
+}
+
+impl BulkFormat {
+    pub fn from_str(format: &str) -> Result<BulkFormat, String> {
+        match format {
+            "jsonl" => Ok(BulkFormat::JsonLines),
+            "csv" => Ok(BulkFormat::Csv),
+            _ => Err(format!("Unknown format {}, expected jsonl or csv", format)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BulkFormat::JsonLines => "jsonl",
+            BulkFormat::Csv => "csv",
+        }
+    }
+}
+
+impl ImportReport {
+    pub fn new() -> ImportReport {
+        ImportReport {
+            imported: 0,
+            batches: 0,
+            rejected: Vec::new(),
+        }
+    }
+}
+
+impl ToJson for ImportReport {
+    fn to_json(&self) -> JsonValue {
+        let rejected = self.rejected
+            .iter()
+            .map(|row| {
+                let mut res = BTreeMap::new();
+                res.insert(String::from("line"), JsonValue::U64(row.line as u64));
+                res.insert(String::from("error"), JsonValue::String(row.error.to_string()));
+                JsonValue::Object(res)
+            })
+            .collect();
+        let mut res = BTreeMap::new();
+        res.insert(String::from("imported"), JsonValue::U64(self.imported as u64));
+        res.insert(String::from("batches"), JsonValue::U64(self.batches as u64));
+        res.insert(String::from("rejected"), JsonValue::Array(rejected));
+        JsonValue::Object(res)
+    }
+}
+
+impl<'a, R: BufRead> RowReader<'a, R> {
+    pub fn new(input: R, format: BulkFormat, description: &'a TableDescription) -> RowReader<'a, R> {
+        RowReader {
+            input: input,
+            format: format,
+            description: description,
+            line: 0,
+            columns: None,
+        }
+    }
+
+    /** Next not empty row
+     * return - line of row and key with value, None at end of input */
+    pub fn next_row(&mut self) -> Result<Option<(usize, Result<(JsonValue, JsonValue), IoEntityError>)>, IoEntityError> {
+        loop {
+            let mut text = String::new();
+            let read = try!(self.input
+                .read_line(&mut text)
+                .map_err(|err| IoEntityError::Read(format!("Cannot read line {}: {}", self.line + 1, err))));
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let row = text.trim_right_matches(|symbol: char| symbol == '\n' || symbol == '\r');
+            if row.trim().is_empty() {
+                continue;
+            }
+            match self.format {
+                BulkFormat::JsonLines => return Ok(Some((self.line, json_line_row(row)))),
+                BulkFormat::Csv => {
+                    match self.columns {
+                        Some(ref columns) => return Ok(Some((self.line, csv_row(row, columns)))),
+                        None => (),
+                    }
+                    // First row of CSV is header
+                    self.columns = Some(try!(read_header(row, self.description)
+                        .map_err(|err| IoEntityError::Read(format!("Wrong header in line {}: {}", self.line, err)))));
+                }
+            }
+        }
+    }
+}
+
+fn json_line_row(row: &str) -> Result<(JsonValue, JsonValue), IoEntityError> {
+    let json = try!(JsonValue::from_str(row).map_err(|err| IoEntityError::Read(format!("Wrong json: {}", err))));
+    match (json.find("key"), json.find("value")) {
+        (Some(key), Some(value)) => Ok((key.clone(), value.clone())),
+        _ => Err(IoEntityError::Read(String::from("Properties key and value not found"))),
+    }
+}
+
+// Column name is field name or key.field and value.field, if key and value have the same field
+fn read_header(row: &str, description: &TableDescription) -> Result<Vec<Column>, String> {
+    let mut columns: Vec<Column> = Vec::new();
+    for name in try!(split_csv(row)) {
+        let (key, field) = if name.starts_with("key.") {
+            (true, &name[4..])
+        } else if name.starts_with("value.") {
+            (false, &name[6..])
+        } else {
+            (description.key.fields.contains_key(&name), &name[..])
+        };
+        let entity = if key { &description.key } else { &description.value };
+        let type_desc = try!(entity.fields
+            .get(field)
+            .cloned()
+            .ok_or(format!("Column {} is not field of table {}", name, description.name)));
+        columns.push(Column {
+            key: key,
+            name: String::from(field),
+            type_desc: type_desc,
+        });
+    }
+    for &(key, entity) in &[(true, &description.key), (false, &description.value)] {
+        for field in entity.fields.keys() {
+            if !columns.iter().any(|column| column.key == key && column.name == *field) {
+                return Err(format!("Column of field {} not found", field));
+            }
+        }
+    }
+    Ok(columns)
+}
+
+fn csv_row(row: &str, columns: &[Column]) -> Result<(JsonValue, JsonValue), IoEntityError> {
+    let cells = try!(split_csv(row).map_err(|err| IoEntityError::Read(err)));
+    if cells.len() != columns.len() {
+        return Err(IoEntityError::Read(format!("Expected {} cells, found {}", columns.len(), cells.len())));
+    }
+    let mut key = BTreeMap::new();
+    let mut value = BTreeMap::new();
+    for (column, cell) in columns.iter().zip(cells.iter()) {
+        let fields = if column.key { &mut key } else { &mut value };
+        fields.insert(column.name.clone(), cell_to_json(cell, &column.type_desc));
+    }
+    Ok((JsonValue::Object(key), JsonValue::Object(value)))
+}
+
+// Cell is string, if type reads it as string, else json. Value is checked by reader of type on put
+fn cell_to_json(cell: &str, type_desc: &TypeDescription) -> JsonValue {
+    let string = JsonValue::String(String::from(cell));
+    if (type_desc.reader)(&string).is_ok() {
+        return string;
+    }
+    JsonValue::from_str(cell).unwrap_or(string)
+}
+
+/** Split row of CSV by commas. Cells with commas and quotes are quoted, quote in cell is doubled.
+ * Cells with line breaks are not supported */
+pub fn split_csv(row: &str) -> Result<Vec<String>, String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut symbols = row.chars().peekable();
+    while let Some(symbol) = symbols.next() {
+        match symbol {
+            '"' if quoted => {
+                if symbols.peek() == Some(&'"') {
+                    symbols.next();
+                    cell.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if cell.is_empty() => quoted = true,
+            ',' if !quoted => cells.push(::std::mem::replace(&mut cell, String::new())),
+            symbol => cell.push(symbol),
+        }
+    }
+    if quoted {
+        return Err(String::from("Quote of cell is not closed"));
+    }
+    cells.push(cell);
+    Ok(cells)
+}
+
+fn quote_csv(cell: &str) -> String {
+    if cell.contains(|symbol: char| symbol == ',' || symbol == '"' || symbol == '\n' || symbol == '\r') || cell.trim() != cell {
+        format!("\"{}\"", cell.replace("\"", "\"\""))
+    } else {
+        String::from(cell)
+    }
+}
+
+fn csv_cell(json: Option<&JsonValue>) -> String {
+    match json {
+        Some(&JsonValue::String(ref string)) => quote_csv(string),
+        Some(&JsonValue::Null) | None => String::new(),
+        Some(json) => quote_csv(&json.to_string()),
+    }
+}
+
+/** Write pairs of key and value. CSV has header with fields of key and then of value,
+ * field of value with name of key field is written as value.field */
+pub fn write_rows<W: Write>(output: &mut W,
+                            format: &BulkFormat,
+                            description: &TableDescription,
+                            rows: &[(JsonValue, JsonValue)])
+                            -> Result<(), IoEntityError> {
+    let write_error = |err: ::std::io::Error| IoEntityError::Write(format!("Cannot write row: {}", err));
+    match *format {
+        BulkFormat::JsonLines => {
+            for &(ref key, ref value) in rows {
+                let mut row = BTreeMap::new();
+                row.insert(String::from("key"), key.clone());
+                row.insert(String::from("value"), value.clone());
+                try!(writeln!(output, "{}", JsonValue::Object(row)).map_err(&write_error));
+            }
+        }
+        BulkFormat::Csv => {
+            let key_fields: Vec<&String> = description.key.fields.keys().collect();
+            let value_fields: Vec<&String> = description.value.fields.keys().collect();
+            let header: Vec<String> = key_fields.iter()
+                .map(|field| quote_csv(field))
+                .chain(value_fields.iter().map(|field| {
+                    if description.key.fields.contains_key(*field) {
+                        quote_csv(&format!("value.{}", field))
+                    } else {
+                        quote_csv(field)
+                    }
+                }))
+                .collect();
+            try!(writeln!(output, "{}", header.join(",")).map_err(&write_error));
+            for &(ref key, ref value) in rows {
+                let cells: Vec<String> = key_fields.iter()
+                    .map(|field| csv_cell(key.find(field)))
+                    .chain(value_fields.iter().map(|field| csv_cell(value.find(field))))
+                    .collect();
+                try!(writeln!(output, "{}", cells.join(",")).map_err(&write_error));
+            }
+        }
+    }
+    Ok(())
+}
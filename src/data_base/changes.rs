@@ -0,0 +1,189 @@
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::io::Read;
+use std::sync::{Arc, Mutex, Condvar};
+use std::time::{Duration, Instant};
+
+use data_base::PersistenceError;
+use data_base::json::{JsonValue, ToJson};
+
+pub const DEFAULT_CHANGE_LOG_CAPACITY: usize = 10000;
+const STREAM_BATCH_SIZE: usize = 100;
+const STREAM_KEEP_ALIVE_SECS: u64 = 15;
+
+// Change of single entity, made by committed transaction
+#[derive(Debug, Clone)]
+pub struct ChangeRecord {
+    pub seq: u64, // commit sequence number, unique and increasing for all tables
+    pub tx_id: u64,
+    pub table_name: String,
+    pub key: JsonValue,
+    pub old_value: Option<JsonValue>, // none - entity was created by transaction
+    pub new_value: Option<JsonValue>, // none - tombstone, entity was removed by transaction
+}
+
+struct ChangeBuffer {
+    records: VecDeque<ChangeRecord>,
+    last_seq: u64,
+}
+
+// In memory ring buffer of last committed changes
+pub struct ChangeLog {
+    capacity: usize,
+    buffer: Mutex<ChangeBuffer>,
+    condition: Condvar,
+}
+
+// Server-sent events representation of change log, that blocks until new changes arrive
+pub struct ChangeEventStream {
+    change_log: Arc<ChangeLog>,
+    last_seq: u64,
+    pending: Vec<u8>,
+}
+
+impl ToJson for ChangeRecord {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("seq"), JsonValue::U64(self.seq));
+        res.insert(String::from("tx_id"), JsonValue::U64(self.tx_id));
+        res.insert(String::from("table"), JsonValue::String(self.table_name.clone()));
+        res.insert(String::from("key"), self.key.clone());
+        res.insert(String::from("old_value"), self.old_value.clone().unwrap_or(JsonValue::Null));
+        res.insert(String::from("new_value"), self.new_value.clone().unwrap_or(JsonValue::Null));
+        JsonValue::Object(res)
+    }
+}
+
+impl ChangeLog {
+    pub fn new(capacity: usize) -> ChangeLog {
+        ChangeLog {
+            capacity: capacity,
+            buffer: Mutex::new(ChangeBuffer {
+                records: VecDeque::with_capacity(capacity),
+                last_seq: 0,
+            }),
+            condition: Condvar::new(),
+        }
+    }
+
+    /** Append changes of one transaction, records get sequential numbers
+     * return - appended records */
+    pub fn append(&self, tx_id: u64, changes: Vec<(String, JsonValue, Option<JsonValue>, Option<JsonValue>)>) -> Vec<ChangeRecord> {
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut appended = Vec::with_capacity(changes.len());
+        for (table_name, key, old_value, new_value) in changes {
+            buffer.last_seq += 1;
+            let seq = buffer.last_seq;
+            if buffer.records.len() >= self.capacity {
+                buffer.records.pop_front();
+            }
+            let record = ChangeRecord {
+                seq: seq,
+                tx_id: tx_id,
+                table_name: table_name,
+                key: key,
+                old_value: old_value,
+                new_value: new_value,
+            };
+            buffer.records.push_back(record.clone());
+            appended.push(record);
+        }
+        trace!("Change log last seq = {}, size = {}",
+               buffer.last_seq,
+               buffer.records.len());
+        self.condition.notify_all();
+        appended
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.buffer.lock().unwrap().last_seq
+    }
+
+    // Sequence number of oldest record, that still kept in buffer
+    pub fn first_seq(&self) -> u64 {
+        let buffer = self.buffer.lock().unwrap();
+        buffer.records.front().map(|record| record.seq).unwrap_or(buffer.last_seq + 1)
+    }
+
+    /** Records with sequence number greater than since.
+     * Fails with ChangesEvicted, if some of them were already removed from buffer */
+    pub fn since(&self, since: u64, limit: usize) -> Result<Vec<ChangeRecord>, PersistenceError> {
+        let buffer = self.buffer.lock().unwrap();
+        ChangeLog::select(&buffer, since, limit)
+    }
+
+    // Like since, but waits for new records up to timeout, if there are no records yet
+    pub fn wait_since(&self, since: u64, limit: usize, timeout: Duration) -> Result<Vec<ChangeRecord>, PersistenceError> {
+        let deadline = Instant::now() + timeout;
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.last_seq <= since {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(vec![]);
+            }
+            buffer = self.condition.wait_timeout(buffer, deadline - now).unwrap().0;
+        }
+        ChangeLog::select(&buffer, since, limit)
+    }
+
+    fn select(buffer: &ChangeBuffer, since: u64, limit: usize) -> Result<Vec<ChangeRecord>, PersistenceError> {
+        if let Some(first) = buffer.records.front() {
+            if since.saturating_add(1) < first.seq {
+                return Err(PersistenceError::ChangesEvicted(first.seq));
+            }
+        }
+        Ok(buffer.records
+            .iter()
+            .skip_while(|record| record.seq <= since)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+impl ChangeEventStream {
+    pub fn new(change_log: Arc<ChangeLog>, since: u64) -> ChangeEventStream {
+        ChangeEventStream {
+            change_log: change_log,
+            last_seq: since,
+            pending: Vec::new(),
+        }
+    }
+
+    fn fill_pending(&mut self) {
+        let records = match self.change_log.wait_since(self.last_seq,
+                                                       STREAM_BATCH_SIZE,
+                                                       Duration::from_secs(STREAM_KEEP_ALIVE_SECS)) {
+            Ok(records) => records,
+            Err(PersistenceError::ChangesEvicted(first_seq)) => {
+                // Consumer missed evicted changes, it must reload state and continue from oldest kept record
+                self.last_seq = first_seq - 1;
+                let event = format!("id: {}\nevent: reset\ndata: {{\"first_seq\":{}}}\n\n", self.last_seq, first_seq);
+                self.pending.extend_from_slice(event.as_bytes());
+                return;
+            }
+            Err(_) => vec![],
+        };
+        if records.is_empty() {
+            self.pending.extend_from_slice(b": keep-alive\n\n");
+        }
+        for record in records {
+            self.last_seq = record.seq;
+            let event = format!("id: {}\nevent: change\ndata: {}\n\n", record.seq, record.to_json());
+            self.pending.extend_from_slice(event.as_bytes());
+        }
+    }
+}
+
+impl Read for ChangeEventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            self.fill_pending();
+        }
+        let size = cmp::min(buf.len(), self.pending.len());
+        buf[..size].copy_from_slice(&self.pending[..size]);
+        self.pending.drain(..size);
+        Ok(size)
+    }
+}
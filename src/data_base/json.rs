@@ -0,0 +1,11 @@
+use std::collections::BTreeMap;
+
+// Json of data base is value of serde_json, so data base doesn't depend on web framework
+pub use serde_json::Value as JsonValue;
+
+pub type Object = BTreeMap<String, JsonValue>;
+
+// Json representation of data base structures
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
@@ -4,16 +4,19 @@ use std::sync::Arc;
 use std::boxed::Box;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use rustless::json::ToJson;
-use rustless;
+use data_base::json::{JsonValue, ToJson};
 
 use data_base::IoEntityError;
+use data_base::transaction::LockMode;
+
+pub const JSON_SCHEMA_VERSION: &'static str = "http://json-schema.org/draft-04/schema#";
 
 // Type trait, that allow define user type
 pub struct TypeDescription {
     pub name: String,
-    pub reader: Box<Fn(&rustless::json::JsonValue) -> Result<Vec<u8>, IoEntityError>>,
-    pub writer: Box<Fn(&Vec<u8>) -> Result<rustless::json::JsonValue, IoEntityError>>,
+    pub reader: Box<Fn(&JsonValue) -> Result<Vec<u8>, IoEntityError> + Send + Sync>,
+    pub writer: Box<Fn(&Vec<u8>) -> Result<JsonValue, IoEntityError> + Send + Sync>,
+    pub schema: JsonValue, // JSON Schema of json, that is accepted by reader
 }
 
 // Universal description of some entity. For example: key or value
@@ -30,19 +33,21 @@ pub struct TableDescription {
     pub name: String,
     pub key: EntityDescription,
     pub value: EntityDescription,
+    pub lock_mode: LockMode, // lock mode of implicit transactions for single operations
 }
 
 // For getting from frontend
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EntityDescriptionView {
     pub fields: BTreeMap<String, String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TableDescriptionView {
     pub name: String,
     pub key: EntityDescriptionView,
     pub value: EntityDescriptionView,
+    pub lock_mode: Option<LockMode>,
 }
 
 // TypeDescription impl
@@ -50,13 +55,17 @@ unsafe impl Send for TypeDescription {}
 unsafe impl Sync for TypeDescription {}
 
 impl ToJson for TypeDescription {
-    fn to_json(&self) -> rustless::json::JsonValue {
-        rustless::json::to_value(self.name.clone())
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.name.clone())
     }
 }
 
 impl EntityDescriptionView {
-    fn from_json(json: &BTreeMap<String, rustless::json::JsonValue>) -> Result<EntityDescriptionView, IoEntityError> {
+    pub fn new(fields: &[(&str, &str)]) -> EntityDescriptionView {
+        EntityDescriptionView { fields: fields.iter().map(|&(name, type_name)| (String::from(name), String::from(type_name))).collect() }
+    }
+
+    fn from_json(json: &BTreeMap<String, JsonValue>) -> Result<EntityDescriptionView, IoEntityError> {
         match json.get("fields") {
             Some(value) => {
                 match value.as_object() {
@@ -79,24 +88,48 @@ impl EntityDescriptionView {
 }
 
 impl TableDescriptionView {
-    pub fn from_json(json: &rustless::json::JsonValue) -> Result<TableDescriptionView, IoEntityError> {
+    /** Description of table by pairs of field name and type name */
+    pub fn new(name: &str, key: &[(&str, &str)], value: &[(&str, &str)]) -> TableDescriptionView {
+        TableDescriptionView {
+            name: String::from(name),
+            key: EntityDescriptionView::new(key),
+            value: EntityDescriptionView::new(value),
+            lock_mode: None,
+        }
+    }
+
+    pub fn from_json(json: &JsonValue) -> Result<TableDescriptionView, IoEntityError> {
         let name = try!(json.find("name").and_then(|name| name.as_str()).ok_or(IoEntityError::Read(String::from("Table name not found"))));
         trace!("Reading table with name = {}", name);
         let key = try!(EntityDescriptionView::from_json(json.find("key").unwrap().as_object().unwrap()));
         let value = try!(EntityDescriptionView::from_json(json.find("value").unwrap().as_object().unwrap()));
+        let lock_mode = try!(match json.find("lock_mode") {
+            Some(lock_mode) => {
+                lock_mode.as_str()
+                    .ok_or(IoEntityError::Read(format!("Lock mode {} is not a string", lock_mode)))
+                    .and_then(|lock_mode| LockMode::from_str(lock_mode).map_err(|error| IoEntityError::Read(error)))
+                    .and_then(|lock_mode| match lock_mode {
+                        // Implicit transactions of table must be able to write
+                        LockMode::ReadOnly => Err(IoEntityError::Read(String::from("Lock mode of table cannot be read_only"))),
+                        lock_mode => Ok(Some(lock_mode)),
+                    })
+            }
+            None => Ok(None),
+        });
         trace!("Table description {} succefully readed", name);
         Ok(TableDescriptionView {
             name: String::from(name),
             key: key,
             value: value,
+            lock_mode: lock_mode,
         })
     }
 }
 
 // EntityDescription impl
 impl ToJson for EntityDescription {
-    fn to_json(&self) -> rustless::json::JsonValue {
-        rustless::json::JsonValue::Object(self.fields
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Object(self.fields
             .iter()
             .map(|(k, v)| (k.clone(), v.to_json()))
             .collect())
@@ -104,6 +137,21 @@ impl ToJson for EntityDescription {
 }
 
 impl EntityDescription {
+    /** JSON Schema of object with all fields of entity */
+    pub fn json_schema(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("type"), JsonValue::String(String::from("object")));
+        res.insert(String::from("properties"),
+                   JsonValue::Object(self.fields
+                       .iter()
+                       .map(|(name, type_desc)| (name.clone(), type_desc.schema.clone()))
+                       .collect()));
+        res.insert(String::from("required"),
+                   JsonValue::Array(self.fields.keys().map(|name| JsonValue::String(name.clone())).collect()));
+        res.insert(String::from("additionalProperties"), JsonValue::Bool(false));
+        JsonValue::Object(res)
+    }
+
     pub fn get_field_id(&self, name: &String) -> Option<&u16> {
         self.reverse_ids_map.get(name)
     }
@@ -190,17 +238,34 @@ impl EntityDescription {
 
 // TableDescription impl
 impl ToJson for TableDescription {
-    fn to_json(&self) -> rustless::json::JsonValue {
-        let mut res = BTreeMap::<String, rustless::json::JsonValue>::new();
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
         res.insert(String::from("name"),
-                   rustless::json::to_value(self.name.clone()));
+                   JsonValue::String(self.name.clone()));
         res.insert(String::from("key"), self.key.to_json());
         res.insert(String::from("value"), self.value.to_json());
-        rustless::json::JsonValue::Object(res)
+        res.insert(String::from("lock_mode"),
+                   JsonValue::String(String::from(self.lock_mode.name())));
+        JsonValue::Object(res)
     }
 }
 
 impl TableDescription {
+    // JSON Schema of {"key": .., "value": ..}, as entities are put and imported
+    pub fn json_schema(&self) -> JsonValue {
+        let mut properties = BTreeMap::<String, JsonValue>::new();
+        properties.insert(String::from("key"), self.key.json_schema());
+        properties.insert(String::from("value"), self.value.json_schema());
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("$schema"), JsonValue::String(String::from(JSON_SCHEMA_VERSION)));
+        res.insert(String::from("title"), JsonValue::String(self.name.clone()));
+        res.insert(String::from("type"), JsonValue::String(String::from("object")));
+        res.insert(String::from("properties"), JsonValue::Object(properties));
+        res.insert(String::from("required"),
+                   JsonValue::Array(vec![JsonValue::String(String::from("key")), JsonValue::String(String::from("value"))]));
+        JsonValue::Object(res)
+    }
+
     pub fn from_view(view: &TableDescriptionView, type_descs: &BTreeMap<String, Arc<Box<TypeDescription>>>) -> Result<TableDescription, String> {
         let key_desc = try!(EntityDescription::from_view(&view.key, type_descs).map_err(|error| format!("Cannot read key description: {}", error)));
         let value_desc = try!(EntityDescription::from_view(&view.value, type_descs)
@@ -209,6 +274,7 @@ impl TableDescription {
             name: view.name.clone(),
             key: key_desc,
             value: value_desc,
+            lock_mode: view.lock_mode.clone().unwrap_or(LockMode::Pessimistic),
         })
     }
 }
@@ -0,0 +1,236 @@
+// Counters and latency histograms of data base, rendered in Prometheus text format.
+// Metrics are updated by atomics, so they don't add locks to operations
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Upper bounds of buckets in seconds
+const OPERATION_BUCKETS: &'static [f64] = &[0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+const LOCK_WAIT_BUCKETS: &'static [f64] = &[0.001, 0.01, 0.1, 1.0, 10.0, 60.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Get,
+    Put,
+    Remove,
+    List,
+}
+
+pub const OPERATIONS: [Operation; 4] = [Operation::Get, Operation::Put, Operation::Remove, Operation::List];
+
+/** Histogram with fixed buckets, count of bucket doesn't include lower buckets */
+pub struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<AtomicUsize>, // last count is of +Inf bucket
+    sum_micros: AtomicUsize,
+}
+
+struct OperationMetrics {
+    count: AtomicUsize,
+    errors: AtomicUsize,
+    latency: Histogram,
+}
+
+// Committed entities of table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub name: String,
+    pub entries: usize,
+    pub bytes: usize, // approximate size of encoded keys and values
+}
+
+pub struct Metrics {
+    operations: Vec<OperationMetrics>, // in order of OPERATIONS
+    tx_started: AtomicUsize,
+    tx_committed: AtomicUsize,
+    tx_rolled_back: AtomicUsize,
+    tx_failed: AtomicUsize, // commit or rollback failed
+    lock_failures: AtomicUsize, // optimistic transaction found key locked
+    lock_wait: Histogram, // waits of pessimistic transactions for locked keys
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Operation::Get => "get",
+            Operation::Put => "put",
+            Operation::Remove => "remove",
+            Operation::List => "list",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match *self {
+            Operation::Get => 0,
+            Operation::Put => 1,
+            Operation::Remove => 2,
+            Operation::List => 3,
+        }
+    }
+}
+
+fn micros(duration: Duration) -> usize {
+    (duration.as_secs() * 1000000 + (duration.subsec_nanos() / 1000) as u64) as usize
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Histogram {
+        Histogram {
+            bounds: bounds,
+            counts: (0..bounds.len() + 1).map(|_| AtomicUsize::new(0)).collect(),
+            sum_micros: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let micros = micros(duration);
+        let secs = micros as f64 / 1000000.0;
+        let index = self.bounds.iter().position(|bound| secs <= *bound).unwrap_or(self.bounds.len());
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> usize {
+        self.counts.iter().map(|count| count.load(Ordering::Relaxed)).sum()
+    }
+
+    // Cumulative buckets, sum and count of histogram with labels, for example operation="get"
+    fn render(&self, name: &str, labels: &str, out: &mut String) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        let mut cumulative = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            cumulative += count.load(Ordering::Relaxed);
+            let bound = self.bounds.get(index).map(|bound| bound.to_string()).unwrap_or(String::from("+Inf"));
+            let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, bound, cumulative);
+        }
+        let labels = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum_micros.load(Ordering::Relaxed) as f64 / 1000000.0);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, cumulative);
+    }
+}
+
+fn header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+}
+
+// Quotes, backslashes and line feeds are escaped in values of labels
+fn label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            operations: OPERATIONS.iter()
+                .map(|_| {
+                    OperationMetrics {
+                        count: AtomicUsize::new(0),
+                        errors: AtomicUsize::new(0),
+                        latency: Histogram::new(OPERATION_BUCKETS),
+                    }
+                })
+                .collect(),
+            tx_started: AtomicUsize::new(0),
+            tx_committed: AtomicUsize::new(0),
+            tx_rolled_back: AtomicUsize::new(0),
+            tx_failed: AtomicUsize::new(0),
+            lock_failures: AtomicUsize::new(0),
+            lock_wait: Histogram::new(LOCK_WAIT_BUCKETS),
+        }
+    }
+
+    pub fn observe_operation(&self, operation: Operation, duration: Duration, success: bool) {
+        let metrics = &self.operations[operation.index()];
+        metrics.count.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            metrics.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        metrics.latency.observe(duration);
+    }
+
+    pub fn operation_count(&self, operation: Operation) -> usize {
+        self.operations[operation.index()].count.load(Ordering::Relaxed)
+    }
+
+    pub fn transaction_started(&self) {
+        self.tx_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn transaction_committed(&self) {
+        self.tx_committed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn transaction_rolled_back(&self) {
+        self.tx_rolled_back.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn transaction_failed(&self) {
+        self.tx_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn lock_failed(&self) {
+        self.lock_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn observe_lock_wait(&self, duration: Duration) {
+        self.lock_wait.observe(duration);
+    }
+
+    pub fn lock_wait_count(&self) -> usize {
+        self.lock_wait.count()
+    }
+
+    /** All metrics in Prometheus text format 0.0.4 */
+    pub fn render(&self, tables: &[TableStats], active_transactions: usize) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "nimdg_table_entries", "gauge", "Committed entities of table");
+        for table in tables {
+            let _ = writeln!(out, "nimdg_table_entries{{table=\"{}\"}} {}", label_value(&table.name), table.entries);
+        }
+        header(&mut out, "nimdg_table_bytes", "gauge", "Approximate size of encoded keys and values of table");
+        for table in tables {
+            let _ = writeln!(out, "nimdg_table_bytes{{table=\"{}\"}} {}", label_value(&table.name), table.bytes);
+        }
+
+        header(&mut out, "nimdg_operations_total", "counter", "Operations with entities by type");
+        for operation in OPERATIONS.iter() {
+            let _ = writeln!(out,
+                             "nimdg_operations_total{{operation=\"{}\"}} {}",
+                             operation.name(),
+                             self.operations[operation.index()].count.load(Ordering::Relaxed));
+        }
+        header(&mut out, "nimdg_operation_errors_total", "counter", "Failed operations with entities by type");
+        for operation in OPERATIONS.iter() {
+            let _ = writeln!(out,
+                             "nimdg_operation_errors_total{{operation=\"{}\"}} {}",
+                             operation.name(),
+                             self.operations[operation.index()].errors.load(Ordering::Relaxed));
+        }
+        header(&mut out, "nimdg_operation_duration_seconds", "histogram", "Latency of operations with entities");
+        for operation in OPERATIONS.iter() {
+            self.operations[operation.index()]
+                .latency
+                .render("nimdg_operation_duration_seconds", &format!("operation=\"{}\"", operation.name()), &mut out);
+        }
+
+        let transactions = [("started", &self.tx_started, "Started transactions"),
+                            ("committed", &self.tx_committed, "Committed transactions"),
+                            ("rolled_back", &self.tx_rolled_back, "Rolled back transactions"),
+                            ("failed", &self.tx_failed, "Transactions, whose commit or rollback failed")];
+        for &(name, counter, help) in transactions.iter() {
+            let name = format!("nimdg_transactions_{}_total", name);
+            header(&mut out, &name, "counter", help);
+            let _ = writeln!(out, "{} {}", name, counter.load(Ordering::Relaxed));
+        }
+        header(&mut out, "nimdg_active_transactions", "gauge", "Running transactions");
+        let _ = writeln!(out, "nimdg_active_transactions {}", active_transactions);
+
+        header(&mut out, "nimdg_lock_failures_total", "counter", "Locks, that failed because key was locked by other transaction");
+        let _ = writeln!(out, "nimdg_lock_failures_total {}", self.lock_failures.load(Ordering::Relaxed));
+        header(&mut out, "nimdg_lock_wait_seconds", "histogram", "Waits of pessimistic transactions for locked keys");
+        self.lock_wait.render("nimdg_lock_wait_seconds", "", &mut out);
+        out
+    }
+}
@@ -4,34 +4,53 @@ extern crate chrono;
 
 use std;
 use std::hash::{Hash, Hasher};
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use std::boxed::Box;
 use std::fmt::{Debug, Display};
 use std::sync::Mutex;
+use std::sync::mpsc::Receiver;
 use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
 
 use concurrent_hashmap::*;
 
 use bincode::rustc_serialize::{encode, decode};
 
-use rustless;
-use rustless::json::ToJson;
+use data_base::json::{JsonValue, ToJson};
 
-pub mod app_extension;
+pub mod bulk;
+pub mod changes;
+pub mod json;
 pub mod meta;
+pub mod metrics;
+pub mod prepared;
+pub mod processor;
+pub mod query;
 pub mod transaction;
+pub mod typed;
 
 use data_base::meta::{TypeDescription, EntityDescription, TableDescription, TableDescriptionView};
-use data_base::transaction::{Transaction, TransactionManager, Lock, LockType, LockMode};
+use data_base::transaction::{Transaction, TransactionManager, Lock, LockMode, IsolationLevel, RangeLock, DEFAULT_TX_ID};
+use data_base::processor::EntryProcessor;
+use data_base::changes::{ChangeLog, ChangeRecord};
+use data_base::prepared::PreparedLog;
+use data_base::query::{ContinuousQuery, ContinuousQueryManager, QueryEvent, QueryEventType};
+use data_base::typed::TypedTable;
+use data_base::bulk::{BulkFormat, ImportReport, RejectedRow, RowReader};
+use data_base::metrics::{Metrics, Operation, TableStats};
 
 use self::chrono::prelude::*;
 
+const BATCH_SAVEPOINT: &'static str = "__nimdg_batch"; // internal savepoint of atomic batch
 
 // Top struct for interaction with tables
 pub struct DataBaseManager {
     type_descriptions: BTreeMap<String, Arc<Box<TypeDescription>>>,
-    table_descriptions: ConcHashMap<String, TableDescription>,
     tables: ConcHashMap<String, Arc<Table>>,
     tx_manager: Arc<TransactionManager>,
 }
@@ -54,6 +73,7 @@ pub struct Table {
     description: TableDescription,
     data: ConcHashMap<Entity, Arc<Mutex<Entity>>>,
     tx_manager: Arc<TransactionManager>,
+    range_lock: Arc<RangeLock>, // phantom protection for serializable transactions
 }
 
 // Errors
@@ -69,10 +89,23 @@ pub enum PersistenceError {
     TableNotFound(String),
     EntityNotFound(Entity),
     Undefined(String),
-    UndefinedTransaction(u32),
-    TransactionAlreadyStarted(u32),
+    UndefinedTransaction(u64),
+    TransactionAlreadyStarted(u64),
     TransactionFailed(String),
-    WrongTransaction(u32, u32), // real tx_id, expected tx_id
+    LockFailed(String), // key or range is locked by other transaction
+    LockTimeout(u64, u64), // tx_id, tx_id of holder of lock, that wasn't released in time of lock wait
+    WrongTransaction(u64, u64), // real tx_id, expected tx_id
+    ProcessorFailed(String),
+    BatchFailed(usize, Box<PersistenceError>), // index of failed item in batch, error of item
+    SavepointNotFound(String),
+    ReservedSavepoint(String), // name of savepoint is used by data base
+    ReadOnlyTransaction(u64),
+    TransactionPrepared(u64), // operation is not allowed for prepared transaction
+    TransactionNotPrepared(u64),
+    PreparedLogFailed(String),
+    TransactionKilled(u64),
+    ChangesEvicted(u64), // requested changes were removed from change log, first_seq of kept records
+    ImportFailed(usize, Box<ImportReport>, Box<PersistenceError>), // failed line, report of committed batches, error of line
 }
 
 impl Display for IoEntityError {
@@ -88,6 +121,13 @@ impl Display for PersistenceError {
 }
 
 // Field impl
+impl Entity {
+    // Data of fields with their ids
+    fn encoded_size(&self) -> usize {
+        self.fields.values().map(|field| field.data.len() + 2).sum()
+    }
+}
+
 impl PartialEq for Field {
     fn eq(&self, other: &Field) -> bool {
         self.data == other.data
@@ -101,6 +141,31 @@ impl PartialEq for Entity {
     }
 }
 
+impl PartialOrd for Field {
+    fn partial_cmp(&self, other: &Field) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Field {
+    fn cmp(&self, other: &Field) -> Ordering {
+        self.data.cmp(&other.data)
+    }
+}
+
+// Order of entities is used for deterministic order of locks
+impl PartialOrd for Entity {
+    fn partial_cmp(&self, other: &Entity) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entity {
+    fn cmp(&self, other: &Entity) -> Ordering {
+        self.fields.cmp(&other.fields)
+    }
+}
+
 impl Hash for Field {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.data.hash(state);
@@ -116,8 +181,8 @@ impl Hash for Entity {
 // Table impl
 impl Table {
     fn select_field_descriptions(description: &EntityDescription,
-                                 entity_json: &rustless::json::Object)
-                                 -> BTreeMap<String, (u16, Arc<Box<TypeDescription>>, rustless::json::JsonValue)> {
+                                 entity_json: &json::Object)
+                                 -> BTreeMap<String, (u16, Arc<Box<TypeDescription>>, JsonValue)> {
         entity_json.iter()
             .filter_map(|(name, value)| {
                 let type_desc = description.get_field(name);
@@ -129,7 +194,7 @@ impl Table {
                     None
                 }
             })
-            .collect::<BTreeMap<String, (u16, Arc<Box<TypeDescription>>, rustless::json::JsonValue)>>()
+            .collect::<BTreeMap<String, (u16, Arc<Box<TypeDescription>>, JsonValue)>>()
     }
 
     fn check_unselected_keys(unselected_json_keys: HashSet<String>, unselected_typed_keys: HashSet<String>) -> Result<(), IoEntityError> {
@@ -144,7 +209,7 @@ impl Table {
         }
     }
 
-    fn json_to_entity(json: &rustless::json::JsonValue, description: &EntityDescription) -> Result<Entity, IoEntityError> {
+    fn json_to_entity(json: &JsonValue, description: &EntityDescription) -> Result<Entity, IoEntityError> {
         if json.is_object() {
             let json_object = try!(json.as_object().ok_or(IoEntityError::Read("Json object not found".to_string())));
             // 1. select types for json fields
@@ -174,8 +239,8 @@ impl Table {
         }
     }
 
-    fn entity_to_json(entity: &Entity, entity_description: &EntityDescription) -> Result<rustless::json::JsonValue, IoEntityError> {
-        let json_object: BTreeMap<String, rustless::json::JsonValue> = try!(entity.fields
+    fn entity_to_json(entity: &Entity, entity_description: &EntityDescription) -> Result<JsonValue, IoEntityError> {
+        let json_object: BTreeMap<String, JsonValue> = try!(entity.fields
             .iter()
             .filter_map(|(type_id, value)| {
                 let field_name = entity_description.ids_map.get(type_id);
@@ -201,11 +266,11 @@ impl Table {
                 .fold(String::new(), |acc, type_id| acc + ", " + type_id.as_str());
             Err(IoEntityError::Write(format!("Not found field descriptions for some fields {}", unset)))
         } else {
-            Ok(rustless::json::JsonValue::Object(json_object))
+            Ok(JsonValue::Object(json_object))
         }
     }
 
-    pub fn put(&self, key: &rustless::json::JsonValue, value: &rustless::json::JsonValue) -> Result<(), PersistenceError> {
+    pub fn put(&self, key: &JsonValue, value: &JsonValue) -> Result<(), PersistenceError> {
         let key_entity = try!(Table::json_to_entity(key, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
         let value_entity = try!(Table::json_to_entity(value, &self.description.value).map_err(|err| PersistenceError::IoEntity(err)));
         self.data.insert(key_entity, Arc::new(Mutex::new(value_entity)));
@@ -216,40 +281,75 @@ impl Table {
         self.data.insert(key, Arc::new(Mutex::new(value)))
     }
 
+    pub fn raw_remove(&self, key: &Entity) -> Option<Arc<Mutex<Entity>>> {
+        self.data.remove(key)
+    }
+
+    /** Count of committed entities and approximate size of their encoded fields */
+    pub fn get_stats(&self) -> TableStats {
+        let mut entries = 0;
+        let mut bytes = 0;
+        for (key, value) in self.data.iter() {
+            entries += 1;
+            bytes += key.encoded_size() + value.lock().unwrap().encoded_size();
+        }
+        TableStats {
+            name: self.description.name.clone(),
+            entries: entries,
+            bytes: bytes,
+        }
+    }
+
     fn get_lock_for_get(&self,
-                        tx_id: &u32,
+                        tx_id: &u64,
                         key_entity: &Entity,
                         value_entity: Option<Arc<Mutex<Entity>>>)
                         -> Result<Option<Entity>, PersistenceError> {
         let transaction = try!(self.tx_manager.get_tx(tx_id));
         let locked_transaction = transaction.lock().unwrap();
+        if locked_transaction.is_read_only() {
+            return Ok(self.get_committed(key_entity, value_entity));
+        }
         let value_from_transaction = locked_transaction.get_locked_value(self.description.name.clone(), key_entity);
         match value_from_transaction {
+            Some(ref locked_value) if locked_value.removed => {
+                trace!("Entity with key = {} removed in tx",
+                       self.key_to_string(key_entity));
+                Ok(None)
+            }
             Some(locked_value) => {
                 trace!("Lock for key = {} already taken",
                        self.key_to_string(key_entity));
                 Ok(Some(locked_value.value.clone()))
             }
+            None if *locked_transaction.get_isolation_level() == IsolationLevel::ReadCommitted => {
+                trace!("Read committed value by key = {}",
+                       self.key_to_string(key_entity));
+                Ok(self.get_committed(key_entity, value_entity))
+            }
             None => {
                 trace!("Entity with key = {} not locked yet",
                        self.key_to_string(key_entity));
                 match value_entity {
-                    Some(value_entity) => {
-                        let locked_value = self.lock_value(tx_id, &locked_transaction, key_entity, value_entity);
-                        locked_value
-                    }
+                    Some(value_entity) => TransactionManager::lock_value(tx_id, self, &locked_transaction, key_entity, Some(value_entity)),
                     None => {
-                        match self.data.find_mut(key_entity) {
-                            Some(mut accessor) => {
-                                self.lock_value(tx_id,
-                                                &locked_transaction,
-                                                key_entity,
-                                                accessor.get().clone())
+                        // Accessor is released before lock, because lock can wait for commit of other transaction
+                        match self.data.find(key_entity).map(|accessor| accessor.get().clone()) {
+                            Some(value_entity) => {
+                                TransactionManager::lock_value(tx_id,
+                                                               self,
+                                                               &locked_transaction,
+                                                               key_entity,
+                                                               Some(value_entity))
                             }
                             None => {
                                 trace!("Not found value by key {:?} in table {}",
                                        self.key_to_string(key_entity),
                                        self.description.name);
+                                // Absence of key must stay the same until end of serializable transaction
+                                if *locked_transaction.get_isolation_level() == IsolationLevel::Serializable {
+                                    try!(locked_transaction.lock_range(&self.range_lock));
+                                }
                                 Ok(None)
                             }
                         }
@@ -259,35 +359,27 @@ impl Table {
         }
     }
 
-    fn lock_value(&self,
-                  tx_id: &u32,
-                  locked_transaction: &Transaction,
-                  key_entity: &Entity,
-                  value_entity: Arc<Mutex<Entity>>)
-                  -> Result<Option<Entity>, PersistenceError> {
-        let lock_type = Table::get_lock_type(value_entity.clone());
-        trace!("Lock type = {:?}", lock_type);
-        match lock_type {
-            LockType::Read => {
-                TransactionManager::lock_value(tx_id,
-                                               self,
-                                               &locked_transaction,
-                                               key_entity,
-                                               Some(value_entity))
-                    .map_err(|err| PersistenceError::TransactionFailed(format!("{:?}", err)))
-            }
-            LockType::Write => Ok(Some(value_entity.lock().unwrap().clone())),
-        }
+    // Last committed value, entity lock is not checked and not taken
+    fn get_committed(&self, key_entity: &Entity, value_entity: Option<Arc<Mutex<Entity>>>) -> Option<Entity> {
+        let value_entity = value_entity.or_else(|| self.data.find(key_entity).map(|accessor| accessor.get().clone()));
+        value_entity.map(|value_entity| value_entity.lock().unwrap().clone())
     }
 
-    fn get_lock_type(entity: Arc<Mutex<Entity>>) -> LockType {
-        entity.lock().unwrap().lock.lock_type.clone()
+    // Keys of in doubt transactions are locked after restart by their json
+    fn check_in_doubt(&self, tx_id: &u64, key_entity: &Entity) -> Result<(), PersistenceError> {
+        if !self.tx_manager.has_in_doubt_keys() {
+            return Ok(());
+        }
+        let key = try!(Table::entity_to_json(key_entity, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
+        self.tx_manager.check_in_doubt_key(&self.description.name, &key, tx_id)
     }
 
-    fn get_lock_for_put(&self, tx_id: &u32, key_entity: &Entity, inserted_value: Arc<Mutex<Entity>>) -> Result<Option<Entity>, PersistenceError> {
+    fn get_lock_for_put(&self, tx_id: &u64, key_entity: &Entity, inserted_value: Arc<Mutex<Entity>>) -> Result<Option<Entity>, PersistenceError> {
+        try!(self.check_in_doubt(tx_id, key_entity));
         let transaction = try!(self.tx_manager.get_tx(tx_id));
         // Try get lock on key
         let locked_transaction = transaction.lock().unwrap();
+        try!(locked_transaction.check_writable());
         let locked_value = locked_transaction.get_locked_value(self.description.name.clone(), key_entity);
         match locked_value {
             Some(value) => {
@@ -295,23 +387,30 @@ impl Table {
                        tx_id,
                        self.value_to_string(&value.value),
                        self.key_to_string(key_entity));
+                locked_transaction.update_value(self.description.name.clone(),
+                                                key_entity,
+                                                inserted_value.lock().unwrap().clone());
                 Ok(Some(value.value.clone()))
             }
             None => {
                 trace!("Entity with key = {} not locked yet",
                        self.key_to_string(key_entity));
-                match self.data.find_mut(key_entity) {
-                    Some(mut accessor) => {
-                        try!(TransactionManager::lock_value(tx_id,
-                                                            self,
-                                                            &locked_transaction,
-                                                            key_entity,
-                                                            Some(accessor.get().clone())));
-                        Ok(None)
+                match self.data.find(key_entity).map(|accessor| accessor.get().clone()) {
+                    Some(value_entity) => {
+                        let previous_value = try!(TransactionManager::lock_value(tx_id,
+                                                                                 self,
+                                                                                 &locked_transaction,
+                                                                                 key_entity,
+                                                                                 Some(value_entity)));
+                        locked_transaction.update_value(self.description.name.clone(),
+                                                        key_entity,
+                                                        inserted_value.lock().unwrap().clone());
+                        Ok(previous_value)
                     }
                     None => {
                         trace!("Tx not contains key yet. Add key {}",
                                self.key_to_string(key_entity));
+                        try!(locked_transaction.lock_insert(&self.range_lock));
                         let mut new_key_entity = key_entity.clone();
                         new_key_entity.lock.tx_id = tx_id.clone();
                         locked_transaction.add_entity(self,
@@ -326,12 +425,41 @@ impl Table {
         }
     }
 
-    fn tx_get_list_entities(&self, tx_id: u32, start: u32, count: u32) -> Result<HashMap<Entity, Entity>, PersistenceError> {
-        let res = self.data
+    // Lock key for read-modify-write, return current value in transaction
+    fn get_lock_for_update(&self, tx_id: &u64, key_entity: &Entity) -> Result<Option<Entity>, PersistenceError> {
+        try!(self.check_in_doubt(tx_id, key_entity));
+        let transaction = try!(self.tx_manager.get_tx(tx_id));
+        let locked_transaction = transaction.lock().unwrap();
+        try!(locked_transaction.check_writable());
+        match locked_transaction.get_locked_value(self.description.name.clone(), key_entity) {
+            Some(ref locked_value) if locked_value.removed => Ok(None),
+            Some(locked_value) => Ok(Some(locked_value.value)),
+            None => {
+                match self.data.find(key_entity).map(|accessor| accessor.get().clone()) {
+                    Some(value_entity) => TransactionManager::lock_value(tx_id, self, &locked_transaction, key_entity, Some(value_entity)),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    fn tx_get_list_entities(&self, tx_id: u64, start: u32, count: u32) -> Result<HashMap<Entity, Entity>, PersistenceError> {
+        {
+            let transaction = try!(self.tx_manager.get_tx(&tx_id));
+            let locked_transaction = transaction.lock().unwrap();
+            if *locked_transaction.get_isolation_level() == IsolationLevel::Serializable && !locked_transaction.is_read_only() {
+                try!(locked_transaction.lock_range(&self.range_lock));
+            }
+        }
+        // Entries are collected before locks, so iteration doesn't block commits of other transactions
+        let entries: Vec<(Entity, Arc<Mutex<Entity>>)> = self.data
             .iter()
             .skip(start as usize)
             .take(count as usize)
-            .map(|(key, value)| {
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let res = entries.iter()
+            .map(|&(ref key, ref value)| {
                 self.get_lock_for_get(&tx_id, key, Some(value.clone()))
                     .and_then(|entity: Option<Entity>| entity.ok_or(PersistenceError::EntityNotFound(key.clone())))
                     .map(|locked_value| (key.clone(), locked_value))
@@ -345,20 +473,33 @@ impl Table {
         Ok(res.collect::<HashMap<Entity, Entity>>())
     }
 
-    pub fn tx_get_list(&self, tx_id: u32, start: u32, count: u32) -> Result<Vec<rustless::json::JsonValue>, PersistenceError> {
+    pub fn tx_get_list(&self, tx_id: u64, start: u32, count: u32) -> Result<Vec<JsonValue>, PersistenceError> {
         let entities_map: HashMap<Entity, Entity> = try!(self.tx_get_list_entities(tx_id, start, count));
-        let res: Result<Vec<rustless::json::JsonValue>, PersistenceError> = entities_map.iter()
+        let res: Result<Vec<JsonValue>, PersistenceError> = entities_map.iter()
             .map(|(key, value)| {
                 Table::entity_to_json(key, &self.description.key).and_then(|key_json| {
                     Table::entity_to_json(value, &self.description.value)
-                        .map(|value_json| rustless::json::JsonValue::Array(vec![key_json, value_json]))
+                        .map(|value_json| JsonValue::Array(vec![key_json, value_json]))
                 })
             })
-            .collect::<Result<Vec<rustless::json::JsonValue>, IoEntityError>>()
+            .collect::<Result<Vec<JsonValue>, IoEntityError>>()
             .map_err(|error| PersistenceError::IoEntity(error));
         res
     }
 
+    // Last committed values, read without locks
+    pub fn committed_list(&self) -> Result<Vec<(JsonValue, JsonValue)>, PersistenceError> {
+        self.data
+            .iter()
+            .map(|(key, value)| {
+                let value = value.lock().unwrap().clone();
+                Table::entity_to_json(key, &self.description.key)
+                    .and_then(|key_json| Table::entity_to_json(&value, &self.description.value).map(|value_json| (key_json, value_json)))
+            })
+            .collect::<Result<Vec<(JsonValue, JsonValue)>, IoEntityError>>()
+            .map_err(|error| PersistenceError::IoEntity(error))
+    }
+
     fn key_to_string(&self, entity: &Entity) -> String {
         match Table::entity_to_json(entity, &self.description.key) {
             Ok(res) => res.to_string(),
@@ -373,7 +514,7 @@ impl Table {
         }
     }
 
-    fn tx_get_entity(&self, tx_id: &u32, key_entity: &Entity) -> Result<Option<Entity>, PersistenceError> {
+    fn tx_get_entity(&self, tx_id: &u64, key_entity: &Entity) -> Result<Option<Entity>, PersistenceError> {
         let locked_value: Option<Entity> = try!(self.get_lock_for_get(tx_id, key_entity, None));
         match locked_value {
             Some(value) => {
@@ -385,12 +526,12 @@ impl Table {
             None => {
                 trace!("In current tx not found value for key = {}",
                        self.key_to_string(key_entity));
-                Ok(self.data.find(key_entity).map(|data| data.get().lock().unwrap().clone()))
+                Ok(None)
             }
         }
     }
 
-    pub fn tx_get(&self, tx_id: &u32, key: &rustless::json::JsonValue) -> Result<Option<rustless::json::JsonValue>, PersistenceError> {
+    pub fn tx_get(&self, tx_id: &u64, key: &JsonValue) -> Result<Option<JsonValue>, PersistenceError> {
         let key_entity = try!(Table::json_to_entity(key, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
         let value_entity = try!(self.tx_get_entity(tx_id, &key_entity));
         value_entity.map(|value_entity| {
@@ -402,16 +543,11 @@ impl Table {
     }
 
 
-    pub fn tx_put(&self, tx_id: &u32, key: &rustless::json::JsonValue, value: &rustless::json::JsonValue) -> Result<(), PersistenceError> {
+    pub fn tx_put(&self, tx_id: &u64, key: &JsonValue, value: &JsonValue) -> Result<(), PersistenceError> {
         trace!("Tx put started");
         let key_entity: Entity = try!(Table::json_to_entity(key, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
         let value_entity = try!(Table::json_to_entity(value, &self.description.value).map_err(|err| PersistenceError::IoEntity(err)));
-        let inserted_value = Arc::new(Mutex::new(value_entity));
-        try!(self.get_lock_for_put(tx_id, &key_entity, inserted_value.clone()));
-
-        trace!("Upsert value = {} with key = {}",
-               Table::entity_to_json(&inserted_value.lock().unwrap(), &self.description.value).unwrap(),
-               Table::entity_to_json(&key_entity, &self.description.key).unwrap());
+        try!(self.tx_put_entity(tx_id, &key_entity, value_entity));
 
         // self.data.upsert(key_entity, inserted_value.clone(), &|value| *value = inserted_value.clone());
         for (k, v) in self.data.iter() {
@@ -421,13 +557,164 @@ impl Table {
         }
         Ok(())
     }
+
+    fn tx_put_entity(&self, tx_id: &u64, key_entity: &Entity, value_entity: Entity) -> Result<(), PersistenceError> {
+        let inserted_value = Arc::new(Mutex::new(value_entity));
+        try!(self.get_lock_for_put(tx_id, key_entity, inserted_value.clone()));
+
+        trace!("Upsert value = {} with key = {}",
+               self.value_to_string(&inserted_value.lock().unwrap()),
+               self.key_to_string(key_entity));
+        Ok(())
+    }
+
+    /** Remove entity by key on commit of transaction
+     * return - true, if entity existed */
+    pub fn tx_remove(&self, tx_id: &u64, key: &JsonValue) -> Result<bool, PersistenceError> {
+        let key_entity = try!(Table::json_to_entity(key, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
+        self.tx_remove_entity(tx_id, &key_entity)
+    }
+
+    fn tx_remove_entity(&self, tx_id: &u64, key_entity: &Entity) -> Result<bool, PersistenceError> {
+        let removed = match try!(self.get_lock_for_update(tx_id, key_entity)) {
+            Some(_) => {
+                let transaction = try!(self.tx_manager.get_tx(tx_id));
+                let locked_transaction = transaction.lock().unwrap();
+                locked_transaction.remove_value(self.description.name.clone(), key_entity)
+            }
+            None => false,
+        };
+        trace!("Remove key {} in tx {}, found = {}",
+               self.key_to_string(key_entity),
+               tx_id,
+               removed);
+        Ok(removed)
+    }
+
+    pub fn tx_put_all(&self,
+                      tx_id: &u64,
+                      entries: &Vec<(JsonValue, JsonValue)>,
+                      atomic: bool)
+                      -> Result<Vec<Result<(), PersistenceError>>, PersistenceError> {
+        let items = entries.iter()
+            .map(|&(ref key, ref value)| {
+                Table::json_to_entity(key, &self.description.key)
+                    .and_then(|key_entity| Table::json_to_entity(value, &self.description.value).map(|value_entity| (key_entity, value_entity)))
+                    .map_err(|err| PersistenceError::IoEntity(err))
+            })
+            .collect();
+        Table::execute_batch(items,
+                             atomic,
+                             |key_entity, value_entity| self.tx_put_entity(tx_id, key_entity, value_entity))
+    }
+
+    pub fn tx_get_all(&self,
+                      tx_id: &u64,
+                      keys: &Vec<JsonValue>,
+                      atomic: bool)
+                      -> Result<Vec<Result<Option<JsonValue>, PersistenceError>>, PersistenceError> {
+        let items = keys.iter()
+            .map(|key| {
+                Table::json_to_entity(key, &self.description.key)
+                    .map(|key_entity| (key_entity, ()))
+                    .map_err(|err| PersistenceError::IoEntity(err))
+            })
+            .collect();
+        Table::execute_batch(items, atomic, |key_entity, _| {
+            let value_entity = try!(self.tx_get_entity(tx_id, key_entity));
+            value_entity.map(|value_entity| {
+                    Table::entity_to_json(&value_entity, &self.description.value)
+                        .map(|r| Some(r))
+                        .map_err(|err| PersistenceError::IoEntity(err))
+                })
+                .unwrap_or(Ok(None))
+        })
+    }
+
+    pub fn tx_remove_all(&self,
+                         tx_id: &u64,
+                         keys: &Vec<JsonValue>,
+                         atomic: bool)
+                         -> Result<Vec<Result<bool, PersistenceError>>, PersistenceError> {
+        let items = keys.iter()
+            .map(|key| {
+                Table::json_to_entity(key, &self.description.key)
+                    .map(|key_entity| (key_entity, ()))
+                    .map_err(|err| PersistenceError::IoEntity(err))
+            })
+            .collect();
+        Table::execute_batch(items,
+                             atomic,
+                             |key_entity, _| self.tx_remove_entity(tx_id, key_entity))
+    }
+
+    /** Execute operation for each item in order of keys, so concurrent batches take locks in same order and don't deadlock.
+     * Atomic batch is failed by first failed item, else result of each item is returned in original order */
+    fn execute_batch<I, T, F>(items: Vec<Result<(Entity, I), PersistenceError>>,
+                              atomic: bool,
+                              operation: F)
+                              -> Result<Vec<Result<T, PersistenceError>>, PersistenceError>
+        where F: Fn(&Entity, I) -> Result<T, PersistenceError>
+    {
+        // Invalid atomic batch is failed before taking of any lock
+        if atomic {
+            if let Some(index) = items.iter().position(|item| item.is_err()) {
+                let error = items.into_iter().nth(index).unwrap().err().unwrap();
+                return Err(PersistenceError::BatchFailed(index, Box::new(error)));
+            }
+        }
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by(|&left, &right| match (&items[left], &items[right]) {
+            (&Ok((ref left_key, _)), &Ok((ref right_key, _))) => left_key.cmp(right_key),
+            (&Ok(_), &Err(_)) => Ordering::Less,
+            (&Err(_), &Ok(_)) => Ordering::Greater,
+            (&Err(_), &Err(_)) => Ordering::Equal,
+        });
+        let mut items: Vec<Option<Result<(Entity, I), PersistenceError>>> = items.into_iter().map(|item| Some(item)).collect();
+        let mut results: Vec<Option<Result<T, PersistenceError>>> = items.iter().map(|_| None).collect();
+        for index in order {
+            let result = match items[index].take().unwrap() {
+                Ok((key_entity, item)) => operation(&key_entity, item),
+                Err(error) => Err(error),
+            };
+            match result {
+                Err(error) if atomic => return Err(PersistenceError::BatchFailed(index, Box::new(error))),
+                result => results[index] = Some(result),
+            }
+        }
+        Ok(results.into_iter().map(|result| result.unwrap()).collect())
+    }
+
+    /** Apply processor to value by key under lock of entity
+     * return - new value and flag, that processor changed value */
+    pub fn tx_invoke(&self,
+                     tx_id: &u64,
+                     key: &JsonValue,
+                     processor: &EntryProcessor)
+                     -> Result<(JsonValue, bool), PersistenceError> {
+        let key_entity = try!(Table::json_to_entity(key, &self.description.key).map_err(|err| PersistenceError::IoEntity(err)));
+        let value_entity = try!(try!(self.get_lock_for_update(tx_id, &key_entity)).ok_or(PersistenceError::EntityNotFound(key_entity.clone())));
+        let value = try!(Table::entity_to_json(&value_entity, &self.description.value).map_err(|err| PersistenceError::IoEntity(err)));
+        let (new_value, applied) = try!(processor.apply(&value).map_err(|err| PersistenceError::ProcessorFailed(err)));
+        if applied {
+            // New value must be valid for table description
+            let new_value_entity = try!(Table::json_to_entity(&new_value, &self.description.value).map_err(|err| PersistenceError::IoEntity(err)));
+            let transaction = try!(self.tx_manager.get_tx(tx_id));
+            transaction.lock().unwrap().update_value(self.description.name.clone(), &key_entity, new_value_entity);
+        }
+        trace!("Processor {:?} applied = {} for key {}, new value = {}",
+               processor,
+               applied,
+               key,
+               new_value);
+        Ok((new_value, applied))
+    }
 }
 
 impl DataBaseManager {
     pub fn new() -> Result<DataBaseManager, String> {
         let mut db_manager = DataBaseManager {
             type_descriptions: BTreeMap::new(),
-            table_descriptions: ConcHashMap::<String, TableDescription>::new(),
             tables: ConcHashMap::<String, Arc<Table>>::new(),
             tx_manager: Arc::new(TransactionManager::new()),
         };
@@ -436,7 +723,7 @@ impl DataBaseManager {
             name: "string".to_string(),
             reader: Box::new(move |json| {
                 match json.clone() {
-                    rustless::json::JsonValue::String(value) => {
+                    JsonValue::String(value) => {
                         encode(&value.clone(), bincode::SizeLimit::Infinite).map_err(|err| IoEntityError::Read(err.to_string()))
                     }
                     _ => Err(IoEntityError::Read(format!("Expected type String: {}", json))),
@@ -444,15 +731,16 @@ impl DataBaseManager {
             }),
             writer: Box::new(|value: &Vec<u8>| {
                 let string: String = try!(decode(&value[..]).map_err(|err| IoEntityError::Write(err.to_string())));
-                Ok(rustless::json::JsonValue::String(string))
+                Ok(JsonValue::String(string))
             }),
+            schema: JsonValue::from_str("{\"type\": \"string\"}").unwrap(),
         };
 
         let u64_type = TypeDescription {
             name: "u64".to_string(),
             reader: Box::new(move |json| {
                 match json.clone() {
-                    rustless::json::JsonValue::U64(value) => {
+                    JsonValue::U64(value) => {
                         encode(&value.clone(), bincode::SizeLimit::Infinite).map_err(|err| IoEntityError::Read(err.to_string()))
                     }
                     _ => Err(IoEntityError::Read(format!("Expected type u64: {}", json))),
@@ -460,8 +748,9 @@ impl DataBaseManager {
             }),
             writer: Box::new(|ref value| {
                 let u64_value = try!(decode(&value[..]).map_err(|err| IoEntityError::Write(err.to_string())));
-                Ok(rustless::json::JsonValue::U64(u64_value))
+                Ok(JsonValue::U64(u64_value))
             }),
+            schema: JsonValue::from_str("{\"type\": \"integer\", \"minimum\": 0}").unwrap(),
         };
 
         let i64_type = TypeDescription {
@@ -474,8 +763,9 @@ impl DataBaseManager {
             }),
             writer: Box::new(|ref value| {
                 let i64_value = try!(decode(&value[..]).map_err(|err| IoEntityError::Write(err.to_string())));
-                Ok(rustless::json::JsonValue::I64(i64_value))
+                Ok(JsonValue::I64(i64_value))
             }),
+            schema: JsonValue::from_str("{\"type\": \"integer\"}").unwrap(),
         };
 
         let date_fmt = "%Y-%m-%d";
@@ -484,7 +774,7 @@ impl DataBaseManager {
             name: "date".to_string(),
             reader: Box::new(move |ref json| {
                 match *json {
-                    &rustless::json::JsonValue::String(ref value) => {
+                    &JsonValue::String(ref value) => {
                         match NaiveDate::parse_from_str(value.clone().as_ref(), date_fmt) {
                             Ok(date) => {
                                 encode(&date.format(date_fmt).to_string(),
@@ -504,15 +794,16 @@ impl DataBaseManager {
             }),
             writer: Box::new(|ref value| {
                 let date_string = try!(decode(&value[..]).map_err(|err| IoEntityError::Write(err.to_string())));
-                Ok(rustless::json::JsonValue::String(date_string))
+                Ok(JsonValue::String(date_string))
             }),
+            schema: JsonValue::from_str("{\"type\": \"string\", \"format\": \"date\"}").unwrap(),
         };
 
         let date_time_type = TypeDescription {
             name: "date_time".to_string(),
             reader: Box::new(move |ref json| {
                 match *json {
-                    &rustless::json::JsonValue::String(ref value) => {
+                    &JsonValue::String(ref value) => {
                         match DateTime::parse_from_rfc3339(value.clone().as_ref()) {
                             Ok(date_time) => {
                                 encode(&date_time.timestamp(), bincode::SizeLimit::Infinite).map_err(|err| IoEntityError::Read(err.to_string()))
@@ -525,8 +816,9 @@ impl DataBaseManager {
             }),
             writer: Box::new(|ref value| {
                 let timestamp = try!(decode(&value[..]).map_err(|err| IoEntityError::Write(err.to_string())));
-                Ok(rustless::json::JsonValue::String(Utc.timestamp(timestamp, 0).to_rfc3339()))
+                Ok(JsonValue::String(Utc.timestamp(timestamp, 0).to_rfc3339()))
             }),
+            schema: JsonValue::from_str("{\"type\": \"string\", \"format\": \"date-time\"}").unwrap(),
         };
 
         try!(db_manager.add_type(u64_type));
@@ -538,6 +830,21 @@ impl DataBaseManager {
         Ok(db_manager)
     }
 
+    /** Data base with durable log of prepared transactions in specified directory.
+     * Transactions, prepared before restart, are listed as in doubt until coordinator resolves them */
+    pub fn with_prepared_log_dir(dir: &Path) -> Result<DataBaseManager, String> {
+        let mut db_manager = try!(DataBaseManager::new());
+        let prepared_log = try!(PreparedLog::open(dir));
+        let tx_manager = try!(TransactionManager::with_prepared_log(prepared_log).map_err(|err| err.to_string()));
+        db_manager.tx_manager = Arc::new(tx_manager);
+        Ok(db_manager)
+    }
+
+    /** Max wait of pessimistic transaction for lock of other transaction, None - without timeout */
+    pub fn set_lock_wait(&self, wait: Option<Duration>) {
+        self.tx_manager.set_lock_wait(wait);
+    }
+
     pub fn add_type(&mut self, type_desc: TypeDescription) -> Result<(), String> {
         if !self.type_descriptions.contains_key(&type_desc.name) {
             self.type_descriptions.insert(type_desc.name.clone(), Arc::new(Box::new(type_desc)));
@@ -551,33 +858,52 @@ impl DataBaseManager {
         println!("I'm a data base manager");
     }
 
-    pub fn get_tables_json_list(&self) -> rustless::json::JsonValue {
-        let res = self.table_descriptions
+    pub fn get_tables_json_list(&self) -> JsonValue {
+        let res = self.tables
             .iter()
-            .map(|(k, v)| (k.clone(), v.to_json()))
+            .map(|(k, v)| (k.clone(), v.description.to_json()))
             .collect();
-        rustless::json::JsonValue::Object(res)
+        JsonValue::Object(res)
     }
 
-    pub fn get_table_json(&self, name: &String) -> Option<rustless::json::JsonValue> {
-        // self.table_descriptions.find(name).map(|table| { table.get().to_json() })
+    pub fn get_table_json(&self, name: &String) -> Option<JsonValue> {
         self.tables.find(name).map(|table| table.get().description.to_json())
     }
 
+    /** JSON Schema of key and value of table */
+    pub fn get_table_schema(&self, name: &String) -> Option<JsonValue> {
+        self.tables.find(name).map(|table| table.get().description.json_schema())
+    }
+
+    // Schemas of all tables by names, ordered by names
+    pub fn get_tables_schemas(&self) -> BTreeMap<String, JsonValue> {
+        self.tables
+            .iter()
+            .map(|(name, table)| (name.clone(), table.description.json_schema()))
+            .collect()
+    }
+
     pub fn get_table(&self, name: &String) -> Option<Arc<Table>> {
         self.tables.find(name).map(|accessor| accessor.get().clone())
     }
 
+    /** Access to table by serde types of key and value. Table is not checked here,
+     * operations fail with TableNotFound, if table doesn't exist */
+    pub fn typed_table<K, V>(&self, name: &str) -> TypedTable<K, V> {
+        TypedTable::new(self, name)
+    }
+
     /** Add new table by he view description
 	 * return - table name or error description is adding fail */
     pub fn add_table(&self, table_description: TableDescriptionView) -> Result<String, String> {
-        if !self.table_descriptions.find(&table_description.name).is_some() {
+        if !self.tables.find(&table_description.name).is_some() {
             let table_desc = try!(TableDescription::from_view(&table_description, &self.type_descriptions));
             self.tables.insert(table_desc.name.clone(),
                                Arc::new(Table {
                                    description: table_desc,
                                    data: ConcHashMap::<Entity, Arc<Mutex<Entity>>>::new(),
                                    tx_manager: self.tx_manager.clone(),
+                                   range_lock: Arc::new(RangeLock::new()),
                                }));
             Ok(table_description.name.clone())
         } else {
@@ -586,44 +912,397 @@ impl DataBaseManager {
     }
 
     pub fn add_data(&self,
-                    tx_id: &u32,
+                    tx_id: &u64,
                     table_name: &String,
-                    key: &rustless::json::JsonValue,
-                    value: &rustless::json::JsonValue)
+                    key: &JsonValue,
+                    value: &JsonValue)
                     -> Result<(), PersistenceError> {
-        match self.tables.find(table_name) {
-            Some(table) => table.get().tx_put(tx_id, key, value),
-            None => Err(PersistenceError::TableNotFound(table_name.clone())),
-        }
+        self.measured(Operation::Put, || {
+            let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+            self.in_transaction(tx_id, &table, |tx_id| table.tx_put(tx_id, key, value))
+        })
     }
 
     pub fn get_data(&self,
-                    tx_id: &u32,
+                    tx_id: &u64,
                     table_name: &String,
-                    key: &rustless::json::JsonValue)
-                    -> Result<Option<rustless::json::JsonValue>, PersistenceError> {
-        let table = try!(self.tables.find(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
-        table.get().tx_get(tx_id, key)
+                    key: &JsonValue)
+                    -> Result<Option<JsonValue>, PersistenceError> {
+        self.measured(Operation::Get, || {
+            let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+            self.in_transaction(tx_id, &table, |tx_id| table.tx_get(tx_id, key))
+        })
+    }
+
+    /** Remove entity by key
+     * return - true, if entity existed */
+    pub fn remove_data(&self, tx_id: &u64, table_name: &String, key: &JsonValue) -> Result<bool, PersistenceError> {
+        self.measured(Operation::Remove, || {
+            let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+            self.in_transaction(tx_id, &table, |tx_id| table.tx_remove(tx_id, key))
+        })
+    }
+
+    pub fn get_list(&self, tx_id: u64, table_name: &String, start: u32, count: u32) -> Result<Vec<JsonValue>, PersistenceError> {
+        self.measured(Operation::List, || {
+            let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+            self.in_transaction(&tx_id, &table, |tx_id| table.tx_get_list(tx_id.clone(), start, count))
+        })
+    }
+
+    // Count and latency of operation, implicit transaction is included
+    fn measured<T, F>(&self, operation: Operation, execute: F) -> Result<T, PersistenceError>
+        where F: FnOnce() -> Result<T, PersistenceError>
+    {
+        let started = Instant::now();
+        let res = execute();
+        self.tx_manager.get_metrics().observe_operation(operation, started.elapsed(), res.is_ok());
+        res
+    }
+
+    /** Put entries in order of keys. Atomic batch is failed by first failed entry
+     * and in explicit transaction rolls back changes of batch to savepoint before it, else results of all entries are returned */
+    pub fn put_all(&self,
+                   tx_id: &u64,
+                   table_name: &String,
+                   entries: &Vec<(JsonValue, JsonValue)>,
+                   atomic: bool)
+                   -> Result<Vec<Result<(), PersistenceError>>, PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        self.in_batch_transaction(tx_id, &table, atomic, |tx_id| table.tx_put_all(tx_id, entries, atomic))
+    }
+
+    pub fn get_all(&self,
+                   tx_id: &u64,
+                   table_name: &String,
+                   keys: &Vec<JsonValue>,
+                   atomic: bool)
+                   -> Result<Vec<Result<Option<JsonValue>, PersistenceError>>, PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        self.in_batch_transaction(tx_id, &table, atomic, |tx_id| table.tx_get_all(tx_id, keys, atomic))
+    }
+
+    pub fn remove_all(&self,
+                      tx_id: &u64,
+                      table_name: &String,
+                      keys: &Vec<JsonValue>,
+                      atomic: bool)
+                      -> Result<Vec<Result<bool, PersistenceError>>, PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        self.in_batch_transaction(tx_id, &table, atomic, |tx_id| table.tx_remove_all(tx_id, keys, atomic))
+    }
+
+    // Atomic batch in explicit transaction is undone by rollback to savepoint before batch
+    fn in_batch_transaction<T, F>(&self, tx_id: &u64, table: &Table, atomic: bool, operation: F) -> Result<T, PersistenceError>
+        where F: FnOnce(&u64) -> Result<T, PersistenceError>
+    {
+        if atomic && *tx_id != DEFAULT_TX_ID {
+            let savepoint = String::from(BATCH_SAVEPOINT);
+            try!(self.tx_manager.savepoint(tx_id, &savepoint));
+            let res = operation(tx_id);
+            if res.is_err() {
+                warn!("Atomic batch failed, rollback tx {} to savepoint before batch", tx_id);
+                try!(self.tx_manager.rollback_to_savepoint(tx_id, &savepoint, true));
+            }
+            try!(self.tx_manager.release_savepoint(tx_id, &savepoint));
+            res
+        } else {
+            self.in_transaction(tx_id, table, operation)
+        }
+    }
+
+    /** Import rows of JSON lines or CSV, every batch of rows is committed in own transaction.
+     * Rows with wrong key or value are rejected and reported with their lines,
+     * failure of transaction stops import with ImportFailed, that has report of batches, committed before it */
+    pub fn import<R: BufRead>(&self,
+                              table_name: &String,
+                              format: &BulkFormat,
+                              input: R,
+                              batch_size: usize)
+                              -> Result<ImportReport, PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        let batch_size = std::cmp::max(batch_size, 1);
+        let mut rows = RowReader::new(input, format.clone(), &table.description);
+        let mut report = ImportReport::new();
+        let mut batch = Vec::new();
+        while let Some((line, row)) = try!(rows.next_row().map_err(|err| PersistenceError::IoEntity(err))) {
+            match row {
+                Ok((key, value)) => batch.push((line, key, value)),
+                Err(error) => {
+                    report.rejected.push(RejectedRow {
+                        line: line,
+                        error: error,
+                    })
+                }
+            }
+            if batch.len() >= batch_size {
+                report = try!(self.import_batch(&table, &mut batch, report));
+            }
+        }
+        if !batch.is_empty() {
+            report = try!(self.import_batch(&table, &mut batch, report));
+        }
+        report.rejected.sort_by_key(|row| row.line);
+        info!("Imported {} rows to table {}, rejected {} rows",
+              report.imported,
+              table_name,
+              report.rejected.len());
+        Ok(report)
+    }
+
+    // Report is returned with error, if transaction of batch fails, line of error is first line of batch, if row is unknown
+    fn import_batch(&self, table: &Table, batch: &mut Vec<(usize, JsonValue, JsonValue)>, mut report: ImportReport) -> Result<ImportReport, PersistenceError> {
+        let size = batch.len();
+        let first_line = batch.first().map_or(0, |&(line, _, _)| line);
+        let res = self.auto_commit(table.description.lock_mode.clone(), |tx_id| {
+            let mut rejected = Vec::new();
+            for (line, key, value) in batch.drain(..) {
+                match table.tx_put(tx_id, &key, &value) {
+                    Ok(()) => (),
+                    Err(PersistenceError::IoEntity(error)) => {
+                        rejected.push(RejectedRow {
+                            line: line,
+                            error: error,
+                        })
+                    }
+                    Err(error) => return Err(PersistenceError::BatchFailed(line, Box::new(error))),
+                }
+            }
+            Ok(rejected)
+        });
+        match res {
+            Ok(rejected) => {
+                report.imported += size - rejected.len();
+                report.batches += 1;
+                report.rejected.extend(rejected);
+                Ok(report)
+            }
+            Err(error) => {
+                let (line, error) = match error {
+                    PersistenceError::BatchFailed(line, error) => (line, error),
+                    error => (first_line, Box::new(error)),
+                };
+                warn!("Import to table {} failed on line {}: {}", table.description.name, line, error);
+                report.rejected.sort_by_key(|row| row.line);
+                Err(PersistenceError::ImportFailed(line, Box::new(report), error))
+            }
+        }
+    }
+
+    /** Write last committed entities, ordered by json of keys
+     * return - count of written entities */
+    pub fn export<W: Write>(&self, table_name: &String, format: &BulkFormat, output: &mut W) -> Result<usize, PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        let mut rows = try!(table.committed_list());
+        rows.sort_by_key(|&(ref key, _)| key.to_string());
+        try!(bulk::write_rows(output, format, &table.description, &rows).map_err(|err| PersistenceError::IoEntity(err)));
+        Ok(rows.len())
+    }
+
+    /** Apply processor to value by key
+     * return - new value and flag, that processor changed value */
+    pub fn invoke(&self,
+                  tx_id: &u64,
+                  table_name: &String,
+                  key: &JsonValue,
+                  processor: &EntryProcessor)
+                  -> Result<(JsonValue, bool), PersistenceError> {
+        let table = try!(self.get_table(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
+        self.in_transaction(tx_id, &table, |tx_id| table.tx_invoke(tx_id, key, processor))
+    }
+
+    // Execute operation in specified transaction or, for DEFAULT_TX_ID, in own transaction
+    fn in_transaction<T, F>(&self, tx_id: &u64, table: &Table, operation: F) -> Result<T, PersistenceError>
+        where F: FnOnce(&u64) -> Result<T, PersistenceError>
+    {
+        if *tx_id == DEFAULT_TX_ID {
+            self.auto_commit(table.description.lock_mode.clone(), operation)
+        } else {
+            operation(tx_id)
+        }
+    }
+
+    /** Execute operation in short-lived transaction, that is committed on success and rolled back on error */
+    pub fn auto_commit<T, F>(&self, lock_mode: LockMode, operation: F) -> Result<T, PersistenceError>
+        where F: FnOnce(&u64) -> Result<T, PersistenceError>
+    {
+        let tx_id = try!(self.tx_start(lock_mode));
+        trace!("Implicit tx {} started", tx_id);
+        match operation(&tx_id) {
+            Ok(res) => {
+                try!(self.tx_stop(&tx_id));
+                Ok(res)
+            }
+            Err(error) => {
+                if let Err(rollback_error) = self.tx_rollback(&tx_id) {
+                    error!("Rollback of implicit tx {} failed: {}", tx_id, rollback_error);
+                }
+                Err(error)
+            }
+        }
     }
 
-    pub fn get_list(&self, tx_id: u32, table_name: &String, start: u32, count: u32) -> Result<Vec<rustless::json::JsonValue>, PersistenceError> {
-        let table = try!(self.tables.find(table_name).ok_or(PersistenceError::TableNotFound(table_name.clone())));
-        table.get().tx_get_list(tx_id, start, count)
+    pub fn tx_start(&self, lock_mode: LockMode) -> Result<u64, PersistenceError> {
+        self.tx_start_with_isolation(lock_mode, IsolationLevel::RepeatableRead)
     }
 
-    pub fn tx_start(&self, lock_mode: LockMode) -> Result<u32, PersistenceError> {
-        self.tx_manager.start(lock_mode)
+    pub fn tx_start_with_isolation(&self, lock_mode: LockMode, isolation_level: IsolationLevel) -> Result<u64, PersistenceError> {
+        self.tx_manager.start(lock_mode, isolation_level)
     }
 
-    pub fn tx_stop(&self, tx_id: &u32) -> Result<(), PersistenceError> {
+    pub fn tx_stop(&self, tx_id: &u64) -> Result<(), PersistenceError> {
         self.tx_manager.stop(self, tx_id)
     }
 
-    pub fn tx_rollback(&self, tx_id: &u32) -> Result<(), PersistenceError> {
+    pub fn tx_rollback(&self, tx_id: &u64) -> Result<(), PersistenceError> {
         self.tx_manager.rollback(tx_id)
     }
 
-    pub fn get_transactions_list(&self) -> rustless::json::JsonValue {
+    pub fn tx_prepare(&self, tx_id: &u64) -> Result<(), PersistenceError> {
+        self.tx_manager.prepare(self, tx_id)
+    }
+
+    // Commit of prepared transaction or replay of changes of in doubt transaction
+    pub fn tx_commit_prepared(&self, tx_id: &u64) -> Result<(), PersistenceError> {
+        if try!(self.tx_manager.commit_prepared(self, tx_id)) {
+            return Ok(());
+        }
+        let prepared = try!(self.tx_manager.take_in_doubt(tx_id).ok_or(PersistenceError::UndefinedTransaction(tx_id.clone())));
+        let res = self.auto_commit(LockMode::Pessimistic, |replay_tx_id| {
+            self.tx_manager.reserve_in_doubt_keys(&prepared, replay_tx_id.clone());
+            for change in prepared.changes.iter() {
+                let table = try!(self.get_table(&change.table_name).ok_or(PersistenceError::TableNotFound(change.table_name.clone())));
+                match change.value {
+                    Some(ref value) => try!(table.tx_put(replay_tx_id, &change.key, value)),
+                    None => {
+                        try!(table.tx_remove(replay_tx_id, &change.key));
+                    }
+                }
+            }
+            Ok(())
+        });
+        match res {
+            Ok(_) => {
+                info!("In doubt tx {} committed, changes = {}",
+                      tx_id,
+                      prepared.changes.len());
+                self.tx_manager.release_in_doubt_keys(&prepared);
+                self.tx_manager.remove_prepared_record(tx_id)
+            }
+            Err(error) => {
+                error!("Commit of in doubt tx {} failed: {}", tx_id, error);
+                self.tx_manager.return_in_doubt(prepared);
+                Err(error)
+            }
+        }
+    }
+
+    pub fn tx_rollback_prepared(&self, tx_id: &u64) -> Result<(), PersistenceError> {
+        self.tx_manager.rollback_prepared(tx_id)
+    }
+
+    pub fn get_prepared_list(&self) -> JsonValue {
+        self.tx_manager.get_prepared_list()
+    }
+
+    /** Name of savepoint of atomic batch is reserved, so user savepoint can't be replaced or released by batch */
+    pub fn tx_savepoint(&self, tx_id: &u64, name: &String) -> Result<(), PersistenceError> {
+        try!(DataBaseManager::check_savepoint_name(name));
+        self.tx_manager.savepoint(tx_id, name)
+    }
+
+    /** Undo changes of transaction, made after savepoint
+     * release_locks - true, if keys locked after savepoint must be unlocked */
+    pub fn tx_rollback_to_savepoint(&self, tx_id: &u64, name: &String, release_locks: bool) -> Result<(), PersistenceError> {
+        try!(DataBaseManager::check_savepoint_name(name));
+        self.tx_manager.rollback_to_savepoint(tx_id, name, release_locks)
+    }
+
+    pub fn tx_release_savepoint(&self, tx_id: &u64, name: &String) -> Result<(), PersistenceError> {
+        try!(DataBaseManager::check_savepoint_name(name));
+        self.tx_manager.release_savepoint(tx_id, name)
+    }
+
+    fn check_savepoint_name(name: &String) -> Result<(), PersistenceError> {
+        if name == BATCH_SAVEPOINT {
+            Err(PersistenceError::ReservedSavepoint(name.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn get_transactions_list(&self) -> JsonValue {
         self.tx_manager.get_transactions_list()
     }
+
+    pub fn get_transaction_json(&self, tx_id: &u64) -> Option<JsonValue> {
+        self.tx_manager.get_transaction_json(self, tx_id)
+    }
+
+    // Rollback transaction by operator, even if it waits for lock
+    pub fn tx_kill(&self, tx_id: &u64) -> Result<(), PersistenceError> {
+        self.tx_manager.kill(tx_id)
+    }
+
+    pub fn get_change_log(&self) -> Arc<ChangeLog> {
+        self.tx_manager.get_change_log()
+    }
+
+    // Committed changes with sequence number greater than since
+    pub fn get_changes(&self, since: u64, limit: usize) -> Result<Vec<ChangeRecord>, PersistenceError> {
+        self.tx_manager.get_change_log().since(since, limit)
+    }
+
+    pub fn get_query_manager(&self) -> Arc<ContinuousQueryManager> {
+        self.tx_manager.get_query_manager()
+    }
+
+    pub fn get_metrics(&self) -> Arc<Metrics> {
+        self.tx_manager.get_metrics()
+    }
+
+    // Sizes of tables, ordered by names
+    pub fn get_tables_stats(&self) -> Vec<TableStats> {
+        let mut stats: Vec<TableStats> = self.tables.iter().map(|(_, table)| table.get_stats()).collect();
+        stats.sort_by(|left, right| left.name.cmp(&right.name));
+        stats
+    }
+
+    /** Metrics of operations, transactions, locks and tables in Prometheus text format */
+    pub fn get_metrics_text(&self) -> String {
+        self.get_metrics().render(&self.get_tables_stats(), self.tx_manager.get_active_count())
+    }
+
+    /** Register continuous query
+     * return - query id, committed entries matched query and receiver of next events */
+    pub fn register_query(&self, query: ContinuousQuery) -> Result<(u64, Vec<QueryEvent>, Receiver<QueryEvent>), PersistenceError> {
+        let table = try!(self.get_table(&query.table_name).ok_or(PersistenceError::TableNotFound(query.table_name.clone())));
+        // Registration and reading of entries are not divided by commit, so changes are neither lost nor duplicated
+        let query_manager = self.get_query_manager();
+        let (seq, query_id, receiver, entries) = self.tx_manager.without_commits(|seq| {
+            let (query_id, receiver) = query_manager.register(query.clone());
+            (seq, query_id, receiver, table.committed_list())
+        });
+        let initial = match entries {
+            Ok(entries) => {
+                entries.into_iter()
+                    .filter(|&(ref key, ref value)| query.matches(key, value))
+                    .map(|(key, value)| {
+                        QueryEvent {
+                            query_id: query_id,
+                            seq: seq,
+                            event_type: QueryEventType::Initial,
+                            key: key,
+                            value: value,
+                        }
+                    })
+                    .collect()
+            }
+            Err(error) => {
+                self.get_query_manager().cancel(query_id);
+                return Err(error);
+            }
+        };
+        Ok((query_id, initial, receiver))
+    }
 }
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use data_base::json::{JsonValue, ToJson};
+
+const PREPARED_FILE_EXTENSION: &'static str = "json";
+const TX_ID_LIMIT_FILE: &'static str = "tx_id_limit";
+
+// Change of entity, that will be applied by commit of prepared transaction
+#[derive(Debug, Clone)]
+pub struct PreparedChange {
+    pub table_name: String,
+    pub key: JsonValue,
+    pub value: Option<JsonValue>, // none - entity will be removed
+}
+
+// Changes of transaction, recorded by prepare step of two-phase commit
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    pub tx_id: u64,
+    pub changes: Vec<PreparedChange>,
+}
+
+// Durable log of prepared transactions, one file per transaction in directory
+pub struct PreparedLog {
+    dir: PathBuf,
+}
+
+impl ToJson for PreparedChange {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("table"), JsonValue::String(self.table_name.clone()));
+        res.insert(String::from("key"), self.key.clone());
+        res.insert(String::from("value"), self.value.clone().unwrap_or(JsonValue::Null));
+        JsonValue::Object(res)
+    }
+}
+
+impl PreparedChange {
+    fn from_json(json: &JsonValue) -> Result<PreparedChange, String> {
+        let table_name = try!(json.find("table").and_then(|table| table.as_str()).ok_or(String::from("Prepared change table not found")));
+        let key = try!(json.find("key").ok_or(String::from("Prepared change key not found")));
+        let value = match json.find("value") {
+            Some(&JsonValue::Null) | None => None,
+            Some(value) => Some(value.clone()),
+        };
+        Ok(PreparedChange {
+            table_name: String::from(table_name),
+            key: key.clone(),
+            value: value,
+        })
+    }
+}
+
+impl ToJson for PreparedTransaction {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("tx_id"), JsonValue::U64(self.tx_id));
+        res.insert(String::from("changes"),
+                   JsonValue::Array(self.changes.iter().map(|change| change.to_json()).collect()));
+        JsonValue::Object(res)
+    }
+}
+
+impl PreparedTransaction {
+    fn from_json(json: &JsonValue) -> Result<PreparedTransaction, String> {
+        let tx_id = try!(json.find("tx_id").and_then(|tx_id| tx_id.as_u64()).ok_or(String::from("Prepared tx_id not found")));
+        let changes = try!(json.find("changes").and_then(|changes| changes.as_array()).ok_or(String::from("Prepared changes not found")));
+        let changes = try!(changes.iter().map(PreparedChange::from_json).collect::<Result<Vec<PreparedChange>, String>>());
+        Ok(PreparedTransaction {
+            tx_id: tx_id,
+            changes: changes,
+        })
+    }
+}
+
+impl PreparedLog {
+    pub fn open(dir: &Path) -> Result<PreparedLog, String> {
+        try!(fs::create_dir_all(dir).map_err(|err| format!("Cannot create prepared log dir {:?}: {}", dir, err)));
+        info!("Prepared log opened in {:?}", dir);
+        Ok(PreparedLog { dir: dir.to_path_buf() })
+    }
+
+    fn get_path(&self, tx_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.{}", tx_id, PREPARED_FILE_EXTENSION))
+    }
+
+    // Record is synced to disk before return, so prepared transaction survives restart
+    pub fn write(&self, prepared: &PreparedTransaction) -> Result<(), String> {
+        let path = self.get_path(prepared.tx_id);
+        try!(PreparedLog::write_durable(&path, prepared.to_json().to_string().as_bytes()));
+        debug!("Prepared tx {} written to {:?}", prepared.tx_id, path);
+        Ok(())
+    }
+
+    // Write to temporary file and rename, so file is never seen partially written
+    fn write_durable(path: &Path, content: &[u8]) -> Result<(), String> {
+        let temp_path = path.with_extension("tmp");
+        {
+            let mut file = try!(File::create(&temp_path).map_err(|err| format!("Cannot create {:?}: {}", temp_path, err)));
+            try!(file.write_all(content).map_err(|err| format!("Cannot write {:?}: {}", temp_path, err)));
+            try!(file.sync_all().map_err(|err| format!("Cannot sync {:?}: {}", temp_path, err)));
+        }
+        try!(fs::rename(&temp_path, path).map_err(|err| format!("Cannot rename {:?}: {}", temp_path, err)));
+        PreparedLog::sync_dir(path)
+    }
+
+    // Rename and remove are durable only after sync of directory, that contains file
+    fn sync_dir(path: &Path) -> Result<(), String> {
+        let dir = try!(path.parent().ok_or(format!("No directory of {:?}", path)));
+        File::open(dir)
+            .and_then(|dir_file| dir_file.sync_all())
+            .map_err(|err| format!("Cannot sync dir {:?}: {}", dir, err))
+    }
+
+    /** Upper limit of tx ids, that could be given before restart
+     * return - 0, if limit was never written */
+    pub fn read_tx_id_limit(&self) -> Result<u64, String> {
+        let path = self.dir.join(TX_ID_LIMIT_FILE);
+        if !path.exists() {
+            return Ok(0);
+        }
+        let mut content = String::new();
+        try!(File::open(&path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|err| format!("Cannot read {:?}: {}", path, err)));
+        content.trim().parse::<u64>().map_err(|err| format!("Wrong tx id limit in {:?}: {}", path, err))
+    }
+
+    pub fn write_tx_id_limit(&self, limit: u64) -> Result<(), String> {
+        PreparedLog::write_durable(&self.dir.join(TX_ID_LIMIT_FILE), limit.to_string().as_bytes())
+    }
+
+    pub fn remove(&self, tx_id: u64) -> Result<(), String> {
+        let path = self.get_path(tx_id);
+        try!(fs::remove_file(&path).map_err(|err| format!("Cannot remove {:?}: {}", path, err)));
+        PreparedLog::sync_dir(&path)
+    }
+
+    // All prepared transactions, that were not resolved before restart
+    pub fn read_all(&self) -> Result<Vec<PreparedTransaction>, String> {
+        let entries = try!(fs::read_dir(&self.dir).map_err(|err| format!("Cannot read prepared log dir {:?}: {}", self.dir, err)));
+        let mut res = Vec::new();
+        for entry in entries {
+            let path = try!(entry.map_err(|err| err.to_string())).path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(PREPARED_FILE_EXTENSION) {
+                continue;
+            }
+            let mut content = String::new();
+            try!(File::open(&path)
+                .and_then(|mut file| file.read_to_string(&mut content))
+                .map_err(|err| format!("Cannot read {:?}: {}", path, err)));
+            let json = try!(JsonValue::from_str(&content).map_err(|err| format!("Wrong prepared record {:?}: {}", path, err)));
+            res.push(try!(PreparedTransaction::from_json(&json)));
+        }
+        res.sort_by_key(|prepared| prepared.tx_id);
+        Ok(res)
+    }
+}
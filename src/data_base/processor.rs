@@ -0,0 +1,138 @@
+use data_base::json::JsonValue;
+
+// Declarative read-modify-write operation, that applied to value under entity lock
+#[derive(Debug, Clone)]
+pub enum EntryProcessor {
+    Increment { field: String, delta: i64 }, // for u64 and i64 fields
+    Append { field: String, suffix: String }, // for string fields
+    SetIfEqual { field: String, expected: JsonValue, value: JsonValue },
+    SetField { field: String, value: JsonValue },
+}
+
+impl EntryProcessor {
+    /** Read processor from json, examples:
+     * { "op": "increment", "field": "age", "delta": 1 }
+     * { "op": "append", "field": "full_name", "value": " Jr." }
+     * { "op": "set-if-equal", "field": "age", "expected": 23, "value": 24 }
+     * { "op": "set-field", "field": "age", "value": 24 } */
+    pub fn from_json(json: &JsonValue) -> Result<EntryProcessor, String> {
+        let op = try!(json.find("op").and_then(|op| op.as_str()).ok_or(String::from("Processor op not found")));
+        let field = String::from(try!(json.find("field").and_then(|field| field.as_str()).ok_or(String::from("Processor field not found"))));
+        match op {
+            "increment" => {
+                let delta = try!(json.find("delta")
+                    .map(|delta| delta.as_i64().ok_or(format!("Processor delta {} is not integer", delta)))
+                    .unwrap_or(Ok(1)));
+                Ok(EntryProcessor::Increment {
+                    field: field,
+                    delta: delta,
+                })
+            }
+            "append" => {
+                let suffix = try!(json.find("value").and_then(|value| value.as_str()).ok_or(String::from("Processor string value not found")));
+                Ok(EntryProcessor::Append {
+                    field: field,
+                    suffix: String::from(suffix),
+                })
+            }
+            "set-if-equal" => {
+                let expected = try!(json.find("expected").ok_or(String::from("Processor expected value not found")));
+                let value = try!(json.find("value").ok_or(String::from("Processor value not found")));
+                Ok(EntryProcessor::SetIfEqual {
+                    field: field,
+                    expected: expected.clone(),
+                    value: value.clone(),
+                })
+            }
+            "set-field" => {
+                let value = try!(json.find("value").ok_or(String::from("Processor value not found")));
+                Ok(EntryProcessor::SetField {
+                    field: field,
+                    value: value.clone(),
+                })
+            }
+            _ => Err(format!("Unknown processor op {}", op)),
+        }
+    }
+
+    fn get_field_name(&self) -> &String {
+        match *self {
+            EntryProcessor::Increment { ref field, .. } |
+            EntryProcessor::Append { ref field, .. } |
+            EntryProcessor::SetIfEqual { ref field, .. } |
+            EntryProcessor::SetField { ref field, .. } => field,
+        }
+    }
+
+    /** Apply processor to json of value
+     * return - new value and flag, that value was changed */
+    pub fn apply(&self, value: &JsonValue) -> Result<(JsonValue, bool), String> {
+        let mut new_value = value.clone();
+        let applied = {
+            let fields = try!(new_value.as_object_mut().ok_or(format!("Value {} is not object", value)));
+            let field_name = self.get_field_name();
+            let current = try!(fields.get(field_name).cloned().ok_or(format!("Field {} not found in value", field_name)));
+            let updated = try!(self.apply_to_field(&current));
+            match updated {
+                Some(updated) => {
+                    fields.insert(field_name.clone(), updated);
+                    true
+                }
+                None => false,
+            }
+        };
+        Ok((new_value, applied))
+    }
+
+    fn apply_to_field(&self, current: &JsonValue) -> Result<Option<JsonValue>, String> {
+        match *self {
+            EntryProcessor::Increment { ref field, delta } => {
+                match *current {
+                    JsonValue::U64(value) => {
+                        let res = if delta >= 0 {
+                            value.checked_add(delta as u64)
+                        } else {
+                            value.checked_sub(delta.wrapping_neg() as u64)
+                        };
+                        res.map(|res| Some(JsonValue::U64(res)))
+                            .ok_or(format!("Increment of field {} = {} by {} is out of u64 range", field, value, delta))
+                    }
+                    JsonValue::I64(value) => {
+                        value.checked_add(delta)
+                            .map(|res| Some(JsonValue::I64(res)))
+                            .ok_or(format!("Increment of field {} = {} by {} is out of i64 range", field, value, delta))
+                    }
+                    _ => Err(format!("Field {} = {} is not integer", field, current)),
+                }
+            }
+            EntryProcessor::Append { ref field, ref suffix } => {
+                match *current {
+                    JsonValue::String(ref value) => Ok(Some(JsonValue::String(value.clone() + suffix.as_str()))),
+                    _ => Err(format!("Field {} = {} is not string", field, current)),
+                }
+            }
+            EntryProcessor::SetIfEqual { ref expected, ref value, .. } => {
+                if EntryProcessor::equal_values(current, expected) {
+                    Ok(Some(value.clone()))
+                } else {
+                    Ok(None)
+                }
+            }
+            EntryProcessor::SetField { ref value, .. } => Ok(Some(value.clone())),
+        }
+    }
+
+    // Numbers are compared by value, so I64 field is equal to U64 literal of request
+    fn equal_values(left: &JsonValue, right: &JsonValue) -> bool {
+        if let (Some(left), Some(right)) = (left.as_i64(), right.as_i64()) {
+            return left == right;
+        }
+        if let (Some(left), Some(right)) = (left.as_u64(), right.as_u64()) {
+            return left == right;
+        }
+        match (left.as_f64(), right.as_f64()) {
+            (Some(left), Some(right)) => left == right,
+            _ => left == right,
+        }
+    }
+}
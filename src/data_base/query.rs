@@ -0,0 +1,410 @@
+extern crate chrono;
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use data_base::json::{JsonValue, ToJson};
+
+use data_base::changes::ChangeRecord;
+
+use self::chrono::prelude::*;
+
+const STREAM_KEEP_ALIVE_SECS: u64 = 15;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionTarget {
+    Key,
+    Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionOperator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+// Comparison of one key or value field with constant
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub target: ConditionTarget,
+    pub field: String,
+    pub operator: ConditionOperator,
+    pub value: JsonValue,
+}
+
+// Continuous query: table and conditions, that all must be satisfied by entry
+#[derive(Debug, Clone)]
+pub struct ContinuousQuery {
+    pub table_name: String,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryEventType {
+    Initial, // entry matched query at moment of registration
+    Added,
+    Updated,
+    Removed, // entry was removed or not match query anymore
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryEvent {
+    pub query_id: u64,
+    pub seq: u64, // sequence number of change, for initial entries - last sequence number at moment of registration
+    pub event_type: QueryEventType,
+    pub key: JsonValue,
+    pub value: JsonValue,
+}
+
+struct Registration {
+    query: ContinuousQuery,
+    created: DateTime<Utc>,
+    sender: Sender<QueryEvent>,
+}
+
+// Registry of continuous queries, that receives changes of committed transactions
+pub struct ContinuousQueryManager {
+    counter: AtomicUsize,
+    registrations: Mutex<BTreeMap<u64, Registration>>,
+}
+
+// Server-sent events representation of continuous query: initial entries and then incremental events
+pub struct QueryEventStream {
+    query_manager: Arc<ContinuousQueryManager>,
+    query_id: u64,
+    initial: VecDeque<QueryEvent>,
+    receiver: Receiver<QueryEvent>,
+    pending: Vec<u8>,
+    closed: bool,
+}
+
+impl ConditionTarget {
+    fn from_str(target: &str) -> Result<ConditionTarget, String> {
+        match target {
+            "key" => Ok(ConditionTarget::Key),
+            "value" => Ok(ConditionTarget::Value),
+            _ => Err(format!("Unknown condition target {}, expected key or value", target)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            ConditionTarget::Key => "key",
+            ConditionTarget::Value => "value",
+        }
+    }
+}
+
+impl ConditionOperator {
+    fn from_str(operator: &str) -> Result<ConditionOperator, String> {
+        match operator {
+            "eq" => Ok(ConditionOperator::Eq),
+            "ne" => Ok(ConditionOperator::Ne),
+            "gt" => Ok(ConditionOperator::Gt),
+            "ge" => Ok(ConditionOperator::Ge),
+            "lt" => Ok(ConditionOperator::Lt),
+            "le" => Ok(ConditionOperator::Le),
+            _ => Err(format!("Unknown condition operator {}", operator)),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            ConditionOperator::Eq => "eq",
+            ConditionOperator::Ne => "ne",
+            ConditionOperator::Gt => "gt",
+            ConditionOperator::Ge => "ge",
+            ConditionOperator::Lt => "lt",
+            ConditionOperator::Le => "le",
+        }
+    }
+}
+
+impl Condition {
+    /** Read condition from json like
+     * { "target": "value", "field": "age", "op": "gt", "value": 30 } */
+    pub fn from_json(json: &JsonValue) -> Result<Condition, String> {
+        let target = try!(json.find("target")
+            .map(|target| target.as_str().ok_or(format!("Condition target {} is not a string", target)))
+            .unwrap_or(Ok("value"))
+            .and_then(ConditionTarget::from_str));
+        let field = try!(json.find("field").and_then(|field| field.as_str()).ok_or(String::from("Condition field not found")));
+        let operator = try!(json.find("op")
+            .map(|operator| operator.as_str().ok_or(format!("Condition op {} is not a string", operator)))
+            .unwrap_or(Ok("eq"))
+            .and_then(ConditionOperator::from_str));
+        let value = try!(json.find("value").ok_or(String::from("Condition value not found")));
+        Ok(Condition {
+            target: target,
+            field: String::from(field),
+            operator: operator,
+            value: value.clone(),
+        })
+    }
+
+    pub fn matches(&self, key: &JsonValue, value: &JsonValue) -> bool {
+        let entity = match self.target {
+            ConditionTarget::Key => key,
+            ConditionTarget::Value => value,
+        };
+        match entity.find(self.field.as_str()) {
+            Some(field_value) => {
+                let ordering = Condition::compare(field_value, &self.value);
+                match self.operator {
+                    ConditionOperator::Eq => ordering == Some(Ordering::Equal),
+                    ConditionOperator::Ne => ordering != Some(Ordering::Equal),
+                    ConditionOperator::Gt => ordering == Some(Ordering::Greater),
+                    ConditionOperator::Ge => ordering == Some(Ordering::Greater) || ordering == Some(Ordering::Equal),
+                    ConditionOperator::Lt => ordering == Some(Ordering::Less),
+                    ConditionOperator::Le => ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal),
+                }
+            }
+            None => false,
+        }
+    }
+
+    // Numbers are compared by value, strings lexicographically, other values are only equal or not
+    fn compare(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+        match (left, right) {
+            (&JsonValue::String(ref left), &JsonValue::String(ref right)) => Some(left.cmp(right)),
+            _ => {
+                match (left.as_f64(), right.as_f64()) {
+                    (Some(left), Some(right)) => left.partial_cmp(&right),
+                    _ if left == right => Some(Ordering::Equal),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl ToJson for Condition {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("target"), JsonValue::String(String::from(self.target.name())));
+        res.insert(String::from("field"), JsonValue::String(self.field.clone()));
+        res.insert(String::from("op"), JsonValue::String(String::from(self.operator.name())));
+        res.insert(String::from("value"), self.value.clone());
+        JsonValue::Object(res)
+    }
+}
+
+impl ContinuousQuery {
+    /** Read query from json like
+     * { "table": "Client", "conditions": [ { "field": "age", "op": "gt", "value": 30 } ] } */
+    pub fn from_json(json: &JsonValue) -> Result<ContinuousQuery, String> {
+        let table_name = try!(json.find("table").and_then(|table| table.as_str()).ok_or(String::from("Query table not found")));
+        let conditions = try!(match json.find("conditions") {
+            Some(&JsonValue::Array(ref conditions)) => conditions.iter().map(Condition::from_json).collect(),
+            Some(conditions) => Err(format!("Query conditions {} is not array", conditions)),
+            None => Ok(vec![]),
+        });
+        Ok(ContinuousQuery {
+            table_name: String::from(table_name),
+            conditions: conditions,
+        })
+    }
+
+    pub fn matches(&self, key: &JsonValue, value: &JsonValue) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(key, value))
+    }
+
+    // Event for subscriber of query about change, or none if change is not interesting for query
+    fn get_event(&self, query_id: u64, change: &ChangeRecord) -> Option<QueryEvent> {
+        if change.table_name != self.table_name {
+            return None;
+        }
+        let old_match = change.old_value.as_ref().map(|value| self.matches(&change.key, value)).unwrap_or(false);
+        let new_match = change.new_value.as_ref().map(|value| self.matches(&change.key, value)).unwrap_or(false);
+        let event = match (old_match, new_match) {
+            (false, true) => Some((QueryEventType::Added, change.new_value.clone())),
+            (true, true) => Some((QueryEventType::Updated, change.new_value.clone())),
+            (true, false) => Some((QueryEventType::Removed, change.old_value.clone())),
+            (false, false) => None,
+        };
+        event.map(|(event_type, value)| {
+            QueryEvent {
+                query_id: query_id,
+                seq: change.seq,
+                event_type: event_type,
+                key: change.key.clone(),
+                value: value.unwrap_or(JsonValue::Null),
+            }
+        })
+    }
+}
+
+impl ToJson for ContinuousQuery {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("table"), JsonValue::String(self.table_name.clone()));
+        res.insert(String::from("conditions"),
+                   JsonValue::Array(self.conditions.iter().map(|condition| condition.to_json()).collect()));
+        JsonValue::Object(res)
+    }
+}
+
+impl QueryEventType {
+    fn name(&self) -> &'static str {
+        match *self {
+            QueryEventType::Initial => "initial",
+            QueryEventType::Added => "add",
+            QueryEventType::Updated => "update",
+            QueryEventType::Removed => "remove",
+        }
+    }
+}
+
+impl ToJson for QueryEvent {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("query_id"), JsonValue::U64(self.query_id));
+        res.insert(String::from("seq"), JsonValue::U64(self.seq));
+        res.insert(String::from("type"), JsonValue::String(String::from(self.event_type.name())));
+        res.insert(String::from("key"), self.key.clone());
+        res.insert(String::from("value"), self.value.clone());
+        JsonValue::Object(res)
+    }
+}
+
+impl ContinuousQueryManager {
+    pub fn new() -> ContinuousQueryManager {
+        ContinuousQueryManager {
+            counter: AtomicUsize::new(1),
+            registrations: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // Register query, events for it will be sent to returned receiver until cancel or receiver drop
+    pub fn register(&self, query: ContinuousQuery) -> (u64, Receiver<QueryEvent>) {
+        let id = self.counter.fetch_add(1, AtomicOrdering::SeqCst) as u64;
+        let (sender, receiver) = channel();
+        debug!("Register continuous query {} = {:?}", id, query);
+        self.registrations.lock().unwrap().insert(id,
+                                                  Registration {
+                                                      query: query,
+                                                      created: Utc::now(),
+                                                      sender: sender,
+                                                  });
+        (id, receiver)
+    }
+
+    pub fn cancel(&self, id: u64) -> bool {
+        debug!("Cancel continuous query {}", id);
+        self.registrations.lock().unwrap().remove(&id).is_some()
+    }
+
+    pub fn get_queries_list(&self) -> JsonValue {
+        JsonValue::Array(self.registrations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, registration)| {
+                let mut res = BTreeMap::<String, JsonValue>::new();
+                res.insert(String::from("id"), JsonValue::U64(id.clone()));
+                res.insert(String::from("query"), registration.query.to_json());
+                res.insert(String::from("created"), JsonValue::String(registration.created.to_rfc3339()));
+                JsonValue::Object(res)
+            })
+            .collect())
+    }
+
+    // Send events about committed changes. Queries with disconnected subscribers are dropped
+    pub fn notify(&self, changes: &Vec<ChangeRecord>) {
+        let mut registrations = self.registrations.lock().unwrap();
+        if registrations.is_empty() {
+            return;
+        }
+        let disconnected: Vec<u64> = registrations.iter()
+            .filter(|&(id, registration)| {
+                changes.iter()
+                    .filter_map(|change| registration.query.get_event(id.clone(), change))
+                    .any(|event| registration.sender.send(event).is_err())
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in disconnected {
+            debug!("Subscriber of continuous query {} disconnected", id);
+            registrations.remove(&id);
+        }
+    }
+}
+
+impl QueryEventStream {
+    pub fn new(query_manager: Arc<ContinuousQueryManager>,
+               query_id: u64,
+               initial: Vec<QueryEvent>,
+               receiver: Receiver<QueryEvent>)
+               -> QueryEventStream {
+        let mut stream = QueryEventStream {
+            query_manager: query_manager,
+            query_id: query_id,
+            initial: initial.into_iter().collect(),
+            receiver: receiver,
+            pending: Vec::new(),
+            closed: false,
+        };
+        if stream.initial.is_empty() {
+            stream.push_ready();
+        }
+        stream
+    }
+
+    // Marks end of initial entries
+    fn push_ready(&mut self) {
+        let data = format!("event: ready\ndata: {{\"query_id\": {}}}\n\n", self.query_id);
+        self.pending.extend_from_slice(data.as_bytes());
+    }
+
+    fn push_event(&mut self, event: &QueryEvent) {
+        let data = format!("event: {}\ndata: {}\n\n", event.event_type.name(), event.to_json());
+        self.pending.extend_from_slice(data.as_bytes());
+    }
+
+    fn fill_pending(&mut self) {
+        if let Some(event) = self.initial.pop_front() {
+            self.push_event(&event);
+            if self.initial.is_empty() {
+                self.push_ready();
+            }
+            return;
+        }
+        match self.receiver.recv_timeout(Duration::from_secs(STREAM_KEEP_ALIVE_SECS)) {
+            Ok(event) => self.push_event(&event),
+            Err(RecvTimeoutError::Timeout) => self.pending.extend_from_slice(b": keep-alive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => {
+                debug!("Continuous query {} is cancelled, close stream", self.query_id);
+                self.closed = true;
+            }
+        }
+    }
+}
+
+impl Read for QueryEventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() && !self.closed {
+            self.fill_pending();
+        }
+        let size = cmp::min(buf.len(), self.pending.len());
+        buf[..size].copy_from_slice(&self.pending[..size]);
+        self.pending.drain(..size);
+        Ok(size)
+    }
+}
+
+impl Drop for QueryEventStream {
+    fn drop(&mut self) {
+        self.query_manager.cancel(self.query_id);
+    }
+}
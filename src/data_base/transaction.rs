@@ -1,22 +1,34 @@
 
-extern crate rustless;
+extern crate chrono;
 
-use rustless::json::JsonValue;
+use data_base::json::JsonValue;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::fmt;
-use std::sync::{Mutex, MutexGuard, Condvar};
+use std::sync::{Mutex, MutexGuard, Condvar, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
 use concurrent_hashmap::*;
 
 use data_base::{DataBaseManager, Entity, PersistenceError, Table};
+use data_base::changes::{ChangeLog, DEFAULT_CHANGE_LOG_CAPACITY};
+use data_base::metrics::Metrics;
+use data_base::query::ContinuousQueryManager;
+use data_base::prepared::{PreparedChange, PreparedLog, PreparedTransaction};
 
-const DEFAULT_TX_ID: u32 = 0;
+use self::chrono::prelude::*;
+
+pub const DEFAULT_TX_ID: u64 = 0; // never given to started transaction
+const TX_ID_RESERVE: u64 = 10000; // count of ids, reserved by one write to prepared log
+pub const DEFAULT_LOCK_WAIT_SECS: u64 = 30; // wait of pessimistic transaction for lock of other transaction
 
 #[derive(Debug, Clone)]
 pub struct Lock {
     pub lock_type: LockType,
-    pub tx_id: u32,
+    pub tx_id: u64,
     condition: Arc<(Mutex<bool>, Condvar)>,
 }
 
@@ -36,12 +48,34 @@ struct LockedKey {
 pub struct LockedValue {
     reference: Option<Arc<Mutex<Entity>>>, // reference to entity in table, if value is new, then none
     pub value: Entity, // actual value in tx
+    pub removed: bool, // true - entity will be removed from table on commit
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum LockMode {
     Optimistic,
     Pessimistic,
+    ReadOnly, // reads last committed values without locks, changes are rejected
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum IsolationLevel {
+    ReadCommitted, // reads don't lock, next read of same key can see newer committed value
+    RepeatableRead, // read keys are locked until end of transaction
+    Serializable, // as repeatable read, plus scans of table exclude inserts of new keys until end of transaction
+}
+
+#[derive(Debug)]
+struct RangeLockState {
+    scanners: HashSet<u64>, // serializable transactions, that read table range
+    inserters: HashSet<u64>, // transactions, that insert new keys into table
+}
+
+// Lock of whole table against phantoms. Conflicting scan or insert fails immediately,
+// because serializable transactions can take it in both roles and would wait each other forever
+#[derive(Debug)]
+pub struct RangeLock {
+    state: Mutex<RangeLockState>,
 }
 
 #[derive(Debug)]
@@ -49,18 +83,60 @@ pub enum TransactionError {
     LockFailed,
 }
 
+// Record of undo log, that allows rollback to savepoint
+#[derive(Debug)]
+enum UndoRecord {
+    Locked(LockedKey), // key was locked or created by transaction
+    Changed(LockedKey, LockedValue), // previous value of key in transaction
+    RangeLocked(Arc<RangeLock>, bool), // range lock of table was taken for scan (true) or insert (false)
+}
+
+// Data of transaction for monitoring, available without lock of transaction,
+// because transaction is locked, while it waits for lock of entity
+struct TransactionInfo {
+    lock_mode: LockMode,
+    isolation_level: IsolationLevel,
+    start_time: DateTime<Utc>,
+    started: Instant,
+    locked_keys: Arc<ConcHashMap<LockedKey, LockedValue>>, // same map as in transaction
+    waiting_for: Mutex<Option<(u64, Arc<(Mutex<bool>, Condvar)>)>>, // owner of awaited entity lock and its condition
+    killed: AtomicBool, // true - transaction is killed by operator and must stop waiting
+    prepared: AtomicBool, // same as prepared flag of transaction, prepared transaction can't be killed
+}
+
 // Struct for store data of transaction
 pub struct Transaction {
-    id: u32,
+    id: u64,
     on: bool, // true - transaction is executed
+    prepared: bool, // true - changes are recorded in prepared log, transaction waits for decision of coordinator
     lock_mode: LockMode,
+    isolation_level: IsolationLevel,
     locked_keys: Arc<ConcHashMap<LockedKey, LockedValue>>, // keys and refs to values of locked entities
+    range_locks: Mutex<Vec<Arc<RangeLock>>>, // tables, where transaction scanned or inserted
+    undo_log: Mutex<Vec<UndoRecord>>, // filled only while transaction has savepoints
+    savepoints: Mutex<Vec<(String, usize)>>, // name and length of undo log at moment of savepoint
+    info: Arc<TransactionInfo>,
+}
+
+// Ids are never reused: counter doesn't wrap and, with prepared log, continues after restart from reserved limit
+struct TxIdCounter {
+    next: u64,
+    reserved: u64, // ids below limit can be given without write to prepared log
 }
 
 // Transactions data driver
 pub struct TransactionManager {
-    counter: Arc<Mutex<u32>>, // beacause need check overflow and get new value - AtomicUsize is not relevant
-    transactions: ConcHashMap<u32, Arc<Mutex<Transaction>>>,
+    counter: Arc<Mutex<TxIdCounter>>, // beacause need check overflow and get new value - AtomicUsize is not relevant
+    transactions: ConcHashMap<u64, Arc<Mutex<Transaction>>>,
+    infos: ConcHashMap<u64, Arc<TransactionInfo>>, // monitoring data of running transactions
+    change_log: Arc<ChangeLog>, // changes of committed transactions
+    query_manager: Arc<ContinuousQueryManager>, // subscribers of changes
+    prepared_log: Option<PreparedLog>, // none - two-phase commit is not available
+    in_doubt: ConcHashMap<u64, PreparedTransaction>, // prepared before restart and not resolved yet
+    in_doubt_keys: RwLock<HashMap<(String, String), u64>>, // table and json of keys of in doubt transactions, with owner tx
+    metrics: Arc<Metrics>, // counters of transactions and locks, shared with data base manager
+    lock_wait_millis: AtomicUsize, // 0 - pessimistic transaction waits for lock without timeout
+    publish_lock: RwLock<()>, // commits apply and publish changes under read lock, so snapshot under write lock matches seq
 }
 
 impl Lock {
@@ -79,6 +155,95 @@ impl Lock {
     }
 }
 
+impl LockMode {
+    pub fn from_str(mode: &str) -> Result<LockMode, String> {
+        match mode {
+            "optimistic" => Ok(LockMode::Optimistic),
+            "pessimistic" => Ok(LockMode::Pessimistic),
+            "read_only" => Ok(LockMode::ReadOnly),
+            _ => Err(format!("Unknown LockMode {}", mode)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LockMode::Optimistic => "optimistic",
+            LockMode::Pessimistic => "pessimistic",
+            LockMode::ReadOnly => "read_only",
+        }
+    }
+}
+
+impl IsolationLevel {
+    pub fn from_str(level: &str) -> Result<IsolationLevel, String> {
+        match level {
+            "read_committed" => Ok(IsolationLevel::ReadCommitted),
+            "repeatable_read" => Ok(IsolationLevel::RepeatableRead),
+            "serializable" => Ok(IsolationLevel::Serializable),
+            _ => Err(format!("Unknown IsolationLevel {}", level)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            IsolationLevel::ReadCommitted => "read_committed",
+            IsolationLevel::RepeatableRead => "repeatable_read",
+            IsolationLevel::Serializable => "serializable",
+        }
+    }
+}
+
+impl RangeLock {
+    pub fn new() -> RangeLock {
+        RangeLock {
+            state: Mutex::new(RangeLockState {
+                scanners: HashSet::new(),
+                inserters: HashSet::new(),
+            }),
+        }
+    }
+
+    /** Take lock for scan or insert
+     * return - true, if transaction didn't hold lock in this role before */
+    fn acquire(&self, tx_id: u64, scan: bool) -> Result<bool, PersistenceError> {
+        let mut state = self.state.lock().unwrap();
+        let conflict = {
+            let others = if scan { &state.inserters } else { &state.scanners };
+            others.iter().any(|id| *id != tx_id)
+        };
+        if conflict {
+            return Err(PersistenceError::LockFailed(if scan {
+                String::from("range lock failed: table has uncommitted inserts")
+            } else {
+                String::from("range lock failed: table is scanned by serializable transaction")
+            }));
+        }
+        if scan {
+            Ok(state.scanners.insert(tx_id))
+        } else {
+            Ok(state.inserters.insert(tx_id))
+        }
+    }
+
+    /** Release lock in one role
+     * return - true, if transaction doesn't hold lock in other role */
+    fn release_role(&self, tx_id: u64, scan: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if scan {
+            state.scanners.remove(&tx_id);
+        } else {
+            state.inserters.remove(&tx_id);
+        }
+        !state.scanners.contains(&tx_id) && !state.inserters.contains(&tx_id)
+    }
+
+    fn release(&self, tx_id: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.scanners.remove(&tx_id);
+        state.inserters.remove(&tx_id);
+    }
+}
+
 impl PartialEq for Lock {
     fn eq(&self, other: &Lock) -> bool {
         self.tx_id == other.tx_id && self.lock_type == other.lock_type
@@ -110,15 +275,16 @@ impl LockedValue {
 impl fmt::Debug for LockedValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f,
-               "{{reference = {:?}, value = {:?} }}",
+               "{{reference = {:?}, value = {:?}, removed = {} }}",
                self.reference,
-               self.value)
+               self.value,
+               self.removed)
     }
 }
 
 impl PartialEq for LockedValue {
     fn eq(&self, other: &LockedValue) -> bool {
-        self.value == other.value
+        self.value == other.value && self.removed == other.removed
     }
 }
 
@@ -133,30 +299,192 @@ impl Hash for LockedValue {
 impl TransactionManager {
     pub fn new() -> TransactionManager {
         TransactionManager {
-            counter: Arc::new(Mutex::new(1)),
-            transactions: ConcHashMap::<u32, Arc<Mutex<Transaction>>>::new(),
+            counter: Arc::new(Mutex::new(TxIdCounter {
+                next: 1,
+                reserved: u64::max_value(),
+            })),
+            transactions: ConcHashMap::<u64, Arc<Mutex<Transaction>>>::new(),
+            infos: ConcHashMap::<u64, Arc<TransactionInfo>>::new(),
+            change_log: Arc::new(ChangeLog::new(DEFAULT_CHANGE_LOG_CAPACITY)),
+            query_manager: Arc::new(ContinuousQueryManager::new()),
+            prepared_log: None,
+            in_doubt: ConcHashMap::<u64, PreparedTransaction>::new(),
+            in_doubt_keys: RwLock::new(HashMap::new()),
+            metrics: Arc::new(Metrics::new()),
+            lock_wait_millis: AtomicUsize::new((DEFAULT_LOCK_WAIT_SECS * 1000) as usize),
+            publish_lock: RwLock::new(()),
+        }
+    }
+
+    /** Max wait of pessimistic transaction for lock, None - without timeout */
+    pub fn set_lock_wait(&self, wait: Option<Duration>) {
+        let millis = wait.map_or(0, |wait| cmp::max(1, wait.as_secs() * 1000 + (wait.subsec_nanos() / 1000000) as u64));
+        self.lock_wait_millis.store(millis as usize, Ordering::SeqCst);
+    }
+
+    fn lock_wait(&self) -> Option<Duration> {
+        match self.lock_wait_millis.load(Ordering::SeqCst) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis as u64)),
         }
     }
 
-    pub fn get_transactions_list(&self) -> rustless::json::JsonValue {
-        JsonValue::Array(self.transactions
+    /** Manager with durable log of prepared transactions.
+     * Transactions, that were prepared before restart, are recovered as in doubt */
+    pub fn with_prepared_log(prepared_log: PreparedLog) -> Result<TransactionManager, PersistenceError> {
+        let recovered = try!(prepared_log.read_all().map_err(|err| PersistenceError::PreparedLogFailed(err)));
+        let reserved = try!(prepared_log.read_tx_id_limit().map_err(|err| PersistenceError::PreparedLogFailed(err)));
+        let mut tx_manager = TransactionManager::new();
+        {
+            // Ids reserved before restart could be given, so next id starts from limit
+            let mut counter = tx_manager.counter.lock().unwrap();
+            counter.next = cmp::max(counter.next, reserved);
+            counter.reserved = counter.next;
+        }
+        for prepared in recovered {
+            warn!("Tx {} is in doubt after restart, changes = {}",
+                  prepared.tx_id,
+                  prepared.changes.len());
+            // New transactions must not reuse ids of in doubt transactions
+            {
+                let mut counter = tx_manager.counter.lock().unwrap();
+                if counter.next <= prepared.tx_id {
+                    counter.next = prepared.tx_id.saturating_add(1);
+                    counter.reserved = counter.next;
+                }
+            }
+            // Keys stay locked until decision of coordinator, so other transactions can't change them before replay
+            tx_manager.reserve_in_doubt_keys(&prepared, prepared.tx_id);
+            tx_manager.in_doubt.insert(prepared.tx_id, prepared);
+        }
+        tx_manager.prepared_log = Some(prepared_log);
+        Ok(tx_manager)
+    }
+
+    pub fn get_change_log(&self) -> Arc<ChangeLog> {
+        self.change_log.clone()
+    }
+
+    pub fn get_query_manager(&self) -> Arc<ContinuousQueryManager> {
+        self.query_manager.clone()
+    }
+
+    /** Execute operation, while no transaction applies its changes, so committed values, that operation reads,
+     * contain exactly changes with sequence number up to given last seq of change log */
+    pub fn without_commits<T, F>(&self, operation: F) -> T
+        where F: FnOnce(u64) -> T
+    {
+        let _publishing = self.publish_lock.write().unwrap();
+        operation(self.change_log.last_seq())
+    }
+
+    pub fn get_metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    pub fn get_active_count(&self) -> usize {
+        self.infos.iter().count()
+    }
+
+    // Running transactions with lock mode, age, count of locked keys per table and awaited transaction
+    pub fn get_transactions_list(&self) -> JsonValue {
+        let mut infos: Vec<(u64, Arc<TransactionInfo>)> = self.infos
             .iter()
-            .map(|(id, _)| rustless::json::JsonValue::U64(id.clone() as u64))
+            .map(|(id, info)| (id.clone(), info.clone()))
+            .collect();
+        infos.sort_by_key(|&(id, _)| id);
+        JsonValue::Array(infos.iter()
+            .map(|&(id, ref info)| JsonValue::Object(TransactionManager::get_info_json(id, info)))
             .collect())
     }
 
-    pub fn get_tx_id(&self) -> u32 {
-        let counter = self.counter.clone();
-        let mut counter_mut = counter.lock().unwrap();
-        if counter_mut.eq(&u32::max_value()) {
-            *counter_mut = 1;
+    /** Details of transaction, like in list, plus locked keys per table
+     * return - none, if transaction is not running */
+    pub fn get_transaction_json(&self, data_base_manager: &DataBaseManager, id: &u64) -> Option<JsonValue> {
+        let info = match self.infos.find(id) {
+            Some(info) => info.get().clone(),
+            None => return None,
         };
-        let res = counter_mut.clone();
-        *counter_mut = *counter_mut + 1;
+        let mut res = TransactionManager::get_info_json(id.clone(), &info);
+        let mut keys = BTreeMap::<String, JsonValue>::new();
+        for (locked_key, _) in info.locked_keys.iter() {
+            let key_json = data_base_manager.get_table(&locked_key.table_name)
+                .and_then(|table| Table::entity_to_json(&locked_key.key, &table.description.key).ok())
+                .unwrap_or(JsonValue::Null);
+            match keys.entry(locked_key.table_name.clone()).or_insert(JsonValue::Array(vec![])) {
+                &mut JsonValue::Array(ref mut table_keys) => table_keys.push(key_json),
+                _ => {}
+            }
+        }
+        res.insert(String::from("keys"), JsonValue::Object(keys));
+        Some(JsonValue::Object(res))
+    }
+
+    fn get_info_json(id: u64, info: &TransactionInfo) -> BTreeMap<String, JsonValue> {
+        let mut locked_counts = BTreeMap::<String, u64>::new();
+        for (locked_key, _) in info.locked_keys.iter() {
+            *locked_counts.entry(locked_key.table_name.clone()).or_insert(0) += 1;
+        }
+        let age = info.started.elapsed();
+        let waiting_for = info.waiting_for.lock().unwrap().as_ref().map(|&(owner, _)| JsonValue::U64(owner));
+        let mut res = BTreeMap::<String, JsonValue>::new();
+        res.insert(String::from("tx_id"), JsonValue::U64(id));
+        res.insert(String::from("lock_mode"), JsonValue::String(String::from(info.lock_mode.name())));
+        res.insert(String::from("isolation_level"),
+                   JsonValue::String(String::from(info.isolation_level.name())));
+        res.insert(String::from("start_time"), JsonValue::String(info.start_time.to_rfc3339()));
+        res.insert(String::from("age_ms"),
+                   JsonValue::U64(age.as_secs() * 1000 + (age.subsec_nanos() / 1000000) as u64));
+        res.insert(String::from("locked_keys"),
+                   JsonValue::Object(locked_counts.into_iter().map(|(table_name, count)| (table_name, JsonValue::U64(count))).collect()));
+        res.insert(String::from("waiting_for"), waiting_for.unwrap_or(JsonValue::Null));
+        res
+    }
+
+    /** Rollback of transaction by operator. Transaction, that waits for lock, is woken up and fails.
+     * Prepared transaction is finished only by coordinator, so it is not killed */
+    pub fn kill(&self, id: &u64) -> Result<(), PersistenceError> {
+        let info = try!(self.infos.find(id).map(|info| info.get().clone()).ok_or(PersistenceError::UndefinedTransaction(id.clone())));
+        if info.prepared.load(Ordering::SeqCst) {
+            return Err(PersistenceError::TransactionPrepared(id.clone()));
+        }
+        warn!("Kill tx {}", id);
+        info.killed.store(true, Ordering::SeqCst);
+        // Guard of waiting_for is released before lock of condition, waiting transaction takes them in reverse order
+        let waiting_for = info.waiting_for.lock().unwrap().clone();
+        if let Some((_, condition)) = waiting_for {
+            let &(ref lock_var, ref condvar) = &*condition;
+            let _locked = lock_var.lock().unwrap();
+            condvar.notify_all();
+        }
+        let res = self.rollback(id);
+        // Transaction was prepared after check, it stays alive for coordinator
+        if let Err(PersistenceError::TransactionPrepared(_)) = res {
+            info.killed.store(false, Ordering::SeqCst);
+        }
         res
     }
 
-    pub fn get_tx(&self, tx_id: &u32) -> Result<Arc<Mutex<Transaction>>, PersistenceError> {
+    pub fn get_tx_id(&self) -> Result<u64, PersistenceError> {
+        let counter = self.counter.clone();
+        let mut counter_mut = counter.lock().unwrap();
+        if counter_mut.next == u64::max_value() {
+            return Err(PersistenceError::TransactionFailed(String::from("tx ids are exhausted")));
+        }
+        if counter_mut.next >= counter_mut.reserved {
+            if let Some(ref prepared_log) = self.prepared_log {
+                let reserved = counter_mut.next.saturating_add(TX_ID_RESERVE);
+                try!(prepared_log.write_tx_id_limit(reserved).map_err(|err| PersistenceError::PreparedLogFailed(err)));
+                debug!("Tx ids reserved up to {}", reserved);
+                counter_mut.reserved = reserved;
+            }
+        }
+        let res = counter_mut.next;
+        counter_mut.next += 1;
+        Ok(res)
+    }
+
+    pub fn get_tx(&self, tx_id: &u64) -> Result<Arc<Mutex<Transaction>>, PersistenceError> {
         match self.transactions.find(&tx_id) {
             Some(transaction) => {
                 debug!("Found tx with id = {}", tx_id);
@@ -169,14 +497,34 @@ impl TransactionManager {
         }
     }
 
-    pub fn start(&self, lock_mode: LockMode) -> Result<u32, PersistenceError> {
-        let id = self.get_tx_id();
-        debug!("Try start tx id = {}, mode = {:?}", id, lock_mode);
+    pub fn start(&self, lock_mode: LockMode, isolation_level: IsolationLevel) -> Result<u64, PersistenceError> {
+        let id = try!(self.get_tx_id());
+        debug!("Try start tx id = {}, mode = {:?}, isolation level = {:?}",
+               id,
+               lock_mode,
+               isolation_level);
+        let locked_keys = Arc::new(ConcHashMap::<LockedKey, LockedValue>::new());
+        let info = Arc::new(TransactionInfo {
+            lock_mode: lock_mode.clone(),
+            isolation_level: isolation_level.clone(),
+            start_time: Utc::now(),
+            started: Instant::now(),
+            locked_keys: locked_keys.clone(),
+            waiting_for: Mutex::new(None),
+            killed: AtomicBool::new(false),
+            prepared: AtomicBool::new(false),
+        });
         let transaction = Arc::new(Mutex::new(Transaction {
             id: id,
             on: true,
+            prepared: false,
             lock_mode: lock_mode,
-            locked_keys: Arc::new(ConcHashMap::<LockedKey, LockedValue>::new()),
+            isolation_level: isolation_level,
+            locked_keys: locked_keys,
+            range_locks: Mutex::new(Vec::new()),
+            undo_log: Mutex::new(Vec::new()),
+            savepoints: Mutex::new(Vec::new()),
+            info: info.clone(),
         }));
         match self.transactions.insert(id, transaction) {
             Some(_) => {
@@ -184,32 +532,235 @@ impl TransactionManager {
                 Err(PersistenceError::TransactionAlreadyStarted(id))
             }
             None => {
+                self.infos.insert(id, info);
+                self.metrics.transaction_started();
                 debug!("Tx with id = {} started", id);
                 Ok(id)
             }
         }
     }
 
-    pub fn stop(&self, data_base_manager: &DataBaseManager, id: &u32) -> Result<(), PersistenceError> {
+    pub fn stop(&self, data_base_manager: &DataBaseManager, id: &u64) -> Result<(), PersistenceError> {
+        try!(self.check_not_prepared(id));
+        self.commit(data_base_manager, id)
+    }
+
+    fn is_prepared(&self, id: &u64) -> Result<bool, PersistenceError> {
+        let transaction = try!(self.get_tx(id));
+        let locked_transaction = transaction.lock().unwrap();
+        Ok(locked_transaction.prepared)
+    }
+
+    // Prepared transaction is finished only by decision of coordinator
+    fn check_not_prepared(&self, id: &u64) -> Result<(), PersistenceError> {
+        if try!(self.is_prepared(id)) {
+            Err(PersistenceError::TransactionPrepared(id.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_prepared_log(&self) -> Result<&PreparedLog, PersistenceError> {
+        self.prepared_log.as_ref().ok_or(PersistenceError::PreparedLogFailed(String::from("prepared log is not configured")))
+    }
+
+    /** First phase of two-phase commit: validate changes and durably record them.
+     * Locks are kept, transaction can't be changed after prepare */
+    pub fn prepare(&self, data_base_manager: &DataBaseManager, id: &u64) -> Result<(), PersistenceError> {
+        let prepared_log = try!(self.get_prepared_log());
+        let transaction = try!(self.get_tx(id));
+        let mut locked_transaction = transaction.lock().unwrap();
+        if locked_transaction.prepared {
+            return Err(PersistenceError::TransactionPrepared(id.clone()));
+        }
+        let mut changes = Vec::new();
+        for (locked_key, locked_value) in locked_transaction.locked_keys.iter() {
+            if locked_value.reference.is_none() && locked_value.removed {
+                continue;
+            }
+            let table = try!(data_base_manager.get_table(&locked_key.table_name)
+                .ok_or(PersistenceError::TableNotFound(locked_key.table_name.clone())));
+            let key = try!(Table::entity_to_json(&locked_key.key, &table.description.key).map_err(|err| PersistenceError::IoEntity(err)));
+            let value = if locked_value.removed {
+                None
+            } else {
+                Some(try!(Table::entity_to_json(&locked_value.value, &table.description.value).map_err(|err| PersistenceError::IoEntity(err))))
+            };
+            changes.push(PreparedChange {
+                table_name: locked_key.table_name.clone(),
+                key: key,
+                value: value,
+            });
+        }
+        let prepared = PreparedTransaction {
+            tx_id: id.clone(),
+            changes: changes,
+        };
+        try!(prepared_log.write(&prepared).map_err(|err| PersistenceError::PreparedLogFailed(err)));
+        locked_transaction.prepared = true;
+        locked_transaction.info.prepared.store(true, Ordering::SeqCst);
+        debug!("Tx {} prepared, changes = {}", id, prepared.changes.len());
+        Ok(())
+    }
+
+    /** Second phase of two-phase commit for transaction, that was prepared in this run
+     * return - false, if transaction is not active, but can be in doubt */
+    pub fn commit_prepared(&self, data_base_manager: &DataBaseManager, id: &u64) -> Result<bool, PersistenceError> {
+        let prepared_log = try!(self.get_prepared_log());
+        if self.transactions.find(id).is_none() {
+            return Ok(false);
+        }
+        if !try!(self.is_prepared(id)) {
+            return Err(PersistenceError::TransactionNotPrepared(id.clone()));
+        }
+        try!(self.commit(data_base_manager, id));
+        try!(prepared_log.remove(id.clone()).map_err(|err| PersistenceError::PreparedLogFailed(err)));
+        Ok(true)
+    }
+
+    /** Rollback of prepared or in doubt transaction */
+    pub fn rollback_prepared(&self, id: &u64) -> Result<(), PersistenceError> {
+        let prepared_log = try!(self.get_prepared_log());
+        match self.in_doubt.remove(id) {
+            Some(prepared) => self.release_in_doubt_keys(&prepared),
+            None => {
+                if !try!(self.is_prepared(id)) {
+                    return Err(PersistenceError::TransactionNotPrepared(id.clone()));
+                }
+                try!(self.abort(id));
+            }
+        }
+        try!(prepared_log.remove(id.clone()).map_err(|err| PersistenceError::PreparedLogFailed(err)));
+        debug!("Prepared tx {} rolled back", id);
+        Ok(())
+    }
+
+    // In doubt transaction is taken for commit, it must be returned back, if commit failed
+    pub fn take_in_doubt(&self, id: &u64) -> Option<PreparedTransaction> {
+        self.in_doubt.remove(id)
+    }
+
+    pub fn return_in_doubt(&self, prepared: PreparedTransaction) {
+        self.reserve_in_doubt_keys(&prepared, prepared.tx_id);
+        self.in_doubt.insert(prepared.tx_id, prepared);
+    }
+
+    /** Lock keys of in doubt transaction for owner - transaction itself or transaction, that replays its changes.
+     * Tables are not known while prepared log is recovered, so keys are locked by table name and json */
+    pub fn reserve_in_doubt_keys(&self, prepared: &PreparedTransaction, owner: u64) {
+        let mut in_doubt_keys = self.in_doubt_keys.write().unwrap();
+        for change in prepared.changes.iter() {
+            in_doubt_keys.insert((change.table_name.clone(), change.key.to_string()), owner);
+        }
+    }
+
+    pub fn release_in_doubt_keys(&self, prepared: &PreparedTransaction) {
+        let mut in_doubt_keys = self.in_doubt_keys.write().unwrap();
+        for change in prepared.changes.iter() {
+            in_doubt_keys.remove(&(change.table_name.clone(), change.key.to_string()));
+        }
+    }
+
+    pub fn has_in_doubt_keys(&self) -> bool {
+        !self.in_doubt_keys.read().unwrap().is_empty()
+    }
+
+    // Key of in doubt transaction can't be changed by other transactions, they fail without wait
+    pub fn check_in_doubt_key(&self, table_name: &String, key: &JsonValue, tx_id: &u64) -> Result<(), PersistenceError> {
+        match self.in_doubt_keys.read().unwrap().get(&(table_name.clone(), key.to_string())) {
+            Some(owner) if owner != tx_id => Err(PersistenceError::LockFailed(format!("key is locked by in doubt tx {}", owner))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn remove_prepared_record(&self, id: &u64) -> Result<(), PersistenceError> {
+        let prepared_log = try!(self.get_prepared_log());
+        prepared_log.remove(id.clone()).map_err(|err| PersistenceError::PreparedLogFailed(err))
+    }
+
+    // Prepared transactions of this run and in doubt transactions after restart
+    pub fn get_prepared_list(&self) -> JsonValue {
+        let mut prepared: Vec<(u64, &'static str)> = self.transactions
+            .iter()
+            .filter(|&(_, transaction)| transaction.lock().unwrap().prepared)
+            .map(|(id, _)| (id.clone(), "prepared"))
+            .collect();
+        prepared.extend(self.in_doubt.iter().map(|(id, _)| (id.clone(), "in_doubt")));
+        prepared.sort();
+        JsonValue::Array(prepared.into_iter()
+            .map(|(id, state)| {
+                let mut res = BTreeMap::<String, JsonValue>::new();
+                res.insert(String::from("tx_id"), JsonValue::U64(id));
+                res.insert(String::from("state"), JsonValue::String(String::from(state)));
+                JsonValue::Object(res)
+            })
+            .collect())
+    }
+
+    fn commit(&self, data_base_manager: &DataBaseManager, id: &u64) -> Result<(), PersistenceError> {
+        let res = self.commit_changes(data_base_manager, id);
+        self.record_end(&res, true);
+        res
+    }
+
+    // Unknown transaction is error of client, it is not counted as failed transaction
+    fn record_end(&self, res: &Result<(), PersistenceError>, commit: bool) {
+        match *res {
+            Ok(_) if commit => self.metrics.transaction_committed(),
+            Ok(_) => self.metrics.transaction_rolled_back(),
+            Err(PersistenceError::UndefinedTransaction(_)) => (),
+            Err(_) => self.metrics.transaction_failed(),
+        }
+    }
+
+    fn commit_changes(&self, data_base_manager: &DataBaseManager, id: &u64) -> Result<(), PersistenceError> {
         debug!("Begin stop tx {}", id);
+        self.infos.remove(id);
         match self.transactions.remove(&id) {
             Some(transaction) => {
                 let locked_transaction = transaction.lock().unwrap();
+                if locked_transaction.is_read_only() {
+                    debug!("Read only tx with id = {} stopped", id);
+                    return Ok(());
+                }
                 debug!("Lock tx for stop {}, tx cache size = {}",
                        locked_transaction.id,
                        locked_transaction.locked_keys.iter().count());
+                let mut changes = Vec::new();
+                let publishing = self.publish_lock.read().unwrap();
                 for (locked_key, locked_value) in locked_transaction.locked_keys.iter() {
-                    locked_value.update_reference();
-                    try!(TransactionManager::unlock_value(locked_transaction.id.clone(), locked_value));
-                    match locked_value.reference {
-                        Some(_) => {}
-                        None => {
-                            let table: Arc<Table> = data_base_manager.get_table(&locked_key.table_name).unwrap();
+                    let table: Arc<Table> = data_base_manager.get_table(&locked_key.table_name).unwrap();
+                    let old_value = locked_value.reference.as_ref().map(|reference| reference.lock().unwrap().clone());
+                    let new_value = if locked_value.removed {
+                        None
+                    } else {
+                        Some(&locked_value.value)
+                    };
+                    if let Some(change) = TransactionManager::get_change(&table, locked_key, old_value, new_value) {
+                        changes.push(change);
+                    }
+                    match (locked_value.reference.as_ref(), locked_value.removed) {
+                        (Some(_), false) => locked_value.update_reference(),
+                        (Some(_), true) => {
+                            table.raw_remove(&locked_key.key);
+                        }
+                        (None, false) => {
                             table.raw_put(locked_key.key.clone(), locked_value.value.clone());
                         }
+                        (None, true) => {}
                     }
                 }
+                // Changes must be published before unlock, else next transaction on same keys can overtake them
+                if !changes.is_empty() {
+                    let records = self.change_log.append(locked_transaction.id, changes);
+                    self.query_manager.notify(&records);
+                }
+                drop(publishing);
+                for (_, locked_value) in locked_transaction.locked_keys.iter() {
+                    try!(TransactionManager::unlock_value(locked_transaction.id.clone(), locked_value));
+                }
                 locked_transaction.locked_keys.clear();
+                locked_transaction.release_ranges();
                 debug!("Tx with id = {} stopped", id);
                 Ok(())
             }
@@ -217,8 +768,51 @@ impl TransactionManager {
         }
     }
 
-    pub fn rollback(&self, id: &u32) -> Result<(), PersistenceError> {
+    // Json representation of entity change, if value was changed by transaction
+    fn get_change(table: &Table,
+                  locked_key: &LockedKey,
+                  old_value: Option<Entity>,
+                  new_value: Option<&Entity>)
+                  -> Option<(String, JsonValue, Option<JsonValue>, Option<JsonValue>)> {
+        match (old_value.as_ref(), new_value) {
+            (Some(old_value), Some(new_value)) if old_value.fields == new_value.fields => return None,
+            (None, None) => return None,
+            _ => {}
+        }
+        let key_json = Table::entity_to_json(&locked_key.key, &table.description.key);
+        let old_json = match old_value {
+            Some(ref old_value) => Table::entity_to_json(old_value, &table.description.value).map(|json| Some(json)),
+            None => Ok(None),
+        };
+        let new_json = match new_value {
+            Some(new_value) => Table::entity_to_json(new_value, &table.description.value).map(|json| Some(json)),
+            None => Ok(None),
+        };
+        match (key_json, old_json, new_json) {
+            (Ok(key_json), Ok(old_json), Ok(new_json)) => Some((locked_key.table_name.clone(), key_json, old_json, new_json)),
+            _ => {
+                error!("Cannot convert change of key {:?} in table {} to json",
+                       locked_key.key,
+                       locked_key.table_name);
+                None
+            }
+        }
+    }
+
+    pub fn rollback(&self, id: &u64) -> Result<(), PersistenceError> {
+        try!(self.check_not_prepared(id));
+        self.abort(id)
+    }
+
+    fn abort(&self, id: &u64) -> Result<(), PersistenceError> {
+        let res = self.discard_changes(id);
+        self.record_end(&res, false);
+        res
+    }
+
+    fn discard_changes(&self, id: &u64) -> Result<(), PersistenceError> {
         debug!("Begin rollback {}", id);
+        self.infos.remove(id);
         match self.transactions.remove(&id) {
             Some(transaction) => {
                 let locked_transaction = transaction.lock().unwrap();
@@ -229,6 +823,7 @@ impl TransactionManager {
                     try!(TransactionManager::unlock_value(locked_transaction.id.clone(), locked_value));
                 }
                 locked_transaction.locked_keys.clear();
+                locked_transaction.release_ranges();
                 debug!("Tx with id = {} stopped", id);
                 Ok(())
             }
@@ -236,7 +831,32 @@ impl TransactionManager {
         }
     }
 
-    fn unlock_value(tx_id: u32, locked_value: &LockedValue) -> Result<(), PersistenceError> {
+    pub fn savepoint(&self, id: &u64, name: &String) -> Result<(), PersistenceError> {
+        let transaction = try!(self.get_tx(id));
+        let locked_transaction = transaction.lock().unwrap();
+        locked_transaction.savepoint(name);
+        debug!("Savepoint {} set in tx {}", name, id);
+        Ok(())
+    }
+
+    pub fn rollback_to_savepoint(&self, id: &u64, name: &String, release_locks: bool) -> Result<(), PersistenceError> {
+        try!(self.check_not_prepared(id));
+        let transaction = try!(self.get_tx(id));
+        let locked_transaction = transaction.lock().unwrap();
+        debug!("Rollback tx {} to savepoint {}, release locks = {}",
+               id,
+               name,
+               release_locks);
+        locked_transaction.rollback_to_savepoint(name, release_locks)
+    }
+
+    pub fn release_savepoint(&self, id: &u64, name: &String) -> Result<(), PersistenceError> {
+        let transaction = try!(self.get_tx(id));
+        let locked_transaction = transaction.lock().unwrap();
+        locked_transaction.release_savepoint(name)
+    }
+
+    fn unlock_value(tx_id: u64, locked_value: &LockedValue) -> Result<(), PersistenceError> {
         match locked_value.reference {
             Some(ref value_entity) => {
                 let mut mut_value_entity: MutexGuard<Entity> = value_entity.lock().unwrap();
@@ -248,10 +868,11 @@ impl TransactionManager {
                     Err(PersistenceError::WrongTransaction(mut_value_entity.lock.tx_id.clone(), tx_id.clone()))
                 } else {
                     let ref mut mut_lock: Lock = mut_value_entity.lock;
-                    let &(ref lock_var, _) = &*mut_lock.condition;
+                    let &(ref lock_var, ref condvar) = &*mut_lock.condition;
                     let mut locked = lock_var.lock().unwrap();
                     *locked = false;
                     mut_lock.tx_id = DEFAULT_TX_ID;
+                    condvar.notify_all();
                     Ok(())
                 }
             }
@@ -259,7 +880,7 @@ impl TransactionManager {
         }
     }
 
-    pub fn lock_value(tx_id: &u32,
+    pub fn lock_value(tx_id: &u64,
                       table: &Table,
                       locked_transaction: &Transaction,
                       key_entity: &Entity,
@@ -267,43 +888,79 @@ impl TransactionManager {
                       -> Result<Option<Entity>, PersistenceError> {
         match value_entity_opt {
             Some(value_entity) => {
-                let temp = value_entity.clone();
-                let mut mut_value_entity: MutexGuard<Entity> = temp.lock().unwrap();
-                let copy_value = mut_value_entity.clone();
-                debug!("Lock for key {} is taken; lock id on key = {}, prev tx_id = {}",
-                       Table::entity_to_json(key_entity, &table.description.key).unwrap(),
-                       mut_value_entity.lock.tx_id,
-                       mut_value_entity.lock.tx_id);
-                if mut_value_entity.lock.tx_id != *tx_id {
-                    let ref mut lock_mut = mut_value_entity.lock;
-                    let &(ref lock_var, ref condvar) = &*lock_mut.condition;
-                    let mut locked = lock_var.lock().unwrap();
-                    debug!("Current locked = {}", *locked);
-                    if *locked {
-                        match locked_transaction.lock_mode {
-                            LockMode::Optimistic => return Err(PersistenceError::TransactionFailed(String::from("lock failed"))),
-                            _ => {
-                                while *locked {
-                                    debug!("While locked = {}", *locked);
-                                    locked = condvar.wait(locked).unwrap();
+                loop {
+                    let (condition, lock_tx_id) = {
+                        let mut mut_value_entity: MutexGuard<Entity> = value_entity.lock().unwrap();
+                        debug!("Lock for key {} is taken; lock id on key = {}, tx_id = {}",
+                               Table::entity_to_json(key_entity, &table.description.key).unwrap(),
+                               mut_value_entity.lock.tx_id,
+                               tx_id);
+                        if mut_value_entity.lock.tx_id == *tx_id {
+                            debug!("Value already locked by tx");
+                            return Ok(Some(mut_value_entity.clone()));
+                        }
+                        let condition = mut_value_entity.lock.condition.clone();
+                        {
+                            let &(ref lock_var, _) = &*condition;
+                            let mut locked = lock_var.lock().unwrap();
+                            debug!("Current locked = {}", *locked);
+                            if !*locked {
+                                // Killed transaction must not take new locks, its rollback may be already started
+                                if locked_transaction.info.killed.load(Ordering::SeqCst) {
+                                    return Err(PersistenceError::TransactionKilled(tx_id.clone()));
                                 }
+                                *locked = true;
+                                mut_value_entity.lock.tx_id = tx_id.clone();
+                                // Copy is taken after lock, so it contains value of last committed transaction
+                                let copy_value = mut_value_entity.clone();
+                                locked_transaction.add_entity(table,
+                                                              key_entity.clone(),
+                                                              Some(value_entity.clone()),
+                                                              copy_value.clone());
+                                debug!("Lock for key {} is set, tx updated",
+                                       Table::entity_to_json(key_entity, &table.description.key).unwrap());
+                                return Ok(Some(copy_value));
                             }
                         }
+                        if locked_transaction.lock_mode != LockMode::Pessimistic {
+                            table.tx_manager.metrics.lock_failed();
+                            return Err(PersistenceError::LockFailed(String::from("lock failed")));
+                        }
+                        *locked_transaction.info.waiting_for.lock().unwrap() = Some((mut_value_entity.lock.tx_id, condition.clone()));
+                        (condition, mut_value_entity.lock.tx_id)
+                    };
+                    // Wait without entity guard, else owner of lock can't commit and unlock entity
+                    let wait_started = Instant::now();
+                    let lock_wait = table.tx_manager.lock_wait();
+                    let mut timed_out = false;
+                    {
+                        let &(ref lock_var, ref condvar) = &*condition;
+                        let mut locked = lock_var.lock().unwrap();
+                        while *locked && !locked_transaction.info.killed.load(Ordering::SeqCst) {
+                            debug!("While locked = {}", *locked);
+                            locked = match lock_wait {
+                                Some(lock_wait) => {
+                                    let elapsed = wait_started.elapsed();
+                                    if elapsed >= lock_wait {
+                                        timed_out = true;
+                                        break;
+                                    }
+                                    condvar.wait_timeout(locked, lock_wait - elapsed).unwrap().0
+                                }
+                                None => condvar.wait(locked).unwrap(),
+                            };
+                        }
+                    }
+                    table.tx_manager.metrics.observe_lock_wait(wait_started.elapsed());
+                    *locked_transaction.info.waiting_for.lock().unwrap() = None;
+                    if locked_transaction.info.killed.load(Ordering::SeqCst) {
+                        return Err(PersistenceError::TransactionKilled(tx_id.clone()));
+                    }
+                    if timed_out {
+                        table.tx_manager.metrics.lock_failed();
+                        return Err(PersistenceError::LockTimeout(tx_id.clone(), lock_tx_id));
                     }
-
-                    debug!("Lock taken = {}", *locked);
-                    *locked = true;
-                    debug!("Lock taken2 = {}", *locked);
-                    lock_mut.tx_id = tx_id.clone();
-                    locked_transaction.add_entity(table,
-                                                  key_entity.clone(),
-                                                  Some(value_entity.clone()),
-                                                  copy_value);
-                    debug!("Lock for key {} is set, tx updated",
-                           Table::entity_to_json(key_entity, &table.description.key).unwrap());
                 }
-                debug!("Value locked");
-                Ok(Some(mut_value_entity.clone()))
             }
             None => Ok(None),
         }
@@ -311,29 +968,200 @@ impl TransactionManager {
 }
 
 impl Transaction {
+    pub fn get_isolation_level(&self) -> &IsolationLevel {
+        &self.isolation_level
+    }
+
+    // Protect table from phantoms until end of transaction
+    pub fn lock_range(&self, range_lock: &Arc<RangeLock>) -> Result<(), PersistenceError> {
+        self.acquire_range(range_lock, true)
+    }
+
+    // Register insert of new key, fails if table is scanned by other serializable transaction
+    pub fn lock_insert(&self, range_lock: &Arc<RangeLock>) -> Result<(), PersistenceError> {
+        self.acquire_range(range_lock, false)
+    }
+
+    fn acquire_range(&self, range_lock: &Arc<RangeLock>, scan: bool) -> Result<(), PersistenceError> {
+        if try!(range_lock.acquire(self.id, scan)) {
+            {
+                let mut range_locks = self.range_locks.lock().unwrap();
+                if !range_locks.iter().any(|held| Arc::ptr_eq(held, range_lock)) {
+                    range_locks.push(range_lock.clone());
+                }
+            }
+            self.add_undo_record(|| UndoRecord::RangeLocked(range_lock.clone(), scan));
+        }
+        Ok(())
+    }
+
+    fn release_ranges(&self) {
+        for range_lock in self.range_locks.lock().unwrap().drain(..) {
+            range_lock.release(self.id);
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.lock_mode == LockMode::ReadOnly
+    }
+
+    // Error for any change in read only transaction
+    pub fn check_writable(&self) -> Result<(), PersistenceError> {
+        if self.is_read_only() {
+            Err(PersistenceError::ReadOnlyTransaction(self.id))
+        } else if self.prepared {
+            Err(PersistenceError::TransactionPrepared(self.id))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn add_entity(&self, table: &Table, key: Entity, value: Option<Arc<Mutex<Entity>>>, copy_value: Entity) -> bool {
-        self.locked_keys
-            .insert(LockedKey {
-                        table_name: table.description.name.clone(),
-                        key: key,
-                    },
+        let locked_key = LockedKey {
+            table_name: table.description.name.clone(),
+            key: key,
+        };
+        let inserted = self.locked_keys
+            .insert(locked_key.clone(),
                     LockedValue {
                         reference: value,
                         value: copy_value,
+                        removed: false,
                     })
-            .is_none()
+            .is_none();
+        if inserted {
+            self.add_undo_record(|| UndoRecord::Locked(locked_key));
+        }
+        inserted
     }
 
     fn remove_key(&self, key: LockedKey) -> bool {
         self.locked_keys.remove(&key).is_some()
     }
 
-    pub fn get_locked_value(&self, table_name: String, key: &Entity) -> Option<&LockedValue> {
+    /** Mark key, that already locked by transaction, as removed
+     * return - false, if key is not locked by transaction */
+    pub fn remove_value(&self, table_name: String, key: &Entity) -> bool {
+        let locked_key = LockedKey {
+            table_name: table_name,
+            key: key.clone(),
+        };
+        let is_new = match self.locked_keys.find_mut(&locked_key) {
+            Some(mut accessor) => {
+                let locked_value = accessor.get();
+                self.add_undo_record(|| UndoRecord::Changed(locked_key.clone(), locked_value.clone()));
+                locked_value.removed = true;
+                locked_value.reference.is_none()
+            }
+            None => return false,
+        };
+        // Entity, that created in this transaction, just is not inserted
+        if is_new {
+            self.remove_key(locked_key);
+        }
+        true
+    }
+
+    pub fn get_locked_value(&self, table_name: String, key: &Entity) -> Option<LockedValue> {
         self.locked_keys
             .find(&LockedKey {
                 table_name: table_name,
                 key: key.clone(),
             })
-            .map(|accessor| accessor.get())
+            .map(|accessor| accessor.get().clone())
+    }
+
+    // Replace value, that will be written on commit. Key must be already locked by transaction
+    pub fn update_value(&self, table_name: String, key: &Entity, value: Entity) -> bool {
+        let locked_key = LockedKey {
+            table_name: table_name,
+            key: key.clone(),
+        };
+        match self.locked_keys.find_mut(&locked_key) {
+            Some(mut accessor) => {
+                let locked_value = accessor.get();
+                self.add_undo_record(|| UndoRecord::Changed(locked_key.clone(), locked_value.clone()));
+                locked_value.value = value;
+                locked_value.removed = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn add_undo_record<F>(&self, record: F)
+        where F: FnOnce() -> UndoRecord
+    {
+        if !self.savepoints.lock().unwrap().is_empty() {
+            self.undo_log.lock().unwrap().push(record());
+        }
+    }
+
+    // Savepoint with existing name replaces it
+    pub fn savepoint(&self, name: &String) {
+        let mut savepoints = self.savepoints.lock().unwrap();
+        savepoints.retain(|&(ref savepoint, _)| savepoint != name);
+        savepoints.push((name.clone(), self.undo_log.lock().unwrap().len()));
+    }
+
+    /** Restore values of keys, that were changed after savepoint. Keys and range locks taken after savepoint are released
+     * if release_locks is true, else they stay locked with committed values. Later savepoints are dropped */
+    pub fn rollback_to_savepoint(&self, name: &String, release_locks: bool) -> Result<(), PersistenceError> {
+        let mut savepoints = self.savepoints.lock().unwrap();
+        let index = try!(savepoints.iter()
+            .position(|&(ref savepoint, _)| savepoint == name)
+            .ok_or(PersistenceError::SavepointNotFound(name.clone())));
+        let position = savepoints[index].1;
+        savepoints.truncate(index + 1);
+
+        let mut undo_log = self.undo_log.lock().unwrap();
+        let mut kept_locks = Vec::new();
+        while undo_log.len() > position {
+            match undo_log.pop().unwrap() {
+                UndoRecord::Changed(locked_key, previous_value) => {
+                    self.locked_keys.insert(locked_key, previous_value);
+                }
+                UndoRecord::Locked(locked_key) => {
+                    let locked_value = self.locked_keys.remove(&locked_key);
+                    match locked_value {
+                        Some(LockedValue { reference: Some(ref reference), .. }) if !release_locks => {
+                            let committed_value = reference.lock().unwrap().clone();
+                            self.locked_keys.insert(locked_key.clone(),
+                                                    LockedValue {
+                                                        reference: Some(reference.clone()),
+                                                        value: committed_value,
+                                                        removed: false,
+                                                    });
+                            kept_locks.push(UndoRecord::Locked(locked_key));
+                        }
+                        Some(ref locked_value) => try!(TransactionManager::unlock_value(self.id, locked_value)),
+                        None => {}
+                    }
+                }
+                UndoRecord::RangeLocked(range_lock, scan) => {
+                    if !release_locks {
+                        kept_locks.push(UndoRecord::RangeLocked(range_lock, scan));
+                    } else if range_lock.release_role(self.id, scan) {
+                        self.range_locks.lock().unwrap().retain(|held| !Arc::ptr_eq(held, &range_lock));
+                    }
+                }
+            }
+        }
+        // Kept locks still belong to savepoint and can be released by next rollback to it
+        undo_log.extend(kept_locks);
+        Ok(())
+    }
+
+    // Drop savepoint and all later savepoints
+    pub fn release_savepoint(&self, name: &String) -> Result<(), PersistenceError> {
+        let mut savepoints = self.savepoints.lock().unwrap();
+        let index = try!(savepoints.iter()
+            .position(|&(ref savepoint, _)| savepoint == name)
+            .ok_or(PersistenceError::SavepointNotFound(name.clone())));
+        savepoints.truncate(index);
+        if savepoints.is_empty() {
+            self.undo_log.lock().unwrap().clear();
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,80 @@
+// Typed access to table for embedding program.
+// Keys and values are serde structures, that are converted to json of table types
+use std::marker::PhantomData;
+
+use serde::{Serialize, Deserialize};
+use serde_json;
+
+use data_base::{DataBaseManager, IoEntityError, PersistenceError};
+use data_base::json::JsonValue;
+
+/** Table with keys of type K and values of type V. Operations take id of transaction,
+ * DEFAULT_TX_ID - operation is executed in own transaction */
+pub struct TypedTable<'a, K, V> {
+    data_base_manager: &'a DataBaseManager,
+    name: String,
+    types: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> TypedTable<'a, K, V> {
+    pub fn new(data_base_manager: &'a DataBaseManager, name: &str) -> TypedTable<'a, K, V> {
+        TypedTable {
+            data_base_manager: data_base_manager,
+            name: String::from(name),
+            types: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+}
+
+impl<'a, K: Serialize + Deserialize, V: Serialize + Deserialize> TypedTable<'a, K, V> {
+    pub fn get(&self, tx_id: &u64, key: &K) -> Result<Option<V>, PersistenceError> {
+        match try!(self.data_base_manager.get_data(tx_id, &self.name, &to_json(key))) {
+            Some(value) => from_json(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, tx_id: &u64, key: &K, value: &V) -> Result<(), PersistenceError> {
+        self.data_base_manager.add_data(tx_id, &self.name, &to_json(key), &to_json(value))
+    }
+
+    /** Remove value by key
+     * return - true, if value existed */
+    pub fn remove(&self, tx_id: &u64, key: &K) -> Result<bool, PersistenceError> {
+        self.data_base_manager.remove_data(tx_id, &self.name, &to_json(key))
+    }
+
+    pub fn list(&self, tx_id: &u64, start: u32, count: u32) -> Result<Vec<(K, V)>, PersistenceError> {
+        let entities = try!(self.data_base_manager.get_list(tx_id.clone(), &self.name, start, count));
+        entities.into_iter()
+            .map(|entity| match entity {
+                // Entity of list is pair of key and value
+                JsonValue::Array(mut pair) => {
+                    if pair.len() != 2 {
+                        return Err(write_error(format!("Wrong entity of list {:?}", pair)));
+                    }
+                    let value = pair.pop().unwrap();
+                    let key = pair.pop().unwrap();
+                    Ok((try!(from_json(key)), try!(from_json(value))))
+                }
+                entity => Err(write_error(format!("Wrong entity of list {}", entity))),
+            })
+            .collect()
+    }
+}
+
+fn write_error(message: String) -> PersistenceError {
+    PersistenceError::IoEntity(IoEntityError::Write(message))
+}
+
+fn to_json<T: Serialize>(value: &T) -> JsonValue {
+    serde_json::to_value(value)
+}
+
+fn from_json<T: Deserialize>(json: JsonValue) -> Result<T, PersistenceError> {
+    serde_json::from_value(json.clone()).map_err(|err| write_error(format!("Cannot read {}: {}", json, err)))
+}
@@ -0,0 +1,36 @@
+extern crate nimdg;
+
+use std::env;
+use std::io;
+use std::io::Write;
+use std::process;
+
+use nimdg::rest::auth::{hash_password, DEFAULT_HASH_COST};
+use nimdg::server::{ServerConfig, USAGE};
+
+// Password is read from standard input, so it is not kept in history of shell
+fn print_password_hash() -> Result<(), String> {
+    let mut password = String::new();
+    try!(io::stdin().read_line(&mut password).map_err(|err| format!("Cannot read password: {}", err)));
+    let hash = try!(hash_password(password.trim_right_matches(|symbol: char| symbol == '\n' || symbol == '\r'),
+                                  DEFAULT_HASH_COST));
+    println!("{}", hash);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let result = if args.len() == 1 && args[0] == "--hash-password" {
+        print_password_hash()
+    } else {
+        ServerConfig::from_args(&args).and_then(nimdg::server::run)
+    };
+    if let Err(err) = result {
+        let _ = writeln!(io::stderr(), "{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,519 @@
+// Memcached text protocol over one table, for services, that use memcached clients.
+// Entity keeps flags of client, expiration time and version, that is cas unique of entity.
+// Versions are given by counter of server, so new entity with the same key never gets version of removed one.
+// Server with access control requires authentication of memcached ASCII protocol:
+// first command of connection is set with data "name password" or API token, if client certificate is not mapped to principal
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use data_base::json::JsonValue;
+
+use data_base::{DataBaseManager, PersistenceError};
+use data_base::meta::{EntityDescriptionView, TableDescriptionView};
+use data_base::transaction::LockMode;
+use net::{peer_name, AccessControl, AccessGuard, ConnectionAcceptor};
+
+pub const DEFAULT_MEMCACHED_TABLE_NAME: &'static str = "memcached";
+const KEY_FIELD: &'static str = "key";
+const VALUE_FIELD: &'static str = "value";
+const FLAGS_FIELD: &'static str = "flags";
+const EXPIRE_AT_FIELD: &'static str = "expire_at"; // milliseconds since epoch, 0 - never expires
+const VERSION_FIELD: &'static str = "version"; // unique of every write, except touch
+const MAX_KEY_LENGTH: usize = 250;
+const MAX_VALUE_LENGTH: usize = 1024 * 1024;
+const MAX_RELATIVE_EXPTIME: i64 = 60 * 60 * 24 * 30; // larger exptime is unix time
+const VERSION: &'static str = "VERSION nimdg-0.1.0";
+
+pub struct MemcachedServer {
+    listener: TcpListener,
+    data_base_manager: Arc<DataBaseManager>,
+    table_name: String,
+    acceptor: Option<Arc<ConnectionAcceptor>>,
+    cas_counter: Arc<Mutex<u64>>, // last given version
+    access_control: Option<Arc<AccessControl>>,
+}
+
+// Value of entity in table
+#[derive(Debug, Clone)]
+struct Item {
+    value: String,
+    flags: u64,
+    expire_at: u64,
+    version: u64,
+}
+
+// Way to store value, cas stores only if version of entity is not changed
+#[derive(Debug, Clone, Copy)]
+enum StoreMode {
+    Set,
+    Add,
+    Replace,
+    Cas(u64),
+}
+
+impl MemcachedServer {
+    /** Bind listener and create table, if it doesn't exist.
+     * Existing table must have the same fields, as created one */
+    pub fn bind<A: ToSocketAddrs>(address: A, data_base_manager: Arc<DataBaseManager>, table_name: &str) -> Result<MemcachedServer, String> {
+        try!(MemcachedServer::create_table(&data_base_manager, table_name));
+        let last_version = try!(MemcachedServer::get_last_version(&data_base_manager, table_name));
+        let listener = try!(TcpListener::bind(address).map_err(|err| format!("Cannot bind memcached listener: {}", err)));
+        info!("Memcached listens on {:?} for table {}", listener.local_addr(), table_name);
+        Ok(MemcachedServer {
+            listener: listener,
+            data_base_manager: data_base_manager,
+            table_name: String::from(table_name),
+            acceptor: None,
+            cas_counter: Arc::new(Mutex::new(last_version)),
+            access_control: None,
+        })
+    }
+
+    // Accepted streams are wrapped by acceptor, for example into TLS
+    pub fn with_acceptor(mut self, acceptor: Arc<ConnectionAcceptor>) -> MemcachedServer {
+        self.acceptor = Some(acceptor);
+        self
+    }
+
+    // Commands are executed only for authenticated principal with permissions for table
+    pub fn with_access_control(mut self, access_control: Arc<AccessControl>) -> MemcachedServer {
+        self.access_control = Some(access_control);
+        self
+    }
+
+    fn create_table(data_base_manager: &DataBaseManager, table_name: &str) -> Result<(), String> {
+        let mut key_fields = BTreeMap::new();
+        key_fields.insert(String::from(KEY_FIELD), String::from("string"));
+        let mut value_fields = BTreeMap::new();
+        value_fields.insert(String::from(VALUE_FIELD), String::from("string"));
+        value_fields.insert(String::from(FLAGS_FIELD), String::from("u64"));
+        value_fields.insert(String::from(EXPIRE_AT_FIELD), String::from("u64"));
+        value_fields.insert(String::from(VERSION_FIELD), String::from("u64"));
+
+        match data_base_manager.get_table_json(&String::from(table_name)) {
+            Some(table_json) => {
+                let same_fields = |entity: &str, fields: &BTreeMap<String, String>| {
+                    table_json.find(entity).and_then(|entity| entity.as_object()).map_or(false, |entity| {
+                        entity.len() == fields.len() &&
+                        fields.iter().all(|(name, type_name)| entity.get(name).and_then(|field| field.as_str()) == Some(type_name.as_str()))
+                    })
+                };
+                if same_fields("key", &key_fields) && same_fields("value", &value_fields) {
+                    Ok(())
+                } else {
+                    Err(format!("Table {} cannot be used by memcached, its description is {}", table_name, table_json))
+                }
+            }
+            None => {
+                data_base_manager.add_table(TableDescriptionView {
+                        name: String::from(table_name),
+                        key: EntityDescriptionView { fields: key_fields },
+                        value: EntityDescriptionView { fields: value_fields },
+                        lock_mode: Some(LockMode::Pessimistic),
+                    })
+                    .map(|_| ())
+            }
+        }
+    }
+
+    // Counter continues after versions of entities, that table already has
+    fn get_last_version(data_base_manager: &DataBaseManager, table_name: &str) -> Result<u64, String> {
+        let table = try!(data_base_manager.get_table(&String::from(table_name)).ok_or(format!("Table {} not found", table_name)));
+        let entries = try!(table.committed_list().map_err(|err| err.to_string()));
+        Ok(entries.iter().map(|&(_, ref value)| Item::from_json(value).version).max().unwrap_or(0))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let data_base_manager = self.data_base_manager.clone();
+                    let table_name = self.table_name.clone();
+                    let acceptor = self.acceptor.clone();
+                    let cas_counter = self.cas_counter.clone();
+                    let guard = AccessGuard::new(self.access_control.clone());
+                    thread::spawn(move || {
+                        let peer = peer_name(&stream);
+                        match acceptor {
+                            Some(acceptor) => {
+                                match acceptor.accept(stream) {
+                                    Ok((connection, principal)) => {
+                                        serve_connection(connection,
+                                                         peer,
+                                                         &data_base_manager,
+                                                         &table_name,
+                                                         &cas_counter,
+                                                         guard.with_principal(principal))
+                                    }
+                                    Err(err) => warn!("Memcached connection from {} rejected: {}", peer, err),
+                                }
+                            }
+                            None => serve_connection(stream, peer, &data_base_manager, &table_name, &cas_counter, guard),
+                        }
+                    });
+                }
+                Err(err) => error!("Memcached accept failed: {}", err),
+            }
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.run())
+    }
+}
+
+// Stream is read and written by one thread, so it may be TLS stream
+fn serve_connection<S: Read + Write>(stream: S,
+                                     peer: String,
+                                     data_base_manager: &DataBaseManager,
+                                     table_name: &String,
+                                     cas_counter: &Mutex<u64>,
+                                     mut guard: AccessGuard) {
+    debug!("Memcached connection from {}", peer);
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(err) => {
+                debug!("Cannot read command from {}: {}", peer, err);
+                break;
+            }
+        }
+        let args: Vec<&str> = line.split_whitespace().collect();
+        if args.is_empty() {
+            continue;
+        }
+        if args[0] == "quit" {
+            break;
+        }
+        let response = if guard.is_enabled() && guard.principal().is_err() {
+            login(&mut guard, &args, &mut reader)
+        } else {
+            handle(data_base_manager, table_name, cas_counter, &guard, &args, &mut reader)
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("Cannot read data from {}: {}", peer, err);
+                break;
+            }
+        };
+        if let Some(response) = response {
+            let writer = reader.get_mut();
+            if let Err(err) = writer.write_all(response.as_bytes()).and_then(|_| writer.flush()) {
+                debug!("Cannot write response to {}: {}", peer, err);
+                break;
+            }
+        }
+    }
+    debug!("Memcached connection from {} closed", peer);
+}
+
+// Data block of set is "name password" of user or API token, other commands are rejected till login
+fn login<R: BufRead>(guard: &mut AccessGuard, args: &Vec<&str>, reader: &mut R) -> io::Result<Option<String>> {
+    if args[0] != "set" {
+        return Ok(Some(String::from("CLIENT_ERROR unauthenticated\r\n")));
+    }
+    let data = match args.get(4).and_then(|length| length.parse::<usize>().ok()) {
+        Some(length) if length <= MAX_VALUE_LENGTH && args.len() == 5 => try!(read_data(reader, length)),
+        _ => return Ok(Some(String::from("CLIENT_ERROR bad command line format\r\n"))),
+    };
+    let data = match data {
+        Ok(data) => data,
+        Err(response) => return Ok(Some(response)),
+    };
+    let mut parts = data.trim().splitn(2, ' ');
+    let result = match (parts.next(), parts.next()) {
+        (Some(name), Some(password)) => guard.login(Some(name), password).map(|principal| principal.name.clone()),
+        (Some(token), None) => guard.login(None, token).map(|principal| principal.name.clone()),
+        _ => Err(String::from("Invalid credentials")),
+    };
+    Ok(Some(match result {
+        Ok(name) => {
+            debug!("Memcached connection is authenticated as {}", name);
+            String::from("STORED\r\n")
+        }
+        Err(_) => String::from("CLIENT_ERROR authentication failure\r\n"),
+    }))
+}
+
+/** Data block of storage command with \r\n after it
+ * return - value or error response of client */
+fn read_data<R: BufRead>(reader: &mut R, length: usize) -> io::Result<Result<String, String>> {
+    let mut data = vec![0u8; length + 2];
+    try!(reader.read_exact(&mut data));
+    if &data[length..] != b"\r\n" {
+        return Ok(Err(String::from("CLIENT_ERROR bad data chunk\r\n")));
+    }
+    data.truncate(length);
+    Ok(String::from_utf8(data).map_err(|_| String::from("CLIENT_ERROR value is not utf-8\r\n")))
+}
+
+/** Execute command, data block of storage commands is read from reader
+ * return - response or none for noreply */
+fn handle<R: BufRead>(data_base_manager: &DataBaseManager,
+                      table_name: &String,
+                      cas_counter: &Mutex<u64>,
+                      guard: &AccessGuard,
+                      args: &Vec<&str>,
+                      reader: &mut R)
+                      -> io::Result<Option<String>> {
+    let no_reply = args.len() > 1 && args[args.len() - 1] == "noreply";
+    let response = match args[0] {
+        "get" | "gets" if args.len() > 1 => {
+            execute(data_base_manager, table_name, cas_counter, guard, false, |store| {
+                let mut response = String::new();
+                for key in &args[1..] {
+                    if let Some(item) = try!(store.get(key)) {
+                        response.push_str(&format!("VALUE {} {} {}", key, item.flags, item.value.len()));
+                        if args[0] == "gets" {
+                            response.push_str(&format!(" {}", item.version));
+                        }
+                        response.push_str(&format!("\r\n{}\r\n", item.value));
+                    }
+                }
+                response.push_str("END\r\n");
+                Ok(response)
+            })
+        }
+        "set" | "add" | "replace" | "cas" => {
+            let (arg_count, mode) = match args[0] {
+                "set" => (5, Some(StoreMode::Set)),
+                "add" => (5, Some(StoreMode::Add)),
+                "replace" => (5, Some(StoreMode::Replace)),
+                _ => (6, args.get(5).and_then(|version| version.parse::<u64>().ok()).map(StoreMode::Cas)),
+            };
+            let length = args.get(4).and_then(|length| length.parse::<usize>().ok());
+            let (mode, length, flags, item_expire_at) = match (mode, length, args.get(2).and_then(|flags| flags.parse::<u64>().ok()),
+                   args.get(3).and_then(|exptime| exptime.parse::<i64>().ok()).and_then(expire_at)) {
+                (Some(mode), Some(length), Some(flags), Some(item_expire_at)) if length <= MAX_VALUE_LENGTH &&
+                                                                                 (args.len() == arg_count ||
+                                                                                  args.len() == arg_count + 1 && no_reply) => {
+                    (mode, length, flags, item_expire_at)
+                }
+                _ => return Ok(Some(String::from("CLIENT_ERROR bad command line format\r\n"))),
+            };
+            let value = match try!(read_data(reader, length)) {
+                Ok(value) => value,
+                Err(response) => return Ok(Some(response)),
+            };
+            execute(data_base_manager,
+                    table_name,
+                    cas_counter,
+                    guard,
+                    true,
+                    |store| store.store(args[1], &mode, value.clone(), flags, item_expire_at))
+        }
+        "delete" if args.len() == 2 || args.len() == 3 && no_reply => {
+            execute(data_base_manager, table_name, cas_counter, guard, true, |store| {
+                if try!(store.get(args[1])).is_some() {
+                    try!(store.remove(args[1]));
+                    Ok(String::from("DELETED\r\n"))
+                } else {
+                    Ok(String::from("NOT_FOUND\r\n"))
+                }
+            })
+        }
+        "incr" | "decr" if args.len() == 3 || args.len() == 4 && no_reply => {
+            match args[2].parse::<u64>() {
+                Ok(delta) => {
+                    execute(data_base_manager,
+                            table_name,
+                            cas_counter,
+                            guard,
+                            true,
+                            |store| store.increment(args[1], delta, args[0] == "incr"))
+                }
+                Err(_) => String::from("CLIENT_ERROR invalid numeric delta argument\r\n"),
+            }
+        }
+        "touch" if args.len() == 3 || args.len() == 4 && no_reply => {
+            match args[2].parse::<i64>().ok().and_then(expire_at) {
+                Some(item_expire_at) => {
+                    execute(data_base_manager, table_name, cas_counter, guard, true, |store| {
+                        match try!(store.get(args[1])) {
+                            Some(mut item) => {
+                                item.expire_at = item_expire_at;
+                                try!(store.put(args[1], &item));
+                                Ok(String::from("TOUCHED\r\n"))
+                            }
+                            None => Ok(String::from("NOT_FOUND\r\n")),
+                        }
+                    })
+                }
+                None => String::from("CLIENT_ERROR invalid exptime argument\r\n"),
+            }
+        }
+        "version" => format!("{}\r\n", VERSION),
+        _ => String::from("ERROR\r\n"),
+    };
+    Ok(if no_reply { None } else { Some(response) })
+}
+
+/** Command is executed in own transaction, so check and write of cas, add and incr are atomic.
+ * write - command changes table, else it needs only permission read */
+fn execute<F>(data_base_manager: &DataBaseManager,
+              table_name: &String,
+              cas_counter: &Mutex<u64>,
+              guard: &AccessGuard,
+              write: bool,
+              operation: F)
+              -> String
+    where F: Fn(&Store) -> Result<String, PersistenceError>
+{
+    if let Err(err) = guard.check_table(table_name, write) {
+        return format!("CLIENT_ERROR {}\r\n", err);
+    }
+    data_base_manager.auto_commit(LockMode::Pessimistic, |tx_id| {
+            operation(&Store {
+                data_base_manager: data_base_manager,
+                tx_id: tx_id,
+                table_name: table_name,
+                cas_counter: cas_counter,
+            })
+        })
+        .unwrap_or_else(|err| format!("SERVER_ERROR {}\r\n", err.to_string().replace("\r", " ").replace("\n", " ")))
+}
+
+/** Expiration time of memcached: 0 - never, up to 30 days - seconds from now, else unix time.
+ * Negative time expires entity immediately, None - time in milliseconds overflows */
+fn expire_at(exptime: i64) -> Option<u64> {
+    if exptime == 0 {
+        Some(0)
+    } else if exptime < 0 {
+        Some(1)
+    } else if exptime <= MAX_RELATIVE_EXPTIME {
+        Some(now_millis() + exptime as u64 * 1000)
+    } else {
+        (exptime as u64).checked_mul(1000)
+    }
+}
+
+fn now_millis() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    since_epoch.as_secs() * 1000 + since_epoch.subsec_nanos() as u64 / 1000000
+}
+
+impl Item {
+    fn from_json(json: &JsonValue) -> Item {
+        Item {
+            value: String::from(json.find(VALUE_FIELD).and_then(|value| value.as_str()).unwrap_or("")),
+            flags: json.find(FLAGS_FIELD).and_then(|flags| flags.as_u64()).unwrap_or(0),
+            expire_at: json.find(EXPIRE_AT_FIELD).and_then(|expire_at| expire_at.as_u64()).unwrap_or(0),
+            version: json.find(VERSION_FIELD).and_then(|version| version.as_u64()).unwrap_or(0),
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::new();
+        res.insert(String::from(VALUE_FIELD), JsonValue::String(self.value.clone()));
+        res.insert(String::from(FLAGS_FIELD), JsonValue::U64(self.flags));
+        res.insert(String::from(EXPIRE_AT_FIELD), JsonValue::U64(self.expire_at));
+        res.insert(String::from(VERSION_FIELD), JsonValue::U64(self.version));
+        JsonValue::Object(res)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expire_at != 0 && self.expire_at <= now
+    }
+}
+
+// Access to table of memcached in transaction
+struct Store<'a> {
+    data_base_manager: &'a DataBaseManager,
+    tx_id: &'a u64,
+    table_name: &'a String,
+    cas_counter: &'a Mutex<u64>,
+}
+
+impl<'a> Store<'a> {
+    fn key_json(key: &str) -> JsonValue {
+        let mut res = BTreeMap::new();
+        res.insert(String::from(KEY_FIELD), JsonValue::String(String::from(key)));
+        JsonValue::Object(res)
+    }
+
+    fn next_version(&self) -> u64 {
+        let mut cas_counter = self.cas_counter.lock().unwrap();
+        *cas_counter += 1;
+        *cas_counter
+    }
+
+    // Expired entity is removed and not returned
+    fn get(&self, key: &str) -> Result<Option<Item>, PersistenceError> {
+        if key.len() > MAX_KEY_LENGTH {
+            return Ok(None);
+        }
+        match try!(self.data_base_manager.get_data(self.tx_id, self.table_name, &Store::key_json(key))) {
+            Some(value) => {
+                let item = Item::from_json(&value);
+                if item.is_expired(now_millis()) {
+                    try!(self.remove(key));
+                    Ok(None)
+                } else {
+                    Ok(Some(item))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: &str, item: &Item) -> Result<(), PersistenceError> {
+        self.data_base_manager.add_data(self.tx_id, self.table_name, &Store::key_json(key), &item.to_json())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, PersistenceError> {
+        self.data_base_manager.remove_data(self.tx_id, self.table_name, &Store::key_json(key))
+    }
+
+    fn store(&self, key: &str, mode: &StoreMode, value: String, flags: u64, expire_at: u64) -> Result<String, PersistenceError> {
+        if key.len() > MAX_KEY_LENGTH {
+            return Ok(String::from("CLIENT_ERROR key is too long\r\n"));
+        }
+        let current = try!(self.get(key));
+        match (mode, &current) {
+            (&StoreMode::Add, &Some(_)) |
+            (&StoreMode::Replace, &None) => return Ok(String::from("NOT_STORED\r\n")),
+            (&StoreMode::Cas(_), &None) => return Ok(String::from("NOT_FOUND\r\n")),
+            (&StoreMode::Cas(version), &Some(ref item)) if item.version != version => return Ok(String::from("EXISTS\r\n")),
+            _ => {}
+        }
+        try!(self.put(key,
+                      &Item {
+                          value: value,
+                          flags: flags,
+                          expire_at: expire_at,
+                          version: self.next_version(),
+                      }));
+        Ok(String::from("STORED\r\n"))
+    }
+
+    // Decrement stops at 0, increment wraps around as in memcached
+    fn increment(&self, key: &str, delta: u64, increment: bool) -> Result<String, PersistenceError> {
+        match try!(self.get(key)) {
+            Some(mut item) => {
+                let number = match item.value.trim().parse::<u64>() {
+                    Ok(number) => number,
+                    Err(_) => return Ok(String::from("CLIENT_ERROR cannot increment or decrement non-numeric value\r\n")),
+                };
+                let number = if increment { number.wrapping_add(delta) } else { number.saturating_sub(delta) };
+                item.value = number.to_string();
+                item.version = self.next_version();
+                try!(self.put(key, &item));
+                Ok(format!("{}\r\n", number))
+            }
+            None => Ok(String::from("NOT_FOUND\r\n")),
+        }
+    }
+}
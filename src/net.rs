@@ -0,0 +1,141 @@
+// Connections of protocol listeners and clients. TCP stream may be wrapped by acceptor,
+// for example into TLS stream, listener serves connection the same way.
+// Access of connection is checked by guard, if listener has access control
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use data_base::transaction::DEFAULT_TX_ID;
+
+pub trait Connection: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Connection for T {}
+
+/** Wrapper of accepted TCP streams. Connection is closed, if accept fails.
+ * return - connection and principal, that is authenticated by acceptor, for example by client certificate */
+pub trait ConnectionAcceptor: Send + Sync {
+    fn accept(&self, stream: TcpStream) -> Result<(Box<Connection>, Option<Principal>), String>;
+}
+
+/** TLS of listeners: certificate chain and private key in PEM files.
+ * Clients of binary, memcached and RESP listeners must have certificate signed by client_ca, if it is set */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    pub certificate: String,
+    pub key: String,
+    pub client_ca: Option<String>,
+}
+
+pub fn peer_name(stream: &TcpStream) -> String {
+    stream.peer_addr().map(|addr| addr.to_string()).unwrap_or(String::from("unknown"))
+}
+
+/** Authenticated client of listener or of REST api */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    pub name: String,
+    pub roles: Vec<String>,
+}
+
+/** Access control of binary, memcached and RESP listeners, it is implemented by security of REST api,
+ * so all front-ends have the same principals, permissions and owners of transactions */
+pub trait AccessControl: Send + Sync {
+    // Principal of user by name and password or of API token, if name is none
+    fn login(&self, name: Option<&str>, secret: &str) -> Option<Principal>;
+    fn can_read(&self, principal: &Principal, table_name: &str) -> bool;
+    fn can_write(&self, principal: &Principal, table_name: &str) -> bool;
+    fn set_owner(&self, tx_id: u64, principal: &Principal);
+    fn release_owner(&self, tx_id: u64);
+    fn can_use_transaction(&self, principal: &Principal, tx_id: u64) -> bool;
+}
+
+/** Principal of connection. Listener without access control allows everything,
+ * with access control every request needs principal, that is given by login */
+pub struct AccessGuard {
+    access_control: Option<Arc<AccessControl>>,
+    principal: Option<Principal>,
+}
+
+impl AccessGuard {
+    pub fn new(access_control: Option<Arc<AccessControl>>) -> AccessGuard {
+        AccessGuard {
+            access_control: access_control,
+            principal: None,
+        }
+    }
+
+    // Principal of connection, that is authenticated by acceptor, so login is not needed
+    pub fn with_principal(mut self, principal: Option<Principal>) -> AccessGuard {
+        self.principal = principal;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.access_control.is_some()
+    }
+
+    pub fn login(&mut self, name: Option<&str>, secret: &str) -> Result<&Principal, String> {
+        let principal = {
+            let access_control = try!(self.access_control.as_ref().ok_or(String::from("Authentication is not enabled")));
+            try!(access_control.login(name, secret).ok_or(String::from("Invalid credentials")))
+        };
+        self.principal = Some(principal);
+        Ok(self.principal.as_ref().unwrap())
+    }
+
+    // Principal of connection, none if access control is not enabled
+    pub fn principal(&self) -> Result<Option<&Principal>, String> {
+        match (&self.access_control, &self.principal) {
+            (&None, _) => Ok(None),
+            (&Some(_), &Some(ref principal)) => Ok(Some(principal)),
+            (&Some(_), &None) => Err(String::from("Authentication required")),
+        }
+    }
+
+    pub fn check_table(&self, table_name: &str, write: bool) -> Result<(), String> {
+        match (&self.access_control, try!(self.principal())) {
+            (&Some(ref access_control), Some(principal)) => {
+                let allowed = if write {
+                    access_control.can_write(principal, table_name)
+                } else {
+                    access_control.can_read(principal, table_name)
+                };
+                if allowed {
+                    Ok(())
+                } else {
+                    Err(format!("Principal {} has no permission {} for table {}",
+                                principal.name,
+                                if write { "write" } else { "read" },
+                                table_name))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Implicit transaction of operation has no owner
+    pub fn check_transaction(&self, tx_id: u64) -> Result<(), String> {
+        match (&self.access_control, try!(self.principal())) {
+            (&Some(ref access_control), Some(principal)) if tx_id != DEFAULT_TX_ID => {
+                if access_control.can_use_transaction(principal, tx_id) {
+                    Ok(())
+                } else {
+                    Err(format!("Transaction {} is not owned by {}", tx_id, principal.name))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_owner(&self, tx_id: u64) {
+        if let (&Some(ref access_control), &Some(ref principal)) = (&self.access_control, &self.principal) {
+            access_control.set_owner(tx_id, principal);
+        }
+    }
+
+    pub fn release_owner(&self, tx_id: u64) {
+        if let Some(ref access_control) = self.access_control {
+            access_control.release_owner(tx_id);
+        }
+    }
+}
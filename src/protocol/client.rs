@@ -0,0 +1,206 @@
+use std::io::BufReader;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+
+use rustc_serialize::{Encodable, Decodable};
+use data_base::json::JsonValue;
+
+use data_base::transaction::{IsolationLevel, LockMode};
+use net::Connection;
+use super::*;
+
+/** Client of binary protocol. Requests may be pipelined by send and receive,
+ * typed methods send one request and wait for its response */
+pub struct BinaryClient {
+    stream: BufReader<Box<Connection>>,
+    next_request_id: u64,
+}
+
+impl BinaryClient {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> Result<BinaryClient, String> {
+        let stream = try!(TcpStream::connect(address).map_err(|err| format!("Cannot connect: {}", err)));
+        Ok(BinaryClient::with_connection(Box::new(stream)))
+    }
+
+    // Client over established connection, for example TLS stream
+    pub fn with_connection(connection: Box<Connection>) -> BinaryClient {
+        BinaryClient {
+            stream: BufReader::new(connection),
+            next_request_id: 1,
+        }
+    }
+
+    /** Send request without waiting for response
+     * return - request id, that response will carry */
+    pub fn send<T: Encodable>(&mut self, opcode: u8, request: &T) -> Result<u64, String> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let payload = try!(encode_payload(request));
+        try!(write_frame(self.stream.get_mut(), request_id, opcode, &payload).map_err(|err| format!("Cannot send request: {}", err)));
+        Ok(request_id)
+    }
+
+    /** Receive next response, responses come in order of requests
+     * return - request id and payload or error of request */
+    pub fn receive(&mut self) -> Result<(u64, Result<Vec<u8>, String>), String> {
+        let frame = try!(read_frame(&mut self.stream).map_err(|err| format!("Cannot receive response: {}", err)));
+        let frame = try!(frame.ok_or(String::from("Connection closed")));
+        match frame.code {
+            STATUS_OK => Ok((frame.request_id, Ok(frame.payload))),
+            STATUS_ERROR => {
+                let error = try!(decode_payload::<String>(&frame.payload));
+                Ok((frame.request_id, Err(error)))
+            }
+            status => Err(format!("Unknown response status {}", status)),
+        }
+    }
+
+    fn call<T: Encodable>(&mut self, opcode: u8, request: &T) -> Result<Vec<u8>, String> {
+        let request_id = try!(self.send(opcode, request));
+        let (response_id, result) = try!(self.receive());
+        if response_id != request_id {
+            return Err(format!("Response {} received for request {}", response_id, request_id));
+        }
+        result
+    }
+
+    fn call_decode<T: Encodable, R: Decodable>(&mut self, opcode: u8, request: &T) -> Result<R, String> {
+        let payload = try!(self.call(opcode, request));
+        decode_payload(&payload)
+    }
+
+    /** Log in to server with access control: name and password of user or API token, if name is none.
+     * return - name of principal */
+    pub fn auth(&mut self, name: Option<&str>, secret: &str) -> Result<String, String> {
+        self.call_decode(OP_AUTH,
+                         &AuthRequest {
+                             name: name.map(String::from),
+                             secret: String::from(secret),
+                         })
+    }
+
+    pub fn tx_start(&mut self, lock_mode: LockMode, isolation_level: IsolationLevel) -> Result<u64, String> {
+        self.call_decode(OP_TX_START,
+                         &TxStartRequest {
+                             lock_mode: String::from(lock_mode.name()),
+                             isolation_level: String::from(isolation_level.name()),
+                         })
+    }
+
+    pub fn tx_commit(&mut self, tx_id: u64) -> Result<(), String> {
+        self.call(OP_TX_COMMIT, &TxRequest { tx_id: tx_id }).map(|_| ())
+    }
+
+    pub fn tx_rollback(&mut self, tx_id: u64) -> Result<(), String> {
+        self.call(OP_TX_ROLLBACK, &TxRequest { tx_id: tx_id }).map(|_| ())
+    }
+
+    pub fn get(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<Option<JsonValue>, String> {
+        let value: Option<String> = try!(self.call_decode(OP_GET, &key_request(tx_id, table_name, key)));
+        value.as_ref().map(parse_json).map_or(Ok(None), |value| value.map(Some))
+    }
+
+    pub fn put(&mut self, tx_id: u64, table_name: &str, key: &JsonValue, value: &JsonValue) -> Result<(), String> {
+        self.call(OP_PUT,
+                  &PutRequest {
+                      tx_id: tx_id,
+                      table_name: String::from(table_name),
+                      key: key.to_string(),
+                      value: value.to_string(),
+                  })
+            .map(|_| ())
+    }
+
+    pub fn remove(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<bool, String> {
+        self.call_decode(OP_REMOVE, &key_request(tx_id, table_name, key))
+    }
+
+    pub fn put_all(&mut self,
+                   tx_id: u64,
+                   table_name: &str,
+                   entries: &Vec<(JsonValue, JsonValue)>,
+                   atomic: bool)
+                   -> Result<Vec<Result<(), String>>, String> {
+        let request = PutAllRequest {
+            tx_id: tx_id,
+            table_name: String::from(table_name),
+            entries: entries.iter().map(|&(ref key, ref value)| (key.to_string(), value.to_string())).collect(),
+            atomic: atomic,
+        };
+        let results: Vec<BatchItemResult> = try!(self.call_decode(OP_PUT_ALL, &request));
+        Ok(results.into_iter().map(|result| result.error.map_or(Ok(()), Err)).collect())
+    }
+
+    pub fn get_all(&mut self,
+                   tx_id: u64,
+                   table_name: &str,
+                   keys: &Vec<JsonValue>,
+                   atomic: bool)
+                   -> Result<Vec<Result<Option<JsonValue>, String>>, String> {
+        let results: Vec<BatchItemResult> = try!(self.call_decode(OP_GET_ALL, &keys_request(tx_id, table_name, keys, atomic)));
+        Ok(results.into_iter()
+            .map(|result| {
+                let value = try!(batch_value(result));
+                Ok(match value {
+                    JsonValue::Null => None,
+                    value => Some(value),
+                })
+            })
+            .collect())
+    }
+
+    pub fn remove_all(&mut self,
+                      tx_id: u64,
+                      table_name: &str,
+                      keys: &Vec<JsonValue>,
+                      atomic: bool)
+                      -> Result<Vec<Result<bool, String>>, String> {
+        let results: Vec<BatchItemResult> = try!(self.call_decode(OP_REMOVE_ALL, &keys_request(tx_id, table_name, keys, atomic)));
+        Ok(results.into_iter()
+            .map(|result| {
+                let value = try!(batch_value(result));
+                value.as_bool().ok_or(String::from("Wrong remove result"))
+            })
+            .collect())
+    }
+
+    pub fn scan(&mut self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<JsonValue>, String> {
+        let entities: Vec<String> = try!(self.call_decode(OP_SCAN,
+                                                          &ScanRequest {
+                                                              tx_id: tx_id,
+                                                              table_name: String::from(table_name),
+                                                              start: start,
+                                                              count: count,
+                                                          }));
+        entities.iter().map(parse_json).collect()
+    }
+}
+
+fn key_request(tx_id: u64, table_name: &str, key: &JsonValue) -> KeyRequest {
+    KeyRequest {
+        tx_id: tx_id,
+        table_name: String::from(table_name),
+        key: key.to_string(),
+    }
+}
+
+fn keys_request(tx_id: u64, table_name: &str, keys: &Vec<JsonValue>, atomic: bool) -> KeysRequest {
+    KeysRequest {
+        tx_id: tx_id,
+        table_name: String::from(table_name),
+        keys: keys.iter().map(|key| key.to_string()).collect(),
+        atomic: atomic,
+    }
+}
+
+fn parse_json(text: &String) -> Result<JsonValue, String> {
+    JsonValue::from_str(text).map_err(|err| format!("Wrong json in response {}: {}", text, err))
+}
+
+fn batch_value(result: BatchItemResult) -> Result<JsonValue, String> {
+    match (result.error, result.value) {
+        (Some(error), _) => Err(error),
+        (None, Some(value)) => parse_json(&value),
+        (None, None) => Err(String::from("Batch result has no value")),
+    }
+}
@@ -0,0 +1,161 @@
+// Binary protocol over TCP, alternative to REST API.
+// Every frame is length-prefixed: u32 length of rest of frame, u64 request id,
+// u8 opcode of request (or status of response) and bincode payload.
+// Keys and values are passed as JSON text, so they are checked by table types as in REST API.
+// Server with access control executes requests only after auth request with token or name and password
+extern crate bincode;
+
+use std::io;
+use std::io::{Read, Write};
+
+use bincode::rustc_serialize::{encode, decode};
+use rustc_serialize::{Encodable, Decodable};
+
+pub mod client;
+pub mod server;
+
+pub const OP_TX_START: u8 = 1;
+pub const OP_TX_COMMIT: u8 = 2;
+pub const OP_TX_ROLLBACK: u8 = 3;
+pub const OP_GET: u8 = 4;
+pub const OP_PUT: u8 = 5;
+pub const OP_REMOVE: u8 = 6;
+pub const OP_PUT_ALL: u8 = 7;
+pub const OP_GET_ALL: u8 = 8;
+pub const OP_REMOVE_ALL: u8 = 9;
+pub const OP_SCAN: u8 = 10;
+pub const OP_AUTH: u8 = 11;
+
+pub const STATUS_OK: u8 = 0;
+pub const STATUS_ERROR: u8 = 1;
+
+const FRAME_HEADER_SIZE: usize = 9;
+const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+// Name and password of user or API token without name
+#[derive(RustcEncodable, RustcDecodable, Clone)]
+pub struct AuthRequest {
+    pub name: Option<String>,
+    pub secret: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct TxStartRequest {
+    pub lock_mode: String,
+    pub isolation_level: String,
+}
+
+// Commit and rollback of transaction
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct TxRequest {
+    pub tx_id: u64,
+}
+
+// Get and remove of entity
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct KeyRequest {
+    pub tx_id: u64,
+    pub table_name: String,
+    pub key: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct PutRequest {
+    pub tx_id: u64,
+    pub table_name: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct PutAllRequest {
+    pub tx_id: u64,
+    pub table_name: String,
+    pub entries: Vec<(String, String)>,
+    pub atomic: bool,
+}
+
+// Get all and remove all of entities
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct KeysRequest {
+    pub tx_id: u64,
+    pub table_name: String,
+    pub keys: Vec<String>,
+    pub atomic: bool,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct ScanRequest {
+    pub tx_id: u64,
+    pub table_name: String,
+    pub start: u32,
+    pub count: u32,
+}
+
+/** Result of one entry of batch, value is JSON text:
+ * none for put, entity or null for get, true or false for remove */
+#[derive(RustcEncodable, RustcDecodable, Debug, Clone)]
+pub struct BatchItemResult {
+    pub error: Option<String>,
+    pub value: Option<String>,
+}
+
+// Frame as it is read from stream, code is opcode of request or status of response
+#[derive(Debug)]
+pub struct Frame {
+    pub request_id: u64,
+    pub code: u8,
+    pub payload: Vec<u8>,
+}
+
+pub fn encode_payload<T: Encodable>(value: &T) -> Result<Vec<u8>, String> {
+    encode(value, bincode::SizeLimit::Infinite).map_err(|err| format!("Cannot encode payload: {}", err))
+}
+
+pub fn decode_payload<T: Decodable>(payload: &[u8]) -> Result<T, String> {
+    decode(payload).map_err(|err| format!("Cannot decode payload: {}", err))
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, request_id: u64, code: u8, payload: &[u8]) -> io::Result<()> {
+    let length = FRAME_HEADER_SIZE + payload.len();
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Frame size {} is too big", length)));
+    }
+    let mut frame = Vec::with_capacity(4 + length);
+    for shift in (0..4).rev() {
+        frame.push((length >> (shift * 8)) as u8);
+    }
+    for shift in (0..8).rev() {
+        frame.push((request_id >> (shift * 8)) as u8);
+    }
+    frame.push(code);
+    frame.extend_from_slice(payload);
+    try!(writer.write_all(&frame));
+    writer.flush()
+}
+
+/** Read next frame
+ * return - none, if stream was closed between frames */
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Frame>> {
+    let mut length_bytes = [0u8; 4];
+    let mut read = 0;
+    while read < length_bytes.len() {
+        match try!(reader.read(&mut length_bytes[read..])) {
+            0 if read == 0 => return Ok(None),
+            0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Stream closed in frame length")),
+            size => read += size,
+        }
+    }
+    let length = length_bytes.iter().fold(0usize, |length, byte| (length << 8) | *byte as usize);
+    if length < FRAME_HEADER_SIZE || length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Wrong frame size {}", length)));
+    }
+    let mut frame = vec![0u8; length];
+    try!(reader.read_exact(&mut frame));
+    let request_id = frame[..8].iter().fold(0u64, |request_id, byte| (request_id << 8) | *byte as u64);
+    Ok(Some(Frame {
+        request_id: request_id,
+        code: frame[8],
+        payload: frame.split_off(FRAME_HEADER_SIZE),
+    }))
+}
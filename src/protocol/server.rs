@@ -0,0 +1,294 @@
+use std::io;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+
+use data_base::json::JsonValue;
+
+use data_base::DataBaseManager;
+use data_base::transaction::{IsolationLevel, LockMode};
+use net::{peer_name, AccessControl, AccessGuard, Connection, ConnectionAcceptor};
+use super::*;
+
+// Requests, received ahead of execution, reader of connection waits, when they are not answered yet
+const MAX_PIPELINED_REQUESTS: usize = 64;
+
+pub struct BinaryServer {
+    listener: TcpListener,
+    data_base_manager: Arc<DataBaseManager>,
+    acceptor: Option<Arc<ConnectionAcceptor>>,
+    access_control: Option<Arc<AccessControl>>,
+}
+
+impl BinaryServer {
+    pub fn bind<A: ToSocketAddrs>(address: A, data_base_manager: Arc<DataBaseManager>) -> io::Result<BinaryServer> {
+        let listener = try!(TcpListener::bind(address));
+        info!("Binary protocol listens on {:?}", listener.local_addr());
+        Ok(BinaryServer {
+            listener: listener,
+            data_base_manager: data_base_manager,
+            acceptor: None,
+            access_control: None,
+        })
+    }
+
+    // Accepted streams are wrapped by acceptor, for example into TLS
+    pub fn with_acceptor(mut self, acceptor: Arc<ConnectionAcceptor>) -> BinaryServer {
+        self.acceptor = Some(acceptor);
+        self
+    }
+
+    // Requests are executed only for principal, that is logged in by auth request or authenticated by acceptor
+    pub fn with_access_control(mut self, access_control: Arc<AccessControl>) -> BinaryServer {
+        self.access_control = Some(access_control);
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept connections until listener fails, every connection is served by own threads
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let data_base_manager = self.data_base_manager.clone();
+                    let guard = AccessGuard::new(self.access_control.clone());
+                    match self.acceptor.clone() {
+                        Some(acceptor) => {
+                            thread::spawn(move || {
+                                let peer = peer_name(&stream);
+                                match acceptor.accept(stream) {
+                                    Ok((connection, principal)) => {
+                                        serve_sequential(connection, peer, data_base_manager, guard.with_principal(principal))
+                                    }
+                                    Err(err) => warn!("Binary protocol connection from {} rejected: {}", peer, err),
+                                }
+                            });
+                        }
+                        None => {
+                            thread::spawn(move || serve_connection(stream, data_base_manager, guard));
+                        }
+                    }
+                }
+                Err(err) => error!("Binary protocol accept failed: {}", err),
+            }
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.run())
+    }
+}
+
+/** Requests are read while previous ones are executed, so client may send many requests
+ * without waiting for responses, up to MAX_PIPELINED_REQUESTS are buffered. Requests of connection
+ * are executed in order of receiving, every response carries request id of its request */
+fn serve_connection(stream: TcpStream, data_base_manager: Arc<DataBaseManager>, mut guard: AccessGuard) {
+    let peer = peer_name(&stream);
+    debug!("Binary protocol connection from {}", peer);
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(err) => {
+            error!("Cannot clone stream of {}: {}", peer, err);
+            return;
+        }
+    };
+    let (sender, receiver) = mpsc::sync_channel::<Frame>(MAX_PIPELINED_REQUESTS);
+    let worker = thread::spawn(move || {
+        for frame in receiver {
+            let (status, payload) = respond(&data_base_manager, &mut guard, &frame);
+            if let Err(err) = write_frame(&mut writer, frame.request_id, status, &payload) {
+                error!("Cannot write response {}: {}", frame.request_id, err);
+                break;
+            }
+        }
+    });
+
+    let mut reader = BufReader::new(stream);
+    loop {
+        match read_frame(&mut reader) {
+            Ok(Some(frame)) => {
+                if sender.send(frame).is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                error!("Cannot read request from {}: {}", peer, err);
+                break;
+            }
+        }
+    }
+    // Requests already received are answered before connection is closed
+    drop(sender);
+    let _ = worker.join();
+    debug!("Binary protocol connection from {} closed", peer);
+}
+
+/** Connection, that cannot be read and written by different threads, as TLS stream.
+ * Requests are executed one by one, pipelined requests wait in buffer of stream */
+fn serve_sequential(connection: Box<Connection>, peer: String, data_base_manager: Arc<DataBaseManager>, mut guard: AccessGuard) {
+    debug!("Binary protocol connection from {}", peer);
+    let mut stream = BufReader::new(connection);
+    loop {
+        match read_frame(&mut stream) {
+            Ok(Some(frame)) => {
+                let (status, payload) = respond(&data_base_manager, &mut guard, &frame);
+                if let Err(err) = write_frame(stream.get_mut(), frame.request_id, status, &payload) {
+                    error!("Cannot write response {}: {}", frame.request_id, err);
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(err) => {
+                error!("Cannot read request from {}: {}", peer, err);
+                break;
+            }
+        }
+    }
+    debug!("Binary protocol connection from {} closed", peer);
+}
+
+// Status and payload of response
+fn respond(data_base_manager: &DataBaseManager, guard: &mut AccessGuard, frame: &Frame) -> (u8, Vec<u8>) {
+    match execute(data_base_manager, guard, frame.code, &frame.payload) {
+        Ok(payload) => (STATUS_OK, payload),
+        Err(err) => (STATUS_ERROR, encode_payload(&err).unwrap_or(Vec::new())),
+    }
+}
+
+fn parse_json(text: &String) -> Result<JsonValue, String> {
+    JsonValue::from_str(text).map_err(|err| format!("Wrong json {}: {}", text, err))
+}
+
+fn parse_keys(keys: &Vec<String>) -> Result<Vec<JsonValue>, String> {
+    keys.iter().map(parse_json).collect()
+}
+
+fn batch_result<T, F>(result: Result<T, ::data_base::PersistenceError>, to_json: F) -> BatchItemResult
+    where F: Fn(T) -> Option<String>
+{
+    match result {
+        Ok(value) => {
+            BatchItemResult {
+                error: None,
+                value: to_json(value),
+            }
+        }
+        Err(err) => {
+            BatchItemResult {
+                error: Some(err.to_string()),
+                value: None,
+            }
+        }
+    }
+}
+
+// Table and transaction of request are checked by guard before execution
+fn check_access(guard: &AccessGuard, tx_id: u64, table_name: &str, write: bool) -> Result<(), String> {
+    try!(guard.check_table(table_name, write));
+    guard.check_transaction(tx_id)
+}
+
+fn execute(data_base_manager: &DataBaseManager, guard: &mut AccessGuard, opcode: u8, payload: &[u8]) -> Result<Vec<u8>, String> {
+    match opcode {
+        OP_AUTH => {
+            let request: AuthRequest = try!(decode_payload(payload));
+            let principal = try!(guard.login(request.name.as_ref().map(|name| name.as_str()), &request.secret));
+            encode_payload(&principal.name)
+        }
+        OP_TX_START => {
+            try!(guard.principal());
+            let request: TxStartRequest = try!(decode_payload(payload));
+            let lock_mode = try!(LockMode::from_str(&request.lock_mode));
+            let isolation_level = try!(IsolationLevel::from_str(&request.isolation_level));
+            let tx_id = try!(data_base_manager.tx_start_with_isolation(lock_mode, isolation_level).map_err(|err| err.to_string()));
+            guard.set_owner(tx_id);
+            encode_payload(&tx_id)
+        }
+        OP_TX_COMMIT => {
+            let request: TxRequest = try!(decode_payload(payload));
+            try!(guard.check_transaction(request.tx_id));
+            try!(data_base_manager.tx_stop(&request.tx_id).map_err(|err| err.to_string()));
+            guard.release_owner(request.tx_id);
+            Ok(Vec::new())
+        }
+        OP_TX_ROLLBACK => {
+            let request: TxRequest = try!(decode_payload(payload));
+            try!(guard.check_transaction(request.tx_id));
+            try!(data_base_manager.tx_rollback(&request.tx_id).map_err(|err| err.to_string()));
+            guard.release_owner(request.tx_id);
+            Ok(Vec::new())
+        }
+        OP_GET => {
+            let request: KeyRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, false));
+            let key = try!(parse_json(&request.key));
+            let value = try!(data_base_manager.get_data(&request.tx_id, &request.table_name, &key).map_err(|err| err.to_string()));
+            encode_payload(&value.map(|value| value.to_string()))
+        }
+        OP_PUT => {
+            let request: PutRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, true));
+            let key = try!(parse_json(&request.key));
+            let value = try!(parse_json(&request.value));
+            try!(data_base_manager.add_data(&request.tx_id, &request.table_name, &key, &value).map_err(|err| err.to_string()));
+            Ok(Vec::new())
+        }
+        OP_REMOVE => {
+            let request: KeyRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, true));
+            let key = try!(parse_json(&request.key));
+            let removed = try!(data_base_manager.remove_data(&request.tx_id, &request.table_name, &key).map_err(|err| err.to_string()));
+            encode_payload(&removed)
+        }
+        OP_PUT_ALL => {
+            let request: PutAllRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, true));
+            let mut entries = Vec::with_capacity(request.entries.len());
+            for &(ref key, ref value) in request.entries.iter() {
+                entries.push((try!(parse_json(key)), try!(parse_json(value))));
+            }
+            let results = try!(data_base_manager.put_all(&request.tx_id, &request.table_name, &entries, request.atomic)
+                .map_err(|err| err.to_string()));
+            let results: Vec<BatchItemResult> = results.into_iter().map(|result| batch_result(result, |_| None)).collect();
+            encode_payload(&results)
+        }
+        OP_GET_ALL => {
+            let request: KeysRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, false));
+            let keys = try!(parse_keys(&request.keys));
+            let results = try!(data_base_manager.get_all(&request.tx_id, &request.table_name, &keys, request.atomic)
+                .map_err(|err| err.to_string()));
+            let results: Vec<BatchItemResult> = results.into_iter()
+                .map(|result| batch_result(result, |value| Some(value.unwrap_or(JsonValue::Null).to_string())))
+                .collect();
+            encode_payload(&results)
+        }
+        OP_REMOVE_ALL => {
+            let request: KeysRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, true));
+            let keys = try!(parse_keys(&request.keys));
+            let results = try!(data_base_manager.remove_all(&request.tx_id, &request.table_name, &keys, request.atomic)
+                .map_err(|err| err.to_string()));
+            let results: Vec<BatchItemResult> = results.into_iter()
+                .map(|result| batch_result(result, |removed| Some(removed.to_string())))
+                .collect();
+            encode_payload(&results)
+        }
+        OP_SCAN => {
+            let request: ScanRequest = try!(decode_payload(payload));
+            try!(check_access(guard, request.tx_id, &request.table_name, false));
+            let entities = try!(data_base_manager.get_list(request.tx_id, &request.table_name, request.start, request.count)
+                .map_err(|err| err.to_string()));
+            let entities: Vec<String> = entities.iter().map(|entity| entity.to_string()).collect();
+            encode_payload(&entities)
+        }
+        _ => Err(format!("Unknown opcode {}", opcode)),
+    }
+}
@@ -0,0 +1,108 @@
+// Redis serialization protocol (RESP), so redis-cli and Redis client libraries can work with nimdg.
+// Commands are executed over one designated table, see server module
+use std::io;
+use std::io::{BufRead, Read, Write};
+
+pub mod server;
+
+// Value of RESP, is used both for commands of client and replies of server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<String>), // none - nil
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    pub fn ok() -> Reply {
+        Reply::Status(String::from("OK"))
+    }
+
+    pub fn nil() -> Reply {
+        Reply::Bulk(None)
+    }
+
+    pub fn error(message: &str) -> Reply {
+        Reply::Error(format!("ERR {}", message))
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match *self {
+            Reply::Status(ref status) => write!(writer, "+{}\r\n", status),
+            Reply::Error(ref error) => write!(writer, "-{}\r\n", error.replace("\r", " ").replace("\n", " ")),
+            Reply::Integer(value) => write!(writer, ":{}\r\n", value),
+            Reply::Bulk(None) => write!(writer, "$-1\r\n"),
+            Reply::Bulk(Some(ref value)) => write!(writer, "${}\r\n{}\r\n", value.len(), value),
+            Reply::Array(ref items) => {
+                try!(write!(writer, "*{}\r\n", items.len()));
+                for item in items {
+                    try!(item.write(writer));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Line without trailing \r\n, none if stream is closed
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if try!(reader.read_line(&mut line)) == 0 {
+        return Ok(None);
+    }
+    while line.ends_with('\n') || line.ends_with('\r') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+fn parse_length(text: &str) -> io::Result<i64> {
+    text.parse::<i64>().map_err(|_| invalid_data(format!("Wrong length {}", text)))
+}
+
+/** Read next value from stream. Line, that doesn't start by type prefix, is read as inline command,
+ * which is array of words, as redis-cli sends it in telnet mode
+ * return - none, if stream was closed */
+pub fn read_reply<R: BufRead>(reader: &mut R) -> io::Result<Option<Reply>> {
+    let line = match try!(read_line(reader)) {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    if line.is_empty() {
+        return Ok(Some(Reply::Array(Vec::new())));
+    }
+    let (prefix, rest) = line.split_at(1);
+    let reply = match prefix {
+        "+" => Reply::Status(String::from(rest)),
+        "-" => Reply::Error(String::from(rest)),
+        ":" => Reply::Integer(try!(parse_length(rest))),
+        "$" => {
+            let length = try!(parse_length(rest));
+            if length < 0 {
+                Reply::Bulk(None)
+            } else {
+                let mut data = vec![0u8; length as usize + 2];
+                try!(reader.read_exact(&mut data));
+                data.truncate(length as usize);
+                Reply::Bulk(Some(try!(String::from_utf8(data).map_err(|_| invalid_data(String::from("Bulk string is not utf-8"))))))
+            }
+        }
+        "*" => {
+            let length = try!(parse_length(rest));
+            let mut items = Vec::new();
+            for _ in 0..length {
+                let item = try!(read_reply(reader));
+                items.push(try!(item.ok_or(invalid_data(String::from("Stream closed in array")))));
+            }
+            Reply::Array(items)
+        }
+        _ => Reply::Array(line.split_whitespace().map(|word| Reply::Bulk(Some(String::from(word)))).collect()),
+    };
+    Ok(Some(reply))
+}
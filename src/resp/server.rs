@@ -0,0 +1,573 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use data_base::json::JsonValue;
+
+use data_base::{DataBaseManager, PersistenceError};
+use data_base::meta::{EntityDescriptionView, TableDescriptionView};
+use data_base::transaction::LockMode;
+use net::{peer_name, AccessControl, AccessGuard, ConnectionAcceptor};
+use super::{Reply, read_reply};
+
+pub const DEFAULT_RESP_TABLE_NAME: &'static str = "redis";
+const KEY_FIELD: &'static str = "key";
+const VALUE_FIELD: &'static str = "value";
+const EXPIRE_AT_FIELD: &'static str = "expire_at"; // milliseconds since epoch, 0 - never expires
+const DEFAULT_SCAN_COUNT: u32 = 10;
+
+/** Redis compatible server over one table with string key and string value.
+ * Value can be any text, for example JSON, it is returned as it was set.
+ * Expiration time is kept in entity, expired entity is removed, when it is accessed.
+ * With access control client logs in by AUTH token or AUTH name password */
+pub struct RespServer {
+    listener: TcpListener,
+    data_base_manager: Arc<DataBaseManager>,
+    table_name: String,
+    acceptor: Option<Arc<ConnectionAcceptor>>,
+    access_control: Option<Arc<AccessControl>>,
+}
+
+// State of connection, commands after MULTI are queued till EXEC or DISCARD
+struct Session<'a> {
+    data_base_manager: &'a DataBaseManager,
+    table_name: &'a String,
+    queued: Option<Vec<Vec<String>>>,
+    multi_failed: bool,
+    guard: AccessGuard,
+}
+
+// Value of entity in table
+struct Entry {
+    value: String,
+    expire_at: u64,
+}
+
+impl RespServer {
+    /** Bind listener and create table, if it doesn't exist.
+     * Existing table must have the same fields, as created one */
+    pub fn bind<A: ToSocketAddrs>(address: A, data_base_manager: Arc<DataBaseManager>, table_name: &str) -> Result<RespServer, String> {
+        try!(RespServer::create_table(&data_base_manager, table_name));
+        let listener = try!(TcpListener::bind(address).map_err(|err| format!("Cannot bind RESP listener: {}", err)));
+        info!("RESP listens on {:?} for table {}", listener.local_addr(), table_name);
+        Ok(RespServer {
+            listener: listener,
+            data_base_manager: data_base_manager,
+            table_name: String::from(table_name),
+            acceptor: None,
+            access_control: None,
+        })
+    }
+
+    // Accepted streams are wrapped by acceptor, for example into TLS
+    pub fn with_acceptor(mut self, acceptor: Arc<ConnectionAcceptor>) -> RespServer {
+        self.acceptor = Some(acceptor);
+        self
+    }
+
+    // Commands are executed only for principal, that is logged in by AUTH and has permissions for table
+    pub fn with_access_control(mut self, access_control: Arc<AccessControl>) -> RespServer {
+        self.access_control = Some(access_control);
+        self
+    }
+
+    fn create_table(data_base_manager: &DataBaseManager, table_name: &str) -> Result<(), String> {
+        let mut key_fields = BTreeMap::new();
+        key_fields.insert(String::from(KEY_FIELD), String::from("string"));
+        let mut value_fields = BTreeMap::new();
+        value_fields.insert(String::from(VALUE_FIELD), String::from("string"));
+        value_fields.insert(String::from(EXPIRE_AT_FIELD), String::from("u64"));
+
+        match data_base_manager.get_table_json(&String::from(table_name)) {
+            Some(table_json) => {
+                let same_fields = |entity: &str, fields: &BTreeMap<String, String>| {
+                    table_json.find(entity).and_then(|entity| entity.as_object()).map_or(false, |entity| {
+                        entity.len() == fields.len() &&
+                        fields.iter().all(|(name, type_name)| entity.get(name).and_then(|field| field.as_str()) == Some(type_name.as_str()))
+                    })
+                };
+                if same_fields("key", &key_fields) && same_fields("value", &value_fields) {
+                    Ok(())
+                } else {
+                    Err(format!("Table {} cannot be used by RESP, its description is {}", table_name, table_json))
+                }
+            }
+            None => {
+                data_base_manager.add_table(TableDescriptionView {
+                        name: String::from(table_name),
+                        key: EntityDescriptionView { fields: key_fields },
+                        value: EntityDescriptionView { fields: value_fields },
+                        lock_mode: Some(LockMode::Pessimistic),
+                    })
+                    .map(|_| ())
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let data_base_manager = self.data_base_manager.clone();
+                    let table_name = self.table_name.clone();
+                    let acceptor = self.acceptor.clone();
+                    let guard = AccessGuard::new(self.access_control.clone());
+                    thread::spawn(move || {
+                        let peer = peer_name(&stream);
+                        match acceptor {
+                            Some(acceptor) => {
+                                match acceptor.accept(stream) {
+                                    Ok((connection, principal)) => {
+                                        serve_connection(connection, peer, &data_base_manager, &table_name, guard.with_principal(principal))
+                                    }
+                                    Err(err) => warn!("RESP connection from {} rejected: {}", peer, err),
+                                }
+                            }
+                            None => serve_connection(stream, peer, &data_base_manager, &table_name, guard),
+                        }
+                    });
+                }
+                Err(err) => error!("RESP accept failed: {}", err),
+            }
+        }
+    }
+
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || self.run())
+    }
+}
+
+// Stream is read and written by one thread, so it may be TLS stream
+fn serve_connection<S: Read + Write>(stream: S, peer: String, data_base_manager: &DataBaseManager, table_name: &String, guard: AccessGuard) {
+    debug!("RESP connection from {}", peer);
+    let mut reader = BufReader::new(stream);
+    let mut session = Session {
+        data_base_manager: data_base_manager,
+        table_name: table_name,
+        queued: None,
+        multi_failed: false,
+        guard: guard,
+    };
+    loop {
+        let (reply, quit) = match read_reply(&mut reader) {
+            Ok(Some(Reply::Array(items))) => {
+                match command_args(items) {
+                    Some(ref args) if args.is_empty() => continue,
+                    Some(ref args) if args[0].to_uppercase() == "QUIT" => (Reply::ok(), true),
+                    Some(args) => (session.handle(args), false),
+                    None => (Reply::error("Protocol error: expected array of bulk strings"), true),
+                }
+            }
+            Ok(Some(_)) => (Reply::error("Protocol error: expected array of bulk strings"), true),
+            Ok(None) => break,
+            Err(err) => (Reply::error(&format!("Protocol error: {}", err)), true),
+        };
+        let written = {
+            let mut writer = BufWriter::new(reader.get_mut());
+            reply.write(&mut writer).and_then(|_| writer.flush())
+        };
+        if let Err(err) = written {
+            debug!("Cannot write reply to {}: {}", peer, err);
+            break;
+        }
+        if quit {
+            break;
+        }
+    }
+    // Transaction of MULTI is not started till EXEC, so there is nothing to roll back
+    debug!("RESP connection from {} closed", peer);
+}
+
+fn command_args(items: Vec<Reply>) -> Option<Vec<String>> {
+    items.into_iter()
+        .map(|item| match item {
+            Reply::Bulk(Some(arg)) => Some(arg),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<'a> Session<'a> {
+    fn handle(&mut self, args: Vec<String>) -> Reply {
+        let name = args[0].to_uppercase();
+        if name == "AUTH" {
+            return self.auth(&args);
+        }
+        if self.guard.principal().is_err() {
+            return Reply::Error(String::from("NOAUTH Authentication required."));
+        }
+        match name.as_str() {
+            "MULTI" => {
+                if self.queued.is_some() {
+                    return Reply::error("MULTI calls can not be nested");
+                }
+                self.queued = Some(Vec::new());
+                self.multi_failed = false;
+                Reply::ok()
+            }
+            "EXEC" => {
+                match self.queued.take() {
+                    None => Reply::error("EXEC without MULTI"),
+                    Some(_) if self.multi_failed => {
+                        Reply::Error(String::from("EXECABORT Transaction discarded because of previous errors."))
+                    }
+                    Some(commands) => self.exec(commands),
+                }
+            }
+            "DISCARD" => {
+                match self.queued.take() {
+                    None => Reply::error("DISCARD without MULTI"),
+                    Some(_) => Reply::ok(),
+                }
+            }
+            _ => {
+                let checked = check_arity(&name, args.len()).and_then(|_| self.check_access(&name));
+                if let Err(reply) = checked {
+                    if self.queued.is_some() {
+                        self.multi_failed = true;
+                    }
+                    return reply;
+                }
+                match self.queued {
+                    Some(ref mut queued) => {
+                        queued.push(args);
+                        Reply::Status(String::from("QUEUED"))
+                    }
+                    None => {
+                        // Single command is executed in own transaction, so INCR reads and writes atomically
+                        let (data_base_manager, table_name) = (self.data_base_manager, self.table_name);
+                        data_base_manager.auto_commit(LockMode::Pessimistic,
+                                         |tx_id| execute(data_base_manager, tx_id, table_name, &name, &args))
+                            .unwrap_or_else(|err| Reply::error(&err.to_string()))
+                    }
+                }
+            }
+        }
+    }
+
+    // AUTH password logs in by API token, AUTH name password - by user
+    fn auth(&mut self, args: &Vec<String>) -> Reply {
+        if !self.guard.is_enabled() {
+            return Reply::error("AUTH called without any password configured for the default user. \
+                                 Are you sure your configuration is correct?");
+        }
+        let result = match args.len() {
+            2 => self.guard.login(None, &args[1]).map(|_| ()),
+            3 => self.guard.login(Some(args[1].as_str()), &args[2]).map(|_| ()),
+            _ => return Reply::error("wrong number of arguments for 'auth' command"),
+        };
+        match result {
+            Ok(()) => Reply::ok(),
+            Err(_) => Reply::Error(String::from("WRONGPASS invalid username-password pair or user is disabled.")),
+        }
+    }
+
+    // Reading commands need permission read for table, changing ones - write
+    fn check_access(&self, name: &str) -> Result<(), Reply> {
+        let write = match name {
+            "GET" | "EXISTS" | "TTL" | "SCAN" => false,
+            "SET" | "DEL" | "INCR" | "EXPIRE" => true,
+            _ => return Ok(()),
+        };
+        self.guard.check_table(self.table_name, write).map_err(|err| Reply::Error(format!("NOPERM {}", err)))
+    }
+
+    // Queued commands are executed in one transaction, error of command doesn't stop following commands
+    fn exec(&self, commands: Vec<Vec<String>>) -> Reply {
+        let tx_id = match self.data_base_manager.tx_start(LockMode::Pessimistic) {
+            Ok(tx_id) => tx_id,
+            Err(err) => return Reply::error(&err.to_string()),
+        };
+        let replies = commands.iter()
+            .map(|args| {
+                execute(self.data_base_manager,
+                        &tx_id,
+                        self.table_name,
+                        &args[0].to_uppercase(),
+                        args)
+                    .unwrap_or_else(|err| Reply::error(&err.to_string()))
+            })
+            .collect();
+        match self.data_base_manager.tx_stop(&tx_id) {
+            Ok(_) => Reply::Array(replies),
+            Err(err) => {
+                if let Err(rollback_error) = self.data_base_manager.tx_rollback(&tx_id) {
+                    error!("Rollback of EXEC tx {} failed: {}", tx_id, rollback_error);
+                }
+                Reply::Error(format!("EXECABORT Transaction failed: {}", err))
+            }
+        }
+    }
+}
+
+// Arity includes command name, none - any number of arguments
+fn check_arity(name: &str, count: usize) -> Result<(), Reply> {
+    let (min, max) = match name {
+        "PING" => (1, Some(2)),
+        "ECHO" | "GET" | "INCR" | "TTL" => (2, Some(2)),
+        "COMMAND" => (1, None),
+        "SET" => (3, None),
+        "DEL" | "EXISTS" | "SCAN" => (2, None),
+        "EXPIRE" => (3, Some(3)),
+        _ => return Err(Reply::error(&format!("unknown command '{}'", name.to_lowercase()))),
+    };
+    if count < min || max.map_or(false, |max| count > max) {
+        Err(Reply::error(&format!("wrong number of arguments for '{}' command", name.to_lowercase())))
+    } else {
+        Ok(())
+    }
+}
+
+fn execute(data_base_manager: &DataBaseManager, tx_id: &u64, table_name: &String, name: &str, args: &Vec<String>) -> Result<Reply, PersistenceError> {
+    let store = Store {
+        data_base_manager: data_base_manager,
+        tx_id: tx_id,
+        table_name: table_name,
+    };
+    match name {
+        "PING" => Ok(args.get(1).map_or(Reply::Status(String::from("PONG")), |message| Reply::Bulk(Some(message.clone())))),
+        "ECHO" => Ok(Reply::Bulk(Some(args[1].clone()))),
+        // Clients ask commands of server on connect, empty list means, that nothing is known about them
+        "COMMAND" => Ok(Reply::Array(Vec::new())),
+        "GET" => Ok(Reply::Bulk(try!(store.get(&args[1])).map(|entry| entry.value))),
+        "SET" => set(&store, args),
+        "DEL" => {
+            let mut count = 0;
+            for key in &args[1..] {
+                if try!(store.get(key)).is_some() {
+                    try!(store.remove(key));
+                    count += 1;
+                }
+            }
+            Ok(Reply::Integer(count))
+        }
+        "EXISTS" => {
+            let mut count = 0;
+            for key in &args[1..] {
+                if try!(store.get(key)).is_some() {
+                    count += 1;
+                }
+            }
+            Ok(Reply::Integer(count))
+        }
+        "INCR" => {
+            let entry = try!(store.get(&args[1])).unwrap_or(Entry {
+                value: String::from("0"),
+                expire_at: 0,
+            });
+            let value = match entry.value.parse::<i64>().ok().and_then(|value| value.checked_add(1)) {
+                Some(value) => value,
+                None => return Ok(Reply::error("value is not an integer or out of range")),
+            };
+            try!(store.put(&args[1], &value.to_string(), entry.expire_at));
+            Ok(Reply::Integer(value))
+        }
+        "EXPIRE" => {
+            let seconds = match args[2].parse::<i64>() {
+                Ok(seconds) => seconds,
+                Err(_) => return Ok(Reply::error("value is not an integer or out of range")),
+            };
+            match try!(store.get(&args[1])) {
+                None => Ok(Reply::Integer(0)),
+                Some(_) if seconds <= 0 => {
+                    try!(store.remove(&args[1]));
+                    Ok(Reply::Integer(1))
+                }
+                Some(entry) => {
+                    match expire_at(seconds as u64, 1000) {
+                        Some(expire_at) => {
+                            try!(store.put(&args[1], &entry.value, expire_at));
+                            Ok(Reply::Integer(1))
+                        }
+                        None => Ok(Reply::error("invalid expire time in 'expire' command")),
+                    }
+                }
+            }
+        }
+        "TTL" => {
+            Ok(Reply::Integer(match try!(store.get(&args[1])) {
+                None => -2,
+                Some(Entry { expire_at: 0, .. }) => -1,
+                Some(entry) => ((entry.expire_at.saturating_sub(now_millis()) + 500) / 1000) as i64,
+            }))
+        }
+        "SCAN" => scan(&store, args),
+        _ => Ok(Reply::error(&format!("unknown command '{}'", name.to_lowercase()))),
+    }
+}
+
+// SET key value [EX seconds | PX milliseconds] [NX | XX]
+fn set(store: &Store, args: &Vec<String>) -> Result<Reply, PersistenceError> {
+    let mut expire_at = 0;
+    let mut only_new = false;
+    let mut only_existing = false;
+    let mut index = 3;
+    while index < args.len() {
+        match args[index].to_uppercase().as_str() {
+            option @ "EX" | option @ "PX" if index + 1 < args.len() => {
+                let unit_millis = if option == "EX" { 1000 } else { 1 };
+                let time_expire_at = match args[index + 1].parse::<u64>() {
+                    Ok(time) if time > 0 => self::expire_at(time, unit_millis),
+                    _ => None,
+                };
+                expire_at = match time_expire_at {
+                    Some(time_expire_at) => time_expire_at,
+                    None => return Ok(Reply::error("invalid expire time in 'set' command")),
+                };
+                index += 2;
+            }
+            "NX" => {
+                only_new = true;
+                index += 1;
+            }
+            "XX" => {
+                only_existing = true;
+                index += 1;
+            }
+            _ => return Ok(Reply::error("syntax error")),
+        }
+    }
+    if only_new && only_existing {
+        return Ok(Reply::error("syntax error"));
+    }
+    if only_new || only_existing {
+        let exists = try!(store.get(&args[1])).is_some();
+        if exists == only_new {
+            return Ok(Reply::nil());
+        }
+    }
+    try!(store.put(&args[1], &args[2], expire_at));
+    Ok(Reply::ok())
+}
+
+/** SCAN cursor [MATCH pattern] [COUNT count]
+ * Cursor is position in table, 0 is returned as next cursor, when table is passed */
+fn scan(store: &Store, args: &Vec<String>) -> Result<Reply, PersistenceError> {
+    let cursor = match args[1].parse::<u32>() {
+        Ok(cursor) => cursor,
+        Err(_) => return Ok(Reply::error("invalid cursor")),
+    };
+    let mut pattern = None;
+    let mut count = DEFAULT_SCAN_COUNT;
+    let mut index = 2;
+    while index + 1 < args.len() {
+        match args[index].to_uppercase().as_str() {
+            "MATCH" => pattern = Some(args[index + 1].chars().collect::<Vec<char>>()),
+            "COUNT" => {
+                count = match args[index + 1].parse::<u32>() {
+                    Ok(count) if count > 0 => count,
+                    _ => return Ok(Reply::error("value is not an integer or out of range")),
+                }
+            }
+            _ => return Ok(Reply::error("syntax error")),
+        }
+        index += 2;
+    }
+    if index != args.len() {
+        return Ok(Reply::error("syntax error"));
+    }
+
+    let entities = try!(store.data_base_manager.get_list(*store.tx_id, store.table_name, cursor, count));
+    let next_cursor = if (entities.len() as u32) < count { 0 } else { cursor + count };
+    let now = now_millis();
+    let keys = entities.iter()
+        .filter_map(|entity| {
+            // Entity of list is pair of key and value
+            let pair = match entity.as_array() {
+                Some(pair) if pair.len() == 2 => pair,
+                _ => return None,
+            };
+            if Entry::from_json(&pair[1]).is_expired(now) {
+                return None;
+            }
+            pair[0].find(KEY_FIELD).and_then(|key| key.as_str()).map(String::from)
+        })
+        .filter(|key| pattern.as_ref().map_or(true, |pattern| glob_match(pattern, &key.chars().collect::<Vec<char>>())))
+        .map(|key| Reply::Bulk(Some(key)))
+        .collect();
+    Ok(Reply::Array(vec![Reply::Bulk(Some(next_cursor.to_string())), Reply::Array(keys)]))
+}
+
+// Glob pattern of Redis: * - any sequence, ? - any char, \ - escape of next char
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(&'*') => (0..text.len() + 1).any(|skip| glob_match(&pattern[1..], &text[skip..])),
+        Some(&'?') => !text.is_empty() && glob_match(&pattern[1..], &text[1..]),
+        Some(&'\\') if pattern.len() > 1 => text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..]),
+        Some(expected) => text.first() == Some(expected) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+// Time of expiration after count of units, None - it overflows
+fn expire_at(time: u64, unit_millis: u64) -> Option<u64> {
+    time.checked_mul(unit_millis).and_then(|millis| now_millis().checked_add(millis))
+}
+
+fn now_millis() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    since_epoch.as_secs() * 1000 + since_epoch.subsec_nanos() as u64 / 1000000
+}
+
+impl Entry {
+    fn from_json(json: &JsonValue) -> Entry {
+        Entry {
+            value: String::from(json.find(VALUE_FIELD).and_then(|value| value.as_str()).unwrap_or("")),
+            expire_at: json.find(EXPIRE_AT_FIELD).and_then(|expire_at| expire_at.as_u64()).unwrap_or(0),
+        }
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expire_at != 0 && self.expire_at <= now
+    }
+}
+
+// Access to table of RESP in transaction
+struct Store<'a> {
+    data_base_manager: &'a DataBaseManager,
+    tx_id: &'a u64,
+    table_name: &'a String,
+}
+
+impl<'a> Store<'a> {
+    fn key_json(key: &str) -> JsonValue {
+        let mut res = BTreeMap::new();
+        res.insert(String::from(KEY_FIELD), JsonValue::String(String::from(key)));
+        JsonValue::Object(res)
+    }
+
+    // Expired entity is removed and not returned
+    fn get(&self, key: &str) -> Result<Option<Entry>, PersistenceError> {
+        match try!(self.data_base_manager.get_data(self.tx_id, self.table_name, &Store::key_json(key))) {
+            Some(value) => {
+                let entry = Entry::from_json(&value);
+                if entry.is_expired(now_millis()) {
+                    try!(self.remove(key));
+                    Ok(None)
+                } else {
+                    Ok(Some(entry))
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: &str, value: &str, expire_at: u64) -> Result<(), PersistenceError> {
+        let mut res = BTreeMap::new();
+        res.insert(String::from(VALUE_FIELD), JsonValue::String(String::from(value)));
+        res.insert(String::from(EXPIRE_AT_FIELD), JsonValue::U64(expire_at));
+        self.data_base_manager.add_data(self.tx_id, self.table_name, &Store::key_json(key), &JsonValue::Object(res))
+    }
+
+    fn remove(&self, key: &str) -> Result<bool, PersistenceError> {
+        self.data_base_manager.remove_data(self.tx_id, self.table_name, &Store::key_json(key))
+    }
+}
@@ -0,0 +1,45 @@
+extern crate iron;
+
+use rustless::Extensible;
+use rustless;
+use std::sync::Arc;
+
+use data_base::DataBaseManager;
+use rest::RestSettings;
+use rest::auth::Security;
+
+pub struct AppDataBase;
+impl iron::typemap::Key for AppDataBase {
+    type Value = Arc<DataBaseManager>;
+}
+
+pub struct AppSettings;
+impl iron::typemap::Key for AppSettings {
+    type Value = RestSettings;
+}
+
+// Security is not set, if authentication is not enabled. It may be shared with protocol listeners
+pub struct AppSecurity;
+impl iron::typemap::Key for AppSecurity {
+    type Value = Arc<Security>;
+}
+
+pub trait DataBaseExtension: rustless::Extensible {
+    fn get_data_base_manager(&self) -> &DataBaseManager;
+    fn get_settings(&self) -> &RestSettings;
+    fn get_security(&self) -> Option<&Security>;
+}
+
+impl DataBaseExtension for rustless::Application {
+    fn get_data_base_manager(&self) -> &DataBaseManager {
+        &**self.ext().get::<AppDataBase>().unwrap()
+    }
+
+    fn get_settings(&self) -> &RestSettings {
+        self.ext().get::<AppSettings>().unwrap()
+    }
+
+    fn get_security(&self) -> Option<&Security> {
+        self.ext().get::<AppSecurity>().map(|security| &**security)
+    }
+}
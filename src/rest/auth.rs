@@ -0,0 +1,382 @@
+// Authentication and authorization of REST api. Principal is authenticated by header
+// Authorization: Bearer <token> or Basic <base64 of name:password>, permissions are given
+// to roles per table. Transaction started by principal can be used only by it and by admin.
+// Clients of TLS listeners are authenticated by common name of certificate.
+// Security is access control of binary, memcached and RESP listeners too
+use std;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use bcrypt;
+use rustc_serialize::base64::FromBase64;
+
+use net::AccessControl;
+pub use net::Principal;
+
+pub const DEFAULT_HASH_COST: u32 = bcrypt::DEFAULT_COST;
+// Permissions for table * are given for all tables
+pub const ALL_TABLES: &'static str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    Read,
+    Write,
+    Ddl, // creation of table
+    Admin, // all permissions, for * also transactions of other principals, queries and changes
+}
+
+// Tables with permissions of role
+pub type Role = BTreeMap<String, Vec<Permission>>;
+
+#[derive(Clone, PartialEq)]
+pub enum Credentials {
+    Token(String),
+    Basic(String, String), // name and password
+    Certificate(String), // common name of verified client certificate, only TLS listeners verify them
+}
+
+// Static API token, that is given to principal with name
+#[derive(Clone)]
+pub struct TokenConfig {
+    pub name: String,
+    pub token: String,
+    pub roles: Vec<String>,
+}
+
+// User of HTTP Basic authentication, password is hashed by bcrypt
+#[derive(Debug, Clone)]
+pub struct UserConfig {
+    pub name: String,
+    pub password_hash: String,
+    pub roles: Vec<String>,
+}
+
+// Principal of client certificate with common name
+#[derive(Debug, Clone)]
+pub struct CertificateConfig {
+    pub common_name: String,
+    pub name: String,
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    pub tokens: Vec<TokenConfig>,
+    pub users: Vec<UserConfig>,
+    pub certificates: Vec<CertificateConfig>,
+    pub roles: BTreeMap<String, Role>,
+}
+
+/** Source of principals. Authenticator returns None for credentials, that it doesn't know */
+pub trait Authenticator: Send + Sync {
+    fn authenticate(&self, credentials: &Credentials) -> Option<Principal>;
+}
+
+pub struct TokenAuthenticator {
+    tokens: HashMap<String, Principal>,
+}
+
+pub struct BasicAuthenticator {
+    users: HashMap<String, (String, Principal)>,
+}
+
+pub struct CertificateAuthenticator {
+    certificates: HashMap<String, Principal>,
+}
+
+// Required access of endpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Authenticated,
+    Table(Permission, &'static str), // name of request parameter with table
+    AllTables(Permission),
+    Admin,
+}
+
+/** Authenticators, roles and owners of transactions, started by REST api */
+pub struct Security {
+    authenticators: Vec<Box<Authenticator>>,
+    roles: BTreeMap<String, Role>,
+    owners: Mutex<HashMap<u64, String>>,
+}
+
+impl Permission {
+    pub fn from_str(permission: &str) -> Result<Permission, String> {
+        match permission {
+            "read" => Ok(Permission::Read),
+            "write" => Ok(Permission::Write),
+            "ddl" => Ok(Permission::Ddl),
+            "admin" => Ok(Permission::Admin),
+            _ => Err(format!("Unknown permission {}, expected read, write, ddl or admin", permission)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Ddl => "ddl",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+impl Credentials {
+    /** Credentials by value of header Authorization, None for unknown scheme */
+    pub fn from_header(value: &str) -> Option<Credentials> {
+        let mut parts = value.trim().splitn(2, ' ');
+        match (parts.next(), parts.next().map(|credentials| credentials.trim())) {
+            (Some("Bearer"), Some(token)) => Some(Credentials::Token(String::from(token))),
+            (Some("Basic"), Some(encoded)) => {
+                let decoded = encoded.from_base64().ok().and_then(|decoded| String::from_utf8(decoded).ok());
+                decoded.and_then(|decoded| {
+                    let mut parts = decoded.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(password)) => Some(Credentials::Basic(String::from(name), String::from(password))),
+                        _ => None,
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// Secrets are not written to log
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Credentials::Token(_) => write!(f, "Token(***)"),
+            Credentials::Basic(ref name, _) => write!(f, "Basic({}, ***)", name),
+            Credentials::Certificate(ref common_name) => write!(f, "Certificate({})", common_name),
+        }
+    }
+}
+
+impl std::fmt::Debug for TokenConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "TokenConfig {{ name: {:?}, token: \"***\", roles: {:?} }}", self.name, self.roles)
+    }
+}
+
+impl AuthConfig {
+    /** Check, that roles of principals are defined and names of principals are unique */
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut names = Vec::new();
+        let principals = self.tokens
+            .iter()
+            .map(|token| (&token.name, &token.roles))
+            .chain(self.users.iter().map(|user| (&user.name, &user.roles)))
+            .chain(self.certificates.iter().map(|certificate| (&certificate.name, &certificate.roles)));
+        for (name, roles) in principals {
+            if names.contains(&name) {
+                errors.push(format!("Principal {} is defined twice", name));
+            }
+            names.push(name);
+            for role in roles {
+                if !self.roles.contains_key(role) {
+                    errors.push(format!("Role {} of principal {} not found", role, name));
+                }
+            }
+        }
+        let mut common_names = Vec::new();
+        for certificate in self.certificates.iter() {
+            if common_names.contains(&&certificate.common_name) {
+                errors.push(format!("Certificate {} is mapped twice", certificate.common_name));
+            }
+            common_names.push(&certificate.common_name);
+        }
+        for user in self.users.iter() {
+            if !user.password_hash.starts_with("$2") {
+                errors.push(format!("Password hash of user {} is not bcrypt hash", user.name));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+impl TokenAuthenticator {
+    pub fn new(tokens: &[TokenConfig]) -> TokenAuthenticator {
+        TokenAuthenticator {
+            tokens: tokens.iter()
+                .map(|token| {
+                    (token.token.clone(),
+                     Principal {
+                        name: token.name.clone(),
+                        roles: token.roles.clone(),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Authenticator for TokenAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Option<Principal> {
+        match *credentials {
+            Credentials::Token(ref token) => self.tokens.get(token).cloned(),
+            _ => None,
+        }
+    }
+}
+
+impl BasicAuthenticator {
+    pub fn new(users: &[UserConfig]) -> BasicAuthenticator {
+        BasicAuthenticator {
+            users: users.iter()
+                .map(|user| {
+                    (user.name.clone(),
+                     (user.password_hash.clone(),
+                      Principal {
+                        name: user.name.clone(),
+                        roles: user.roles.clone(),
+                    }))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Authenticator for BasicAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Option<Principal> {
+        match *credentials {
+            Credentials::Basic(ref name, ref password) => {
+                self.users.get(name).and_then(|&(ref hash, ref principal)| {
+                    match bcrypt::verify(password, hash) {
+                        Ok(true) => Some(principal.clone()),
+                        Ok(false) => None,
+                        Err(err) => {
+                            warn!("Cannot verify password of user {}: {:?}", name, err);
+                            None
+                        }
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl CertificateAuthenticator {
+    pub fn new(certificates: &[CertificateConfig]) -> CertificateAuthenticator {
+        CertificateAuthenticator {
+            certificates: certificates.iter()
+                .map(|certificate| {
+                    (certificate.common_name.clone(),
+                     Principal {
+                        name: certificate.name.clone(),
+                        roles: certificate.roles.clone(),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Authenticator for CertificateAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Option<Principal> {
+        match *credentials {
+            Credentials::Certificate(ref common_name) => self.certificates.get(common_name).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/** Bcrypt hash of password for configuration of users */
+pub fn hash_password(password: &str, cost: u32) -> Result<String, String> {
+    bcrypt::hash(password, cost).map_err(|err| format!("Cannot hash password: {:?}", err))
+}
+
+impl Security {
+    pub fn new(roles: BTreeMap<String, Role>) -> Security {
+        Security {
+            authenticators: Vec::new(),
+            roles: roles,
+            owners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /** Security with authenticators of tokens, users and certificates of configuration */
+    pub fn from_config(config: &AuthConfig) -> Security {
+        let mut security = Security::new(config.roles.clone());
+        security.add_authenticator(Box::new(TokenAuthenticator::new(&config.tokens)));
+        security.add_authenticator(Box::new(BasicAuthenticator::new(&config.users)));
+        security.add_authenticator(Box::new(CertificateAuthenticator::new(&config.certificates)));
+        security
+    }
+
+    // Authenticators are asked in order of adding
+    pub fn add_authenticator(&mut self, authenticator: Box<Authenticator>) {
+        self.authenticators.push(authenticator);
+    }
+
+    pub fn authenticate(&self, credentials: &Credentials) -> Option<Principal> {
+        self.authenticators.iter().filter_map(|authenticator| authenticator.authenticate(credentials)).next()
+    }
+
+    /** Permission is given by role for the table or for all tables, admin has all permissions */
+    pub fn allows(&self, principal: &Principal, table_name: &str, permission: Permission) -> bool {
+        principal.roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flat_map(|role| role.get(table_name).into_iter().chain(role.get(ALL_TABLES).into_iter()))
+            .any(|permissions| permissions.iter().any(|granted| *granted == permission || *granted == Permission::Admin))
+    }
+
+    pub fn is_admin(&self, principal: &Principal) -> bool {
+        self.allows(principal, ALL_TABLES, Permission::Admin)
+    }
+
+    pub fn set_owner(&self, tx_id: u64, principal: &Principal) {
+        self.owners.lock().unwrap().insert(tx_id, principal.name.clone());
+    }
+
+    pub fn release_owner(&self, tx_id: u64) {
+        self.owners.lock().unwrap().remove(&tx_id);
+    }
+
+    /** Transaction can be used by its owner and by admin. Transaction without owner was started
+     * by front-end without this security or before restart, it is available only to admin */
+    pub fn can_use_transaction(&self, principal: &Principal, tx_id: u64) -> bool {
+        match self.owners.lock().unwrap().get(&tx_id) {
+            Some(owner) if *owner == principal.name => true,
+            _ => self.is_admin(principal),
+        }
+    }
+}
+
+// Clients of listeners log in by the same token or name and password, as in header Authorization
+impl AccessControl for Security {
+    fn login(&self, name: Option<&str>, secret: &str) -> Option<Principal> {
+        let credentials = match name {
+            Some(name) => Credentials::Basic(String::from(name), String::from(secret)),
+            None => Credentials::Token(String::from(secret)),
+        };
+        self.authenticate(&credentials)
+    }
+
+    fn can_read(&self, principal: &Principal, table_name: &str) -> bool {
+        self.allows(principal, table_name, Permission::Read)
+    }
+
+    fn can_write(&self, principal: &Principal, table_name: &str) -> bool {
+        self.allows(principal, table_name, Permission::Write)
+    }
+
+    fn set_owner(&self, tx_id: u64, principal: &Principal) {
+        Security::set_owner(self, tx_id, principal)
+    }
+
+    fn release_owner(&self, tx_id: u64) {
+        Security::release_owner(self, tx_id)
+    }
+
+    fn can_use_transaction(&self, principal: &Principal, tx_id: u64) -> bool {
+        Security::can_use_transaction(self, principal, tx_id)
+    }
+}
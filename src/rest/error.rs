@@ -0,0 +1,328 @@
+// Errors of REST api. Error response has status of code and body
+// { "code": .., "message": .., "details": .., "tx_id": .., "table": .. }
+use std;
+use std::collections::BTreeMap;
+
+use iron::status::Status;
+
+use data_base::{IoEntityError, PersistenceError};
+use data_base::json::{JsonValue, ToJson};
+use data_base::transaction::DEFAULT_TX_ID;
+
+// Stable codes of errors, clients should check them instead of messages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    InvalidParams,
+    WrongEntity,
+    ProcessorFailed,
+    Unauthorized,
+    Forbidden,
+    TableNotFound,
+    EntityNotFound,
+    TransactionNotFound,
+    SavepointNotFound,
+    QueryNotFound,
+    TableExists,
+    TransactionConflict,
+    ReadOnlyTransaction,
+    TransactionPrepared,
+    TransactionNotPrepared,
+    TransactionKilled,
+    BatchFailed,
+    LockFailed,
+    LockTimeout,
+    ChangesEvicted,
+    Internal,
+}
+
+pub const ERROR_CODES: &'static [ErrorCode] = &[ErrorCode::InvalidParams,
+                                                ErrorCode::WrongEntity,
+                                                ErrorCode::ProcessorFailed,
+                                                ErrorCode::Unauthorized,
+                                                ErrorCode::Forbidden,
+                                                ErrorCode::TableNotFound,
+                                                ErrorCode::EntityNotFound,
+                                                ErrorCode::TransactionNotFound,
+                                                ErrorCode::SavepointNotFound,
+                                                ErrorCode::QueryNotFound,
+                                                ErrorCode::TableExists,
+                                                ErrorCode::TransactionConflict,
+                                                ErrorCode::ReadOnlyTransaction,
+                                                ErrorCode::TransactionPrepared,
+                                                ErrorCode::TransactionNotPrepared,
+                                                ErrorCode::TransactionKilled,
+                                                ErrorCode::BatchFailed,
+                                                ErrorCode::LockFailed,
+                                                ErrorCode::LockTimeout,
+                                                ErrorCode::ChangesEvicted,
+                                                ErrorCode::Internal];
+
+// For show errors on client side
+#[derive(Debug, Clone)]
+pub enum ClientErrorType {
+    GettingParamsError(Vec<String>),
+    CommonError(String),
+    Failed(ErrorCode, String),
+}
+
+#[derive(Debug)]
+pub struct ClientError {
+    pub error_type: ClientErrorType,
+    pub description: String,
+    pub code: ErrorCode,
+    pub details: JsonValue,
+    pub tx_id: Option<u64>,
+    pub table: Option<String>,
+}
+
+impl ErrorCode {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ErrorCode::InvalidParams => "invalid_params",
+            ErrorCode::WrongEntity => "wrong_entity",
+            ErrorCode::ProcessorFailed => "processor_failed",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::TableNotFound => "table_not_found",
+            ErrorCode::EntityNotFound => "entity_not_found",
+            ErrorCode::TransactionNotFound => "transaction_not_found",
+            ErrorCode::SavepointNotFound => "savepoint_not_found",
+            ErrorCode::QueryNotFound => "query_not_found",
+            ErrorCode::TableExists => "table_exists",
+            ErrorCode::TransactionConflict => "transaction_conflict",
+            ErrorCode::ReadOnlyTransaction => "read_only_transaction",
+            ErrorCode::TransactionPrepared => "transaction_prepared",
+            ErrorCode::TransactionNotPrepared => "transaction_not_prepared",
+            ErrorCode::TransactionKilled => "transaction_killed",
+            ErrorCode::BatchFailed => "batch_failed",
+            ErrorCode::LockFailed => "lock_failed",
+            ErrorCode::LockTimeout => "lock_timeout",
+            ErrorCode::ChangesEvicted => "changes_evicted",
+            ErrorCode::Internal => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match *self {
+            ErrorCode::InvalidParams |
+            ErrorCode::WrongEntity |
+            ErrorCode::ProcessorFailed => Status::BadRequest,
+            ErrorCode::Unauthorized => Status::Unauthorized,
+            ErrorCode::Forbidden => Status::Forbidden,
+            ErrorCode::TableNotFound |
+            ErrorCode::EntityNotFound |
+            ErrorCode::TransactionNotFound |
+            ErrorCode::SavepointNotFound |
+            ErrorCode::QueryNotFound => Status::NotFound,
+            ErrorCode::TableExists |
+            ErrorCode::TransactionConflict |
+            ErrorCode::ReadOnlyTransaction |
+            ErrorCode::TransactionPrepared |
+            ErrorCode::TransactionNotPrepared |
+            ErrorCode::TransactionKilled |
+            ErrorCode::BatchFailed => Status::Conflict,
+            ErrorCode::LockFailed |
+            ErrorCode::LockTimeout => Status::Locked,
+            ErrorCode::ChangesEvicted => Status::Gone,
+            ErrorCode::Internal => Status::InternalServerError,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            ErrorCode::InvalidParams => "parameter is missed or has wrong type, details are errors of parameters",
+            ErrorCode::WrongEntity => "key or value doesn't match description of table",
+            ErrorCode::ProcessorFailed => "entry processor cannot be applied to value",
+            ErrorCode::Unauthorized => "credentials are missed or wrong",
+            ErrorCode::Forbidden => "principal has no permission for table or doesn't own transaction",
+            ErrorCode::TableNotFound => "table not found",
+            ErrorCode::EntityNotFound => "entity with key not found",
+            ErrorCode::TransactionNotFound => "transaction not found, it may be already finished",
+            ErrorCode::SavepointNotFound => "savepoint not found in transaction",
+            ErrorCode::QueryNotFound => "continuous query not found",
+            ErrorCode::TableExists => "table with the same name already exists",
+            ErrorCode::TransactionConflict => "transaction conflicts with other transaction or already started",
+            ErrorCode::ReadOnlyTransaction => "write in read only transaction",
+            ErrorCode::TransactionPrepared => "operation is not allowed for prepared transaction",
+            ErrorCode::TransactionNotPrepared => "transaction is not prepared",
+            ErrorCode::TransactionKilled => "transaction was killed and rolled back",
+            ErrorCode::BatchFailed => {
+                "item of batch failed, details.index is index of item (for import details.line is its line and details.report - \
+                 committed rows), details.cause - code, status and details of its error"
+            }
+            ErrorCode::LockFailed => "key or range is locked by other transaction",
+            ErrorCode::LockTimeout => "lock wasn't released by other transaction in time of lock wait, details.lock_tx_id is its holder",
+            ErrorCode::ChangesEvicted => "changes after since were evicted from change log, details.first_seq is oldest kept change",
+            ErrorCode::Internal => "internal error of server",
+        }
+    }
+}
+
+/** Description of error body and codes for api specification */
+pub fn errors_description() -> String {
+    ERROR_CODES.iter().fold(String::from("Errors are returned as { \"code\", \"message\", \"details\", \"tx_id\", \"table\" }. Codes:"),
+                            |acc, code| {
+                                format!("{}\n{} ({}) - {}",
+                                        acc,
+                                        code.name(),
+                                        code.status().to_u16(),
+                                        code.description())
+                            })
+}
+
+impl ClientError {
+    pub fn new(error_type: ClientErrorType) -> ClientError {
+        let (code, details) = match error_type {
+            ClientErrorType::GettingParamsError(ref errors) => {
+                (ErrorCode::InvalidParams, JsonValue::Array(errors.iter().map(|error| JsonValue::String(error.clone())).collect()))
+            }
+            ClientErrorType::CommonError(_) => (ErrorCode::Internal, JsonValue::Null),
+            ClientErrorType::Failed(code, _) => (code, JsonValue::Null),
+        };
+        ClientError {
+            description: ClientError::get_description(&error_type),
+            error_type: error_type,
+            code: code,
+            details: details,
+            tx_id: None,
+            table: None,
+        }
+    }
+
+    fn get_description(error_type: &ClientErrorType) -> String {
+        match *error_type {
+            ClientErrorType::GettingParamsError(ref param_names) => {
+                param_names.iter()
+                    .fold(String::from("Getting params error: "),
+                          |acc, name| acc + name + ";")
+            }
+            ClientErrorType::CommonError(ref message) |
+            ClientErrorType::Failed(_, ref message) => message.clone(),
+        }
+    }
+
+    fn with(mut self, details: JsonValue, tx_id: Option<u64>, table: Option<String>) -> ClientError {
+        self.details = details;
+        self.tx_id = tx_id;
+        self.table = table;
+        self
+    }
+
+    // Table and transaction of request are set, if error doesn't define them
+    pub fn with_params(mut self, params: &JsonValue) -> ClientError {
+        if self.table.is_none() {
+            self.table = params.find("table_name").and_then(|table| table.as_str()).map(String::from);
+        }
+        if self.tx_id.is_none() {
+            self.tx_id = params.find("tx_id").and_then(|tx_id| tx_id.as_u64()).and_then(|tx_id| {
+                if tx_id == DEFAULT_TX_ID { None } else { Some(tx_id) }
+            });
+        }
+        self
+    }
+}
+
+// Client sees, why item of batch failed, as if it was executed alone
+fn batch_failed(error: ClientError, mut details: BTreeMap<String, JsonValue>, cause: PersistenceError) -> ClientError {
+    let cause = ClientError::from(cause);
+    let mut cause_details = BTreeMap::new();
+    cause_details.insert(String::from("code"), JsonValue::String(String::from(cause.code.name())));
+    cause_details.insert(String::from("status"), JsonValue::U64(cause.code.status().to_u16() as u64));
+    cause_details.insert(String::from("details"), cause.details);
+    details.insert(String::from("cause"), JsonValue::Object(cause_details));
+    error.with(JsonValue::Object(details), cause.tx_id, cause.table)
+}
+
+fn details(name: &str, value: JsonValue) -> JsonValue {
+    let mut res = BTreeMap::new();
+    res.insert(String::from(name), value);
+    JsonValue::Object(res)
+}
+
+impl From<IoEntityError> for ClientError {
+    fn from(error: IoEntityError) -> ClientError {
+        let kind = match error {
+            IoEntityError::Read(_) => "read",
+            IoEntityError::Write(_) => "write",
+        };
+        ClientError::new(ClientErrorType::Failed(ErrorCode::WrongEntity, error.to_string()))
+            .with(details("kind", JsonValue::String(String::from(kind))), None, None)
+    }
+}
+
+impl From<PersistenceError> for ClientError {
+    fn from(error: PersistenceError) -> ClientError {
+        let message = error.to_string();
+        let failed = |code| ClientError::new(ClientErrorType::Failed(code, message.clone()));
+        match error {
+            PersistenceError::IoEntity(error) => ClientError::from(error),
+            PersistenceError::TableNotFound(name) => failed(ErrorCode::TableNotFound).with(JsonValue::Null, None, Some(name)),
+            PersistenceError::EntityNotFound(_) => failed(ErrorCode::EntityNotFound),
+            PersistenceError::UndefinedTransaction(tx_id) => failed(ErrorCode::TransactionNotFound).with(JsonValue::Null, Some(tx_id), None),
+            PersistenceError::TransactionAlreadyStarted(tx_id) => {
+                failed(ErrorCode::TransactionConflict).with(JsonValue::Null, Some(tx_id), None)
+            }
+            PersistenceError::TransactionFailed(_) => failed(ErrorCode::TransactionConflict),
+            PersistenceError::WrongTransaction(lock_tx_id, tx_id) => {
+                failed(ErrorCode::TransactionConflict).with(details("lock_tx_id", JsonValue::U64(lock_tx_id)), Some(tx_id), None)
+            }
+            PersistenceError::LockFailed(_) => failed(ErrorCode::LockFailed),
+            PersistenceError::LockTimeout(tx_id, lock_tx_id) => {
+                failed(ErrorCode::LockTimeout).with(details("lock_tx_id", JsonValue::U64(lock_tx_id)), Some(tx_id), None)
+            }
+            PersistenceError::ProcessorFailed(_) => failed(ErrorCode::ProcessorFailed),
+            PersistenceError::BatchFailed(index, cause) => {
+                let mut res = BTreeMap::new();
+                res.insert(String::from("index"), JsonValue::U64(index as u64));
+                batch_failed(failed(ErrorCode::BatchFailed), res, *cause)
+            }
+            PersistenceError::ImportFailed(line, report, cause) => {
+                let mut res = BTreeMap::new();
+                res.insert(String::from("line"), JsonValue::U64(line as u64));
+                res.insert(String::from("report"), report.to_json());
+                batch_failed(failed(ErrorCode::BatchFailed), res, *cause)
+            }
+            PersistenceError::SavepointNotFound(name) => {
+                failed(ErrorCode::SavepointNotFound).with(details("savepoint", JsonValue::String(name)), None, None)
+            }
+            PersistenceError::ReservedSavepoint(name) => {
+                failed(ErrorCode::InvalidParams).with(details("savepoint", JsonValue::String(name)), None, None)
+            }
+            PersistenceError::ReadOnlyTransaction(tx_id) => failed(ErrorCode::ReadOnlyTransaction).with(JsonValue::Null, Some(tx_id), None),
+            PersistenceError::TransactionPrepared(tx_id) => failed(ErrorCode::TransactionPrepared).with(JsonValue::Null, Some(tx_id), None),
+            PersistenceError::TransactionNotPrepared(tx_id) => {
+                failed(ErrorCode::TransactionNotPrepared).with(JsonValue::Null, Some(tx_id), None)
+            }
+            PersistenceError::TransactionKilled(tx_id) => failed(ErrorCode::TransactionKilled).with(JsonValue::Null, Some(tx_id), None),
+            PersistenceError::ChangesEvicted(first_seq) => {
+                failed(ErrorCode::ChangesEvicted).with(details("first_seq", JsonValue::U64(first_seq)), None, None)
+            }
+            PersistenceError::Undefined(_) |
+            PersistenceError::PreparedLogFailed(_) => failed(ErrorCode::Internal),
+        }
+    }
+}
+
+impl ToJson for ClientError {
+    fn to_json(&self) -> JsonValue {
+        let mut res = BTreeMap::new();
+        res.insert(String::from("code"), JsonValue::String(String::from(self.code.name())));
+        res.insert(String::from("message"), JsonValue::String(self.description.clone()));
+        res.insert(String::from("details"), self.details.clone());
+        res.insert(String::from("tx_id"), self.tx_id.map(JsonValue::U64).unwrap_or(JsonValue::Null));
+        res.insert(String::from("table"), self.table.clone().map(JsonValue::String).unwrap_or(JsonValue::Null));
+        JsonValue::Object(res)
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn description(&self) -> &str {
+        self.description.as_str()
+    }
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
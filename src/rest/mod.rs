@@ -0,0 +1,1058 @@
+// REST API over data base, built with rustless. Compiled with feature "rest"
+use std;
+
+use iron;
+use valico::json_dsl;
+use rustless::batteries::swagger;
+
+use rustless::{Application, Api, Nesting, Versioning};
+use rustless::framework::client::{Client, ClientResult};
+
+use data_base::DataBaseManager;
+use data_base::json::{JsonValue, ToJson};
+use data_base::meta::TableDescriptionView;
+use data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+use data_base::bulk::{BulkFormat, DEFAULT_IMPORT_BATCH_SIZE};
+use data_base::changes::ChangeEventStream;
+use data_base::query::{ContinuousQuery, QueryEventStream};
+use data_base::processor::EntryProcessor;
+use data_base::PersistenceError;
+
+use self::app_extension::DataBaseExtension;
+use self::auth::{Access, AuthConfig, Credentials, Permission, Principal, Security, ALL_TABLES};
+use self::error::{ClientError, ClientErrorType, ErrorCode};
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod app_extension;
+pub mod auth;
+pub mod error;
+pub mod openapi;
+
+const DEFAULT_CHANGES_LIMIT: u64 = 1000;
+pub const DEFAULT_CHANGES_TIMEOUT_SECS: u64 = 30;
+pub const MAX_CHANGES_TIMEOUT_SECS: u64 = 300;
+
+// Settings of REST handlers, that are set by configuration of server
+#[derive(Debug, Clone)]
+pub struct RestSettings {
+    pub host: String, // host of swagger specification
+    pub lock_mode: LockMode, // lock mode of transaction, started without mode
+    pub changes_timeout_secs: u64, // long poll of changes without timeout
+    pub max_changes_timeout_secs: u64,
+    pub auth: Option<AuthConfig>, // None - api is open
+}
+
+impl Default for RestSettings {
+    fn default() -> RestSettings {
+        RestSettings {
+            host: String::from("localhost:4300"),
+            lock_mode: LockMode::Pessimistic,
+            changes_timeout_secs: DEFAULT_CHANGES_TIMEOUT_SECS,
+            max_changes_timeout_secs: MAX_CHANGES_TIMEOUT_SECS,
+            auth: None,
+        }
+    }
+}
+
+fn handle_response<'a, F>(mut client: Client<'a>, params: &JsonValue, handler: F) -> ClientResult<'a>
+    where F: Fn(&mut Client<'a>) -> Result<JsonValue, ClientError>
+{
+    match handler(&mut client) {
+        Ok(res) => client.json(&res),
+        Err(error) => error_response(client, params, error),
+    }
+}
+
+fn error_response<'a>(mut client: Client<'a>, params: &JsonValue, error: ClientError) -> ClientResult<'a> {
+    let error = error.with_params(params);
+    debug!("Request failed with {}: {}", error.code.name(), error.description);
+    if error.code == ErrorCode::Unauthorized {
+        client.response.headers.set_raw("WWW-Authenticate", vec![b"Basic realm=\"nimdg\"".to_vec()]);
+    }
+    client.set_status(error.code.status());
+    client.json(&error.to_json())
+}
+
+/** Check access of principal of request, None - security is not enabled.
+ * Table is got only from parameter, that is named by access, so other parameters cannot replace it.
+ * Transaction of parameter tx_id must be owned by principal or principal must be admin */
+fn authorize<'a>(client: &Client<'a>, params: &JsonValue, access: Access) -> Result<Option<Principal>, ClientError> {
+    let security = match client.app.get_security() {
+        Some(security) => security,
+        None => return Ok(None),
+    };
+    let credentials = client.request
+        .headers()
+        .get_raw("Authorization")
+        .and_then(|values| values.first())
+        .and_then(|value| String::from_utf8(value.clone()).ok())
+        .and_then(|value| Credentials::from_header(&value));
+    let principal = try!(credentials.and_then(|credentials| security.authenticate(&credentials))
+        .ok_or(ClientError::new(ClientErrorType::Failed(ErrorCode::Unauthorized, String::from("Authentication required")))));
+
+    let forbidden = |message: String| ClientError::new(ClientErrorType::Failed(ErrorCode::Forbidden, message));
+    let table_access = match access {
+        Access::Authenticated => None,
+        // Parameter is required by route, without it permission is checked for all tables
+        Access::Table(permission, parameter) => {
+            Some((permission, params.find(parameter).and_then(|table_name| table_name.as_str()).unwrap_or(ALL_TABLES)))
+        }
+        Access::AllTables(permission) => Some((permission, ALL_TABLES)),
+        Access::Admin => {
+            if !security.is_admin(&principal) {
+                return Err(forbidden(format!("Principal {} is not admin", principal.name)));
+            }
+            None
+        }
+    };
+    if let Some((permission, table_name)) = table_access {
+        if !security.allows(&principal, table_name, permission) {
+            return Err(forbidden(format!("Principal {} has no permission {} for table {}",
+                                         principal.name,
+                                         permission.name(),
+                                         table_name)));
+        }
+    }
+    let tx_id = params.find("tx_id").and_then(|tx_id| tx_id.as_u64()).unwrap_or(DEFAULT_TX_ID);
+    if tx_id != DEFAULT_TX_ID && !security.can_use_transaction(&principal, tx_id) {
+        return Err(forbidden(format!("Transaction {} is not owned by {}", tx_id, principal.name)));
+    }
+    Ok(Some(principal))
+}
+
+// Tables, that principal of request can read, all tables - security is not enabled
+fn readable_tables<'a>(client: &Client<'a>,
+                       principal: &Option<Principal>,
+                       tables: std::collections::BTreeMap<String, JsonValue>)
+                       -> std::collections::BTreeMap<String, JsonValue> {
+    match (client.app.get_security(), principal.as_ref()) {
+        (Some(security), Some(principal)) => {
+            tables.into_iter().filter(|&(ref table_name, _)| security.allows(principal, table_name, Permission::Read)).collect()
+        }
+        _ => tables,
+    }
+}
+
+// Owner of transaction is forgotten, when transaction is finished
+fn release_transaction_owner<'a>(client: &Client<'a>, tx_id: u64) {
+    if let Some(security) = client.app.get_security() {
+        security.release_owner(tx_id);
+    }
+}
+
+fn get_key_and_value(params: &JsonValue) -> Result<(&JsonValue, &JsonValue), String> {
+    let data = try!(params.find("data").and_then(|data| data.as_object()).ok_or("Param data not found"));
+    let key = try!(data.get("key").ok_or("Attribute key not found"));
+    let value = try!(data.get("value").ok_or("Attribute value not found"));
+    Ok((&key, &value))
+}
+
+fn get_parameter<'s, T>(name: &str, params: &'s JsonValue, mapping: &Fn(&'s JsonValue) -> Option<T>) -> Result<T, ClientError> {
+    params.find(name)
+        .and_then(|value| mapping(value))
+        .ok_or(ClientError::new(ClientErrorType::GettingParamsError(vec![format!("{}:{}",
+                                                                                 String::from(name),
+                                                                                 params.find(name)
+                                                                                     .map(|param| param.to_string())
+                                                                                     .unwrap_or("not found".to_string()))])))
+}
+
+// Key is passed in path as json
+fn get_key_parameter(params: &JsonValue) -> Result<JsonValue, ClientError> {
+    params.find("key")
+        .and_then(|key| key.as_str())
+        .map(|key| {
+            JsonValue::from_str(key)
+                .map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![format!("key:{}", error)])))
+        })
+        .unwrap_or(Err(ClientError::new(ClientErrorType::GettingParamsError(vec![format!("key")]))))
+}
+
+// Omitted tx_id means implicit transaction for single operation
+fn get_tx_id_parameter(params: &JsonValue) -> Result<u64, ClientError> {
+    match params.find("tx_id") {
+        Some(_) => get_parameter("tx_id", params, &JsonValue::as_u64),
+        None => Ok(DEFAULT_TX_ID),
+    }
+}
+
+fn get_atomic_parameter(params: &JsonValue) -> bool {
+    params.find("atomic").and_then(|atomic| atomic.as_bool()).unwrap_or(false)
+}
+
+// Each item of batch result is { "ok": true, "value": ... } or { "ok": false, "error": "..." }
+fn batch_results_to_json<T, F>(results: Vec<Result<T, PersistenceError>>, to_json: F) -> JsonValue
+    where F: Fn(T) -> JsonValue
+{
+    JsonValue::Array(results.into_iter()
+        .map(|result| {
+            let mut item = std::collections::BTreeMap::new();
+            match result {
+                Ok(value) => {
+                    item.insert(String::from("ok"), JsonValue::Bool(true));
+                    item.insert(String::from("value"), to_json(value));
+                }
+                Err(error) => {
+                    let error = ClientError::from(error);
+                    item.insert(String::from("ok"), JsonValue::Bool(false));
+                    item.insert(String::from("code"), JsonValue::String(String::from(error.code.name())));
+                    item.insert(String::from("error"), JsonValue::String(error.description));
+                }
+            }
+            JsonValue::Object(item)
+        })
+        .collect())
+}
+
+fn get_format_parameter(params: &JsonValue) -> Result<BulkFormat, ClientError> {
+    params.find("format")
+        .and_then(|format| format.as_str())
+        .map_or(Ok(BulkFormat::JsonLines), BulkFormat::from_str)
+        .map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error])))
+}
+
+fn get_keys_parameter(params: &JsonValue) -> Result<Vec<JsonValue>, ClientError> {
+    params.find("keys")
+        .and_then(|keys| keys.as_array())
+        .map(|keys| keys.clone())
+        .ok_or(ClientError::new(ClientErrorType::GettingParamsError(vec![String::from("keys: array not found")])))
+}
+
+fn get_entity<'a>(client: &mut Client<'a>, params: &JsonValue) -> Result<JsonValue, ClientError> {
+    info!("get entity from table {}", params);
+    let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+    let key = try!(get_key_parameter(params));
+    let tx_id = try!(get_tx_id_parameter(params));
+
+    let value = client.app.get_data_base_manager().get_data(&tx_id, &String::from(table_name), &key);
+    match value {
+        Ok(value) => {
+            match value {
+                Some(value) => Ok(value),
+                None => Err(ClientError::new(ClientErrorType::Failed(ErrorCode::EntityNotFound, format!("Entity with key {} not found", key)))),
+            }
+        }
+        Err(error) => Err(ClientError::from(error)),
+    }
+}
+
+fn delete_entity<'a>(client: &mut Client<'a>, params: &JsonValue) -> Result<JsonValue, ClientError> {
+    info!("delete entity from table {}", params);
+    let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+    let key = try!(get_key_parameter(params));
+    let tx_id = try!(get_tx_id_parameter(params));
+
+    match client.app.get_data_base_manager().remove_data(&tx_id, &String::from(table_name), &key) {
+        Ok(true) => Ok(JsonValue::String(String::from("Done"))),
+        Ok(false) => Err(ClientError::new(ClientErrorType::Failed(ErrorCode::EntityNotFound, format!("Entity with key {} not found", key)))),
+        Err(error) => Err(ClientError::from(error)),
+    }
+}
+
+/** REST application over given data base. Logging is not initialized here,
+ * so application may be mounted by embedding program */
+pub fn create_application(data_base_manager: Arc<DataBaseManager>, settings: RestSettings) -> Application {
+    let security = settings.auth.as_ref().map(|auth| Arc::new(Security::from_config(auth)));
+    create_application_with_security(data_base_manager, settings, security)
+}
+
+/** Application with own authenticators, None - api is open.
+ * Security may be shared with listeners, so transactions have the same owners in all front-ends */
+pub fn create_application_with_security(data_base_manager: Arc<DataBaseManager>,
+                                        settings: RestSettings,
+                                        security: Option<Arc<Security>>)
+                                        -> Application {
+    let api = Api::build(|api| {
+        api.version("v1", Versioning::Path);
+        api.prefix("api");
+
+        api.mount(swagger::create_api("api-docs"));
+
+        api.mount(Api::build(|cache_api| {
+
+            cache_api.get("openapi", |endpoint| {
+                endpoint.desc("OpenAPI document with typed paths and models of current tables");
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        let principal = try!(authorize(client, params, Access::Authenticated));
+                        let tables = readable_tables(client, &principal, client.app.get_data_base_manager().get_tables_schemas());
+                        Ok(openapi::openapi_spec(&tables, &client.app.get_settings().host))
+                    })
+                })
+            });
+
+            cache_api.get("info", |endpoint| {
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        let principal = try!(authorize(client, params, Access::Authenticated));
+                        let db_manager = client.app.get_data_base_manager();
+                        db_manager.print_info();
+                        let tables = match db_manager.get_tables_json_list() {
+                            JsonValue::Object(tables) => tables,
+                            _ => std::collections::BTreeMap::new(),
+                        };
+                        Ok(JsonValue::Object(readable_tables(client, &principal, tables)))
+                    })
+                })
+            });
+
+            cache_api.get("metrics", |endpoint| {
+                endpoint.desc("Metrics of operations, transactions, locks and tables in Prometheus text format");
+
+                endpoint.handle(|mut client, params| {
+                    if let Err(error) = authorize(&client, params, Access::AllTables(Permission::Read)) {
+                        return error_response(client, params, error);
+                    }
+                    let metrics = client.app.get_data_base_manager().get_metrics_text();
+                    client.set_header(iron::headers::ContentType("text/plain; version=0.0.4".parse().unwrap()));
+                    client.response.replace_body(Box::new(metrics));
+                    Ok(client)
+                })
+            });
+
+            cache_api.namespace("tx/:mode", |tx_ns| {
+                tx_ns.post("start", |endpoint| {
+                    endpoint.params(|params| {
+                        params.opt_typed("mode", json_dsl::string());
+                        params.opt_typed("isolation", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            let principal = try!(authorize(client, params, Access::Authenticated));
+                            let mode = params.find("mode")
+                                .and_then(|mode_json| mode_json.as_str())
+                                .map(|mode_string| {
+                                    LockMode::from_str(mode_string).map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error])))
+                                })
+                                .unwrap_or(Ok(client.app.get_settings().lock_mode.clone()));
+                            let isolation_level = params.find("isolation")
+                                .and_then(|level_json| level_json.as_str())
+                                .map(|level_string| {
+                                    IsolationLevel::from_str(level_string)
+                                        .map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error])))
+                                })
+                                .unwrap_or(Ok(IsolationLevel::RepeatableRead));
+
+                            let db_manager = client.app.get_data_base_manager();
+                            match db_manager.tx_start_with_isolation(try!(mode), try!(isolation_level)) {
+                                Ok(tx_id) => {
+                                    debug!("Response start tx {}", tx_id);
+                                    if let (Some(security), Some(principal)) = (client.app.get_security(), principal) {
+                                        security.set_owner(tx_id, &principal);
+                                    }
+                                    Ok(JsonValue::U64(tx_id))
+                                }
+                                Err(error) => Err(ClientError::from(error)),
+                            }
+                        })
+                    })
+                });
+
+                tx_ns.delete("stop/:tx_id", |endpoint| {
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+
+                            let db_manager = client.app.get_data_base_manager();
+                            match db_manager.tx_stop(&tx_id) {
+                                Ok(()) => {
+                                    release_transaction_owner(client, tx_id);
+                                    Ok(JsonValue::String(String::from("done")))
+                                }
+                                Err(error) => Err(ClientError::from(error)),
+                            }
+                        })
+                    })
+                })
+            });
+
+            cache_api.delete("tx/rollback/:tx_id", |endpoint| {
+                endpoint.desc("Rollback transaction: undo its changes and release locks");
+                endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Authenticated));
+                        let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                        client.app
+                            .get_data_base_manager()
+                            .tx_rollback(&tx_id)
+                            .map(|_| release_transaction_owner(client, tx_id))
+                            .map(|_| JsonValue::String(String::from("done")))
+                            .map_err(ClientError::from)
+                    })
+                })
+            });
+
+            cache_api.namespace("tx/:tx_id", |tx_ns| {
+                tx_ns.post("savepoint/:name", |endpoint| {
+                    endpoint.desc("Set savepoint in transaction");
+                    endpoint.params(|params| {
+                        params.req_typed("tx_id", json_dsl::u64());
+                        params.req_typed("name", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            let name = try!(get_parameter("name", params, &JsonValue::as_str));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_savepoint(&tx_id, &String::from(name))
+                                .map(|_| JsonValue::String(String::from("done")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                tx_ns.post("rollback-to/:name", |endpoint| {
+                    endpoint.desc("Undo changes made after savepoint, release_locks - unlock keys locked after savepoint");
+                    endpoint.params(|params| {
+                        params.req_typed("tx_id", json_dsl::u64());
+                        params.req_typed("name", json_dsl::string());
+                        params.opt_typed("release_locks", json_dsl::boolean())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            let name = try!(get_parameter("name", params, &JsonValue::as_str));
+                            let release_locks = params.find("release_locks").and_then(|release| release.as_bool()).unwrap_or(false);
+                            client.app
+                                .get_data_base_manager()
+                                .tx_rollback_to_savepoint(&tx_id, &String::from(name), release_locks)
+                                .map(|_| JsonValue::String(String::from("done")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                tx_ns.delete("savepoint/:name", |endpoint| {
+                    endpoint.desc("Release savepoint and all later savepoints");
+                    endpoint.params(|params| {
+                        params.req_typed("tx_id", json_dsl::u64());
+                        params.req_typed("name", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            let name = try!(get_parameter("name", params, &JsonValue::as_str));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_release_savepoint(&tx_id, &String::from(name))
+                                .map(|_| JsonValue::String(String::from("done")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                tx_ns.post("prepare", |endpoint| {
+                    endpoint.desc("Durably record changes of transaction and keep locks until decision of coordinator");
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_prepare(&tx_id)
+                                .map(|_| JsonValue::String(String::from("prepared")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                tx_ns.post("commit-prepared", |endpoint| {
+                    endpoint.desc("Commit prepared or in doubt transaction");
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_commit_prepared(&tx_id)
+                                .map(|_| release_transaction_owner(client, tx_id))
+                                .map(|_| JsonValue::String(String::from("done")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                tx_ns.post("rollback-prepared", |endpoint| {
+                    endpoint.desc("Rollback prepared or in doubt transaction");
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_rollback_prepared(&tx_id)
+                                .map(|_| release_transaction_owner(client, tx_id))
+                                .map(|_| JsonValue::String(String::from("done")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+            });
+
+            cache_api.post("put/:table_name", |endpoint| {
+                endpoint.params(|params| {
+                    params.req_typed("table_name", json_dsl::string());
+                    params.opt_typed("tx_id", json_dsl::u64());
+                    params.req("data", |_| {})
+                });
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                        info!("put entity to table");
+                        match get_key_and_value(params) {
+                            Ok((key, value)) => {
+                                let db_manager = client.app.get_data_base_manager();
+                                let tx_id = try!(get_tx_id_parameter(params));
+                                let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+
+                                db_manager.add_data(&tx_id, &String::from(table_name), &key, &value)
+                                    .map(|_| JsonValue::String("Done".to_string()))
+                                    .map_err(ClientError::from)
+                            }
+                            Err(message) => Err(ClientError::new(ClientErrorType::GettingParamsError(vec![message]))),
+                        }
+                    })
+                })
+            });
+
+            for path in &["get/:table_name/:tx_id/:key", "get/:table_name/:key"] {
+                cache_api.get(path, |endpoint| {
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.req("key", |_| {}); //, json_dsl::object());
+                        params.opt_typed("tx_id", json_dsl::u64())
+                    });
+
+                    endpoint.handle(|client, params| handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Table(Permission::Read, "table_name")));
+                        get_entity(client, params)
+                    }))
+                });
+            }
+
+            for path in &["delete/:table_name/:tx_id/:key", "delete/:table_name/:key"] {
+                cache_api.delete(path, |endpoint| {
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.req("key", |_| {});
+                        params.opt_typed("tx_id", json_dsl::u64())
+                    });
+
+                    endpoint.handle(|client, params| handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                        delete_entity(client, params)
+                    }))
+                });
+            }
+
+            cache_api.get("get/:table_name/:tx_id/:start/:count", |endpoint| {
+                endpoint.params(|params| {
+                    params.req_typed("table_name", json_dsl::string());
+                    params.req_typed("tx_id", json_dsl::u64());
+                    params.req_typed("start", json_dsl::u64());
+                    params.req_typed("count", json_dsl::u64())
+                });
+
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Table(Permission::Read, "table_name")));
+                        debug!("Get list entities from table {}", params);
+                        let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                        let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                        let start = try!(get_parameter("start", params, &JsonValue::as_u64));
+                        let count = try!(get_parameter("count", params, &JsonValue::as_u64));
+                        let db_manager = client.app.get_data_base_manager();
+
+                        let data_list = db_manager.get_list(tx_id,
+                                                            &String::from(table_name),
+                                                            start as u32,
+                                                            count as u32);
+                        data_list.map(|data_list| JsonValue::Array(data_list))
+                            .map_err(ClientError::from)
+                    })
+                })
+            });
+
+            cache_api.namespace("batch", |batch_ns| {
+                batch_ns.post("put/:table_name", |endpoint| {
+                    endpoint.desc("Put array of key/value pairs");
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.opt_typed("tx_id", json_dsl::u64());
+                        params.opt_typed("atomic", json_dsl::boolean());
+                        params.req_typed("data", json_dsl::array())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                            let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                            let tx_id = try!(get_tx_id_parameter(params));
+                            let data = try!(get_parameter("data", params, &JsonValue::as_array));
+                            let entries = try!(data.iter()
+                                .map(|entry| {
+                                    match (entry.find("key"), entry.find("value")) {
+                                        (Some(key), Some(value)) => Ok((key.clone(), value.clone())),
+                                        _ => Err(ClientError::new(ClientErrorType::GettingParamsError(vec![format!("data: key or value not found in {}", entry)]))),
+                                    }
+                                })
+                                .collect::<Result<Vec<(JsonValue, JsonValue)>, ClientError>>());
+                            info!("put {} entities to table {}", entries.len(), table_name);
+
+                            client.app
+                                .get_data_base_manager()
+                                .put_all(&tx_id, &String::from(table_name), &entries, get_atomic_parameter(params))
+                                .map(|results| batch_results_to_json(results, |_| JsonValue::Null))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                batch_ns.post("get/:table_name", |endpoint| {
+                    endpoint.desc("Get values by array of keys, null for not found");
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.opt_typed("tx_id", json_dsl::u64());
+                        params.opt_typed("atomic", json_dsl::boolean());
+                        params.req_typed("keys", json_dsl::array())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                            try!(authorize(client, params, Access::Table(Permission::Read, "table_name")));
+                            let tx_id = try!(get_tx_id_parameter(params));
+                            let keys = try!(get_keys_parameter(params));
+
+                            client.app
+                                .get_data_base_manager()
+                                .get_all(&tx_id, &String::from(table_name), &keys, get_atomic_parameter(params))
+                                .map(|results| batch_results_to_json(results, |value| value.unwrap_or(JsonValue::Null)))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                batch_ns.post("delete/:table_name", |endpoint| {
+                    endpoint.desc("Delete values by array of keys, value of result is false for not found");
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.opt_typed("tx_id", json_dsl::u64());
+                        params.opt_typed("atomic", json_dsl::boolean());
+                        params.req_typed("keys", json_dsl::array())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                            try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                            let tx_id = try!(get_tx_id_parameter(params));
+                            let keys = try!(get_keys_parameter(params));
+
+                            client.app
+                                .get_data_base_manager()
+                                .remove_all(&tx_id, &String::from(table_name), &keys, get_atomic_parameter(params))
+                                .map(|results| batch_results_to_json(results, |removed| JsonValue::Bool(removed)))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+            });
+
+            cache_api.namespace("bulk", |bulk_ns| {
+                bulk_ns.post("import/:table_name", |endpoint| {
+                    endpoint.desc("Import rows of JSON lines or CSV, each batch is committed in own transaction");
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.opt_typed("format", json_dsl::string());
+                        params.opt_typed("batch_size", json_dsl::u64());
+                        params.req_typed("data", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                            let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                            let format = try!(get_format_parameter(params));
+                            let batch_size = params.find("batch_size")
+                                .and_then(|batch_size| batch_size.as_u64())
+                                .map(|batch_size| batch_size as usize)
+                                .unwrap_or(DEFAULT_IMPORT_BATCH_SIZE);
+                            let data = try!(get_parameter("data", params, &JsonValue::as_str));
+                            info!("import {} rows of {} to table {}", format.name(), data.lines().count(), table_name);
+
+                            client.app
+                                .get_data_base_manager()
+                                .import(&String::from(table_name), &format, data.as_bytes(), batch_size)
+                                .map(|report| report.to_json())
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                bulk_ns.get("export/:table_name/:format", |endpoint| {
+                    endpoint.desc("Export last committed entities as JSON lines or CSV");
+                    endpoint.params(|params| {
+                        params.req_typed("table_name", json_dsl::string());
+                        params.req_typed("format", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Read, "table_name")));
+                            let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                            let format = try!(get_format_parameter(params));
+                            let mut data = Vec::new();
+                            let count = try!(client.app
+                                .get_data_base_manager()
+                                .export(&String::from(table_name), &format, &mut data)
+                                .map_err(ClientError::from));
+                            let mut res = std::collections::BTreeMap::new();
+                            res.insert(String::from("count"), JsonValue::U64(count as u64));
+                            res.insert(String::from("data"), JsonValue::String(String::from_utf8_lossy(&data).into_owned()));
+                            Ok(JsonValue::Object(res))
+                        })
+                    })
+                });
+            });
+
+            cache_api.post("invoke/:table_name/:tx_id/:key", |endpoint| {
+                endpoint.desc("Apply processor to value under lock, tx_id = 0 - in own transaction");
+                endpoint.params(|params| {
+                    params.req_typed("table_name", json_dsl::string());
+                    params.req_typed("tx_id", json_dsl::u64());
+                    params.req("key", |_| {});
+                    params.req_typed("op", json_dsl::string());
+                    params.req_typed("field", json_dsl::string())
+                });
+
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::Table(Permission::Write, "table_name")));
+                        let table_name = try!(get_parameter("table_name", params, &JsonValue::as_str));
+                        let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                        let key = try!(get_key_parameter(params));
+                        let processor = try!(EntryProcessor::from_json(params)
+                            .map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error]))));
+                        debug!("Invoke {:?} for key {} in table {}", processor, key, table_name);
+
+                        let db_manager = client.app.get_data_base_manager();
+                        let (value, applied) = try!(db_manager.invoke(&tx_id, &String::from(table_name), &key, &processor)
+                            .map_err(ClientError::from));
+                        let mut res = std::collections::BTreeMap::new();
+                        res.insert(String::from("value"), value);
+                        res.insert(String::from("applied"), JsonValue::Bool(applied));
+                        Ok(JsonValue::Object(res))
+                    })
+                })
+            });
+
+            cache_api.get("changes", |endpoint| {
+                endpoint.desc("Long poll of committed changes with sequence number greater than since");
+                endpoint.params(|params| {
+                    params.opt_typed("since", json_dsl::u64());
+                    params.opt_typed("limit", json_dsl::u64());
+                    params.opt_typed("timeout", json_dsl::u64())
+                });
+
+                endpoint.handle(|client, params| {
+                    handle_response(client, params, |client| {
+                        try!(authorize(client, params, Access::AllTables(Permission::Read)));
+                        let since = params.find("since").and_then(|since| since.as_u64()).unwrap_or(0);
+                        let limit = params.find("limit").and_then(|limit| limit.as_u64()).unwrap_or(DEFAULT_CHANGES_LIMIT);
+                        let settings = client.app.get_settings();
+                        let timeout = params.find("timeout")
+                            .and_then(|timeout| timeout.as_u64())
+                            .unwrap_or(settings.changes_timeout_secs);
+                        let change_log = client.app.get_data_base_manager().get_change_log();
+                        let changes = try!(change_log.wait_since(since,
+                                                                 limit as usize,
+                                                                 Duration::from_secs(std::cmp::min(timeout, settings.max_changes_timeout_secs))));
+                        let mut res = std::collections::BTreeMap::new();
+                        res.insert(String::from("first_seq"), JsonValue::U64(change_log.first_seq()));
+                        res.insert(String::from("last_seq"), JsonValue::U64(change_log.last_seq()));
+                        res.insert(String::from("changes"),
+                                   JsonValue::Array(changes.iter().map(|change| change.to_json()).collect()));
+                        Ok(JsonValue::Object(res))
+                    })
+                })
+            });
+
+            cache_api.get("changes/stream", |endpoint| {
+                endpoint.desc("Server-sent events stream of committed changes");
+                endpoint.params(|params| params.opt_typed("since", json_dsl::u64()));
+
+                endpoint.handle(|mut client, params| {
+                    if let Err(error) = authorize(&client, params, Access::AllTables(Permission::Read)) {
+                        return error_response(client, params, error);
+                    }
+                    // Reconnecting event source sends id of last received event
+                    let last_event_id = client.request
+                        .headers()
+                        .get_raw("Last-Event-ID")
+                        .and_then(|values| values.first())
+                        .and_then(|value| String::from_utf8(value.clone()).ok())
+                        .and_then(|value| u64::from_str(value.trim()).ok());
+                    let since = last_event_id.or(params.find("since").and_then(|since| since.as_u64()))
+                        .unwrap_or_else(|| client.app.get_data_base_manager().get_change_log().last_seq());
+                    debug!("Start changes stream since {}", since);
+
+                    let change_log = client.app.get_data_base_manager().get_change_log();
+                    client.set_header(iron::headers::ContentType("text/event-stream".parse().unwrap()));
+                    client.set_header(iron::headers::CacheControl(vec![iron::headers::CacheDirective::NoCache]));
+                    client.response.replace_body(Box::new(ChangeEventStream::new(change_log, since)));
+                    Ok(client)
+                })
+            });
+
+            cache_api.namespace("queries", |queries_ns| {
+                queries_ns.get("subscribe", |endpoint| {
+                    endpoint.desc("Register continuous query and stream its events, query is dropped on disconnect");
+                    endpoint.params(|params| {
+                        params.req_typed("table", json_dsl::string());
+                        params.opt_typed("conditions", json_dsl::string())
+                    });
+
+                    endpoint.handle(|mut client, params| {
+                        if let Err(error) = authorize(&client, params, Access::Table(Permission::Read, "table")) {
+                            return error_response(client, params, error);
+                        }
+                        let table = params.find("table").map(|table| table.clone()).unwrap_or(JsonValue::Null);
+                        // Conditions are passed in query string as json array
+                        let conditions = params.find("conditions")
+                            .and_then(|conditions| conditions.as_str())
+                            .map(|conditions| JsonValue::from_str(conditions).map_err(|error| format!("conditions: {}", error)))
+                            .unwrap_or(Ok(JsonValue::Array(vec![])));
+                        let query = conditions.and_then(|conditions| {
+                            let mut query_json = std::collections::BTreeMap::new();
+                            query_json.insert(String::from("table"), table);
+                            query_json.insert(String::from("conditions"), conditions);
+                            ContinuousQuery::from_json(&JsonValue::Object(query_json))
+                        });
+                        let registration = query.map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error])))
+                            .and_then(|query| {
+                                client.app
+                                    .get_data_base_manager()
+                                    .register_query(query)
+                                    .map_err(ClientError::from)
+                            });
+                        match registration {
+                            Ok((query_id, initial, receiver)) => {
+                                info!("Continuous query {} registered, initial entries = {}",
+                                      query_id,
+                                      initial.len());
+                                let query_manager = client.app.get_data_base_manager().get_query_manager();
+                                client.set_header(iron::headers::ContentType("text/event-stream".parse().unwrap()));
+                                client.set_header(iron::headers::CacheControl(vec![iron::headers::CacheDirective::NoCache]));
+                                client.response.replace_body(Box::new(QueryEventStream::new(query_manager, query_id, initial, receiver)));
+                                Ok(client)
+                            }
+                            Err(error) => error_response(client, params, error),
+                        }
+                    })
+                });
+
+                queries_ns.get("list", |endpoint| {
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Admin));
+                            Ok(client.app.get_data_base_manager().get_query_manager().get_queries_list())
+                        })
+                    })
+                });
+
+                queries_ns.delete(":query_id", |endpoint| {
+                    endpoint.params(|params| params.req_typed("query_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Admin));
+                            let query_id = try!(get_parameter("query_id", params, &JsonValue::as_u64));
+                            if client.app.get_data_base_manager().get_query_manager().cancel(query_id) {
+                                Ok(JsonValue::String(String::from("done")))
+                            } else {
+                                Err(ClientError::new(ClientErrorType::Failed(ErrorCode::QueryNotFound,
+                                                                format!("Continuous query {} not found", query_id))))
+                            }
+                        })
+                    })
+                });
+            });
+
+            cache_api.namespace("meta", |meta_ns| {
+                meta_ns.post("table", |endpoint| {
+                    endpoint.desc("Update description");
+                    endpoint.params(|params| {
+                        params.req_typed("name", json_dsl::string());
+                        params.req_typed("key", json_dsl::object());
+                        params.req_typed("value", json_dsl::object());
+                        params.opt_typed("lock_mode", json_dsl::string())
+                    });
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Ddl, "name")));
+                            info!("Table update");
+                            let table_desc = try!(TableDescriptionView::from_json(params)
+                                .map_err(|error| ClientError::new(ClientErrorType::GettingParamsError(vec![error.to_string()]))));
+                            let db_manager = client.app.get_data_base_manager();
+                            let name = table_desc.name.clone();
+                            match db_manager.add_table(table_desc) {
+                                Ok(name) => Ok(JsonValue::String(format!("Table with name {} succefully added", name))),
+                                // Table is not created for existing name or unknown types of fields
+                                Err(message) => {
+                                    let code = if db_manager.get_table(&name).is_some() {
+                                        ErrorCode::TableExists
+                                    } else {
+                                        ErrorCode::InvalidParams
+                                    };
+                                    Err(ClientError::new(ClientErrorType::Failed(code, message)))
+                                }
+                            }
+                        })
+                    })
+                });
+
+                meta_ns.get("tx/list", |endpoint| {
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Admin));
+                            Ok(client.app.get_data_base_manager().get_transactions_list())
+                        })
+                    })
+                });
+
+                meta_ns.get("tx/prepared", |endpoint| {
+                    endpoint.desc("Prepared transactions, waiting for decision of coordinator, and in doubt transactions after restart");
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Admin));
+                            Ok(client.app.get_data_base_manager().get_prepared_list())
+                        })
+                    })
+                });
+
+                meta_ns.get("tx/:tx_id", |endpoint| {
+                    endpoint.desc("Transaction details with locked keys");
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            match client.app.get_data_base_manager().get_transaction_json(&tx_id) {
+                                Some(tx_json) => Ok(tx_json),
+                                None => Err(ClientError::from(PersistenceError::UndefinedTransaction(tx_id))),
+                            }
+                        })
+                    })
+                });
+
+                meta_ns.delete("tx/:tx_id", |endpoint| {
+                    endpoint.desc("Kill transaction: rollback it, transaction waiting for lock fails");
+                    endpoint.params(|params| params.req_typed("tx_id", json_dsl::u64()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Authenticated));
+                            let tx_id = try!(get_parameter("tx_id", params, &JsonValue::as_u64));
+                            client.app
+                                .get_data_base_manager()
+                                .tx_kill(&tx_id)
+                                .map(|_| release_transaction_owner(client, tx_id))
+                                .map(|_| JsonValue::String(String::from("killed")))
+                                .map_err(ClientError::from)
+                        })
+                    })
+                });
+
+                meta_ns.get("table/:name/schema", |endpoint| {
+                    endpoint.desc("JSON Schema of key and value of table");
+                    endpoint.params(|params| params.req_typed("name", json_dsl::string()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Read, "name")));
+                            let name = try!(get_parameter("name", params, &JsonValue::as_str));
+                            client.app
+                                .get_data_base_manager()
+                                .get_table_schema(&String::from(name))
+                                .ok_or(ClientError::from(PersistenceError::TableNotFound(String::from(name))))
+                        })
+                    })
+                });
+
+                meta_ns.get("table/:name", |endpoint| {
+                    endpoint.params(|params| params.req_typed("name", json_dsl::string()));
+
+                    endpoint.handle(|client, params| {
+                        handle_response(client, params, |client| {
+                            try!(authorize(client, params, Access::Table(Permission::Read, "name")));
+                            let name = try!(get_parameter("name", params, &JsonValue::as_str));
+                            info!("Table with name {}", name);
+                            let table_desc = client.app
+                                .get_data_base_manager()
+                                .get_table_json(&String::from(name));
+                            match table_desc {
+                                Some(table_desc) => Ok(table_desc),
+                                None => Err(ClientError::from(PersistenceError::TableNotFound(String::from(name)))),
+                            }
+                        })
+                    })
+                });
+            });
+        }));
+    });
+
+    let mut app = Application::new(api);
+    app.ext.insert::<app_extension::AppDataBase>(data_base_manager);
+    let host = settings.host.clone();
+    app.ext.insert::<app_extension::AppSettings>(settings);
+    if let Some(security) = security {
+        app.ext.insert::<app_extension::AppSecurity>(security);
+    }
+
+    swagger::enable(&mut app,
+                    swagger::Spec {
+                        info: swagger::Info {
+                            title: String::from(openapi::API_TITLE),
+                            description: Some(error::errors_description()),
+                            contact: Some(swagger::Contact {
+                                name: "SKN".to_string(),
+                                url: Some("http://panferov.me".to_string()),
+                                ..std::default::Default::default()
+                            }),
+                            license: Some(swagger::License {
+                                name: "MIT".to_string(),
+                                url: "http://opensource.org/licenses/MIT".to_string(),
+                            }),
+                            ..std::default::Default::default()
+                        },
+                        host: Some(host),
+                        ..std::default::Default::default()
+                    });
+
+    app
+}
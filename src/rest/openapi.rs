@@ -0,0 +1,110 @@
+// OpenAPI (swagger 2.0) document with typed paths and models of tables. Document is built
+// from schemas of current tables on each request, so it follows add_table
+use std::collections::BTreeMap;
+
+use data_base::json::JsonValue;
+use rest::error::errors_description;
+
+pub const API_TITLE: &'static str = "nimdg REST API";
+pub const API_BASE_PATH: &'static str = "/api/v1";
+
+fn object(properties: Vec<(&str, JsonValue)>) -> JsonValue {
+    JsonValue::Object(properties.into_iter().map(|(name, value)| (String::from(name), value)).collect())
+}
+
+fn string(value: &str) -> JsonValue {
+    JsonValue::String(String::from(value))
+}
+
+fn reference(model: &str) -> JsonValue {
+    object(vec![("$ref", string(&format!("#/definitions/{}", model)))])
+}
+
+fn path_parameter(name: &str, type_name: &str, description: &str) -> JsonValue {
+    object(vec![("name", string(name)),
+                ("in", string("path")),
+                ("required", JsonValue::Bool(true)),
+                ("type", string(type_name)),
+                ("description", string(description))])
+}
+
+fn operation(table_name: &str, summary: &str, parameters: Vec<JsonValue>, result: JsonValue) -> JsonValue {
+    let responses = object(vec![("200", object(vec![("description", string("Done")), ("schema", result)])),
+                                ("default", object(vec![("description", string("Error")), ("schema", reference("Error"))]))]);
+    object(vec![("tags", JsonValue::Array(vec![string(table_name)])),
+                ("summary", string(summary)),
+                ("parameters", JsonValue::Array(parameters)),
+                ("responses", responses)])
+}
+
+fn error_model() -> JsonValue {
+    object(vec![("type", string("object")),
+                ("properties",
+                 object(vec![("code", object(vec![("type", string("string"))])),
+                             ("message", object(vec![("type", string("string"))])),
+                             ("details", object(vec![])),
+                             ("tx_id", object(vec![("type", string("integer"))])),
+                             ("table", object(vec![("type", string("string"))]))])),
+                ("required", JsonValue::Array(vec![string("code"), string("message")]))])
+}
+
+// Models of table Client are ClientKey and ClientValue
+fn add_table(name: &str, schema: &JsonValue, paths: &mut BTreeMap<String, JsonValue>, definitions: &mut BTreeMap<String, JsonValue>) {
+    let key_model = format!("{}Key", name);
+    let value_model = format!("{}Value", name);
+    for &(part, ref model) in &[("key", &key_model), ("value", &value_model)] {
+        let part_schema = schema.find("properties").and_then(|properties| properties.find(part)).cloned().unwrap_or(object(vec![]));
+        definitions.insert((*model).clone(), part_schema);
+    }
+
+    let tx_id = path_parameter("tx_id", "integer", "id of transaction, 0 - own transaction of operation");
+    let key = path_parameter("key", "string", &format!("json of {}", key_model));
+    let body = object(vec![("type", string("object")),
+                           ("properties",
+                            object(vec![("tx_id", object(vec![("type", string("integer"))])),
+                                        ("data",
+                                         object(vec![("type", string("object")),
+                                                     ("properties",
+                                                      object(vec![("key", reference(&key_model)), ("value", reference(&value_model))])),
+                                                     ("required", JsonValue::Array(vec![string("key"), string("value")]))]))])),
+                           ("required", JsonValue::Array(vec![string("data")]))]);
+    let body_parameter = object(vec![("name", string("body")), ("in", string("body")), ("required", JsonValue::Bool(true)), ("schema", body)]);
+    let done = object(vec![("type", string("string"))]);
+    // Entity of list is array [key, value]
+    let entities = object(vec![("type", string("array")),
+                               ("items", object(vec![("type", string("array")), ("items", object(vec![]))]))]);
+
+    paths.insert(format!("/get/{}/{{tx_id}}/{{key}}", name),
+                 object(vec![("get", operation(name, "Get value by key", vec![tx_id.clone(), key.clone()], reference(&value_model)))]));
+    paths.insert(format!("/put/{}", name),
+                 object(vec![("post", operation(name, "Put value by key", vec![body_parameter], done.clone()))]));
+    paths.insert(format!("/delete/{}/{{tx_id}}/{{key}}", name),
+                 object(vec![("delete", operation(name, "Delete value by key", vec![tx_id.clone(), key], done))]));
+    paths.insert(format!("/get/{}/{{tx_id}}/{{start}}/{{count}}", name),
+                 object(vec![("get",
+                              operation(name,
+                                        "List entities as pairs [key, value]",
+                                        vec![tx_id,
+                                             path_parameter("start", "integer", "index of first entity"),
+                                             path_parameter("count", "integer", "max count of entities")],
+                                        entities))]));
+}
+
+/** Document with paths of tables, schemas of tables are got by DataBaseManager::get_tables_schemas */
+pub fn openapi_spec(tables: &BTreeMap<String, JsonValue>, host: &str) -> JsonValue {
+    let mut paths = BTreeMap::new();
+    let mut definitions = BTreeMap::new();
+    definitions.insert(String::from("Error"), error_model());
+    for (name, schema) in tables {
+        add_table(name, schema, &mut paths, &mut definitions);
+    }
+    object(vec![("swagger", string("2.0")),
+                ("info",
+                 object(vec![("title", string(API_TITLE)), ("version", string("v1")), ("description", string(&errors_description()))])),
+                ("host", string(host)),
+                ("basePath", string(API_BASE_PATH)),
+                ("consumes", JsonValue::Array(vec![string("application/json")])),
+                ("produces", JsonValue::Array(vec![string("application/json")])),
+                ("paths", JsonValue::Object(paths)),
+                ("definitions", JsonValue::Object(definitions))])
+}
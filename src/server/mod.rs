@@ -0,0 +1,619 @@
+// Server of data base with all front-ends. Configuration is read from TOML file,
+// options of command line override it. Compiled with feature "rest", TLS of listeners needs feature "tls"
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use iron::{Iron, Protocol, Timeouts};
+use log4rs;
+use toml;
+
+use data_base::DataBaseManager;
+use data_base::meta::{EntityDescriptionView, TableDescriptionView};
+use data_base::transaction::{LockMode, DEFAULT_LOCK_WAIT_SECS};
+use memcached::{MemcachedServer, DEFAULT_MEMCACHED_TABLE_NAME};
+use net::{ConnectionAcceptor, TlsConfig};
+use protocol::server::BinaryServer;
+use resp::server::{RespServer, DEFAULT_RESP_TABLE_NAME};
+use rest::{create_application_with_security, RestSettings, DEFAULT_CHANGES_TIMEOUT_SECS, MAX_CHANGES_TIMEOUT_SECS};
+use rest::auth::{AuthConfig, CertificateConfig, Permission, Role, Security, TokenConfig, UserConfig};
+#[cfg(feature = "tls")]
+use tls::TlsAcceptor;
+
+pub const DEFAULT_CONFIG_PATH: &'static str = "config/nimdg.toml";
+const DEFAULT_ADDRESS: &'static str = "localhost:4300";
+const DEFAULT_THREADS: usize = 8;
+const DEFAULT_LOG_CONFIG: &'static str = "config/log4rs.yml";
+const DEFAULT_DATA_DIR: &'static str = "data";
+const PREPARED_LOG_DIR: &'static str = "prepared"; // in data directory
+const DEFAULT_BINARY_PROTOCOL_ADDRESS: &'static str = "localhost:4301";
+const DEFAULT_MEMCACHED_ADDRESS: &'static str = "localhost:11211";
+
+pub const USAGE: &'static str = "Usage: nimdg [--config file] [--address host:port] [--threads count] [--log-config file] \
+                                 [--data-dir dir] [--lock-mode optimistic|pessimistic|read_only] [--binary-address host:port] \
+                                 [--memcached-address host:port] [--memcached-table name] [--resp-address host:port] [--resp-table name]\n\
+                                 nimdg --hash-password - print bcrypt hash of password, that is read from standard input";
+
+// Timeouts of http connections, None - default of iron, 0 - without timeout
+#[derive(Debug, Clone)]
+pub struct TimeoutsConfig {
+    pub read_secs: Option<u64>,
+    pub write_secs: Option<u64>,
+    pub keep_alive_secs: Option<u64>,
+    pub changes_secs: u64, // long poll of changes without timeout
+    pub max_changes_secs: u64,
+    pub lock_wait_secs: u64, // wait of pessimistic transaction for lock, 0 - without timeout
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub address: String, // address of REST api
+    pub threads: usize, // worker threads of REST api
+    pub log_config: String, // log4rs configuration
+    pub data_dir: String, // persistent data, now log of prepared transactions
+    pub lock_mode: LockMode, // lock mode of transaction, started without mode
+    pub timeouts: TimeoutsConfig,
+    pub binary_address: String,
+    pub memcached_address: String,
+    pub memcached_table: String,
+    pub resp_address: Option<String>, // None - RESP listener is not started
+    pub resp_table: String,
+    pub tables: Vec<TableDescriptionView>, // tables, created at start
+    pub auth: Option<AuthConfig>, // None - REST api and listeners are open
+    pub tls: Option<TlsConfig>, // None - all listeners are plaintext
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> TimeoutsConfig {
+        TimeoutsConfig {
+            read_secs: None,
+            write_secs: None,
+            keep_alive_secs: None,
+            changes_secs: DEFAULT_CHANGES_TIMEOUT_SECS,
+            max_changes_secs: MAX_CHANGES_TIMEOUT_SECS,
+            lock_wait_secs: DEFAULT_LOCK_WAIT_SECS,
+        }
+    }
+}
+
+impl TimeoutsConfig {
+    fn apply_toml(&mut self, table: &toml::Table) -> Result<(), String> {
+        for (name, value) in table.iter() {
+            let secs = try!(toml_u64(&format!("timeouts.{}", name), value));
+            match name.as_str() {
+                "read_secs" => self.read_secs = Some(secs),
+                "write_secs" => self.write_secs = Some(secs),
+                "keep_alive_secs" => self.keep_alive_secs = Some(secs),
+                "changes_secs" => self.changes_secs = secs,
+                "max_changes_secs" => self.max_changes_secs = secs,
+                "lock_wait_secs" => self.lock_wait_secs = secs,
+                _ => return Err(format!("Unknown option timeouts.{}", name)),
+            }
+        }
+        Ok(())
+    }
+
+    fn iron_timeouts(&self) -> Timeouts {
+        let mut timeouts = Timeouts::default();
+        if let Some(secs) = self.read_secs {
+            timeouts.read = duration(secs);
+        }
+        if let Some(secs) = self.write_secs {
+            timeouts.write = duration(secs);
+        }
+        if let Some(secs) = self.keep_alive_secs {
+            timeouts.keep_alive = duration(secs);
+        }
+        timeouts
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            address: String::from(DEFAULT_ADDRESS),
+            threads: DEFAULT_THREADS,
+            log_config: String::from(DEFAULT_LOG_CONFIG),
+            data_dir: String::from(DEFAULT_DATA_DIR),
+            lock_mode: LockMode::Pessimistic,
+            timeouts: TimeoutsConfig::default(),
+            binary_address: String::from(DEFAULT_BINARY_PROTOCOL_ADDRESS),
+            memcached_address: String::from(DEFAULT_MEMCACHED_ADDRESS),
+            memcached_table: String::from(DEFAULT_MEMCACHED_TABLE_NAME),
+            resp_address: None,
+            resp_table: String::from(DEFAULT_RESP_TABLE_NAME),
+            tables: Vec::new(),
+            auth: None,
+            tls: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /** Configuration by TOML text, options, that are not set, have default values */
+    pub fn from_toml(text: &str) -> Result<ServerConfig, String> {
+        let mut parser = toml::Parser::new(text);
+        let table = match parser.parse() {
+            Some(table) => table,
+            None => {
+                let errors: Vec<String> = parser.errors
+                    .iter()
+                    .map(|error| {
+                        let (line, column) = parser.to_linecol(error.lo);
+                        format!("{}:{}: {}", line + 1, column + 1, error.desc)
+                    })
+                    .collect();
+                return Err(errors.join("\n"));
+            }
+        };
+
+        let mut config = ServerConfig::default();
+        for (name, value) in table.iter() {
+            match name.as_str() {
+                "address" => config.address = try!(toml_string(name, value)),
+                "threads" => config.threads = try!(toml_u64(name, value)) as usize,
+                "log_config" => config.log_config = try!(toml_string(name, value)),
+                "data_dir" => config.data_dir = try!(toml_string(name, value)),
+                "lock_mode" => config.lock_mode = try!(LockMode::from_str(&try!(toml_string(name, value)))),
+                "timeouts" => try!(config.timeouts.apply_toml(try!(toml_table(name, value)))),
+                "protocols" => try!(config.apply_protocols_toml(try!(toml_table(name, value)))),
+                "auth" => config.auth = Some(try!(auth_from_toml(try!(toml_table(name, value))))),
+                "tls" => config.tls = Some(try!(tls_from_toml(try!(toml_table(name, value))))),
+                "tables" => {
+                    let tables = try!(value.as_slice().ok_or(String::from("tables must be array of tables")));
+                    for table in tables {
+                        let table = try!(toml_table(name, table));
+                        config.tables.push(try!(table_from_toml(table)));
+                    }
+                }
+                _ => return Err(format!("Unknown option {}", name)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<ServerConfig, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|err| format!("Cannot read config {}: {}", path, err)));
+        ServerConfig::from_toml(&text).map_err(|err| format!("Wrong config {}: {}", path, err))
+    }
+
+    /** Configuration by arguments of command line without name of program.
+     * File is set by --config, else DEFAULT_CONFIG_PATH is read, if it exists.
+     * Other options override values of file */
+    pub fn from_args(args: &[String]) -> Result<ServerConfig, String> {
+        let mut config_path = None;
+        let mut options = Vec::new();
+        let mut args = args.iter();
+        while let Some(option) = args.next() {
+            let value = try!(args.next().ok_or(format!("Value of option {} not found", option)));
+            if option == "--config" {
+                config_path = Some(value.clone());
+            } else {
+                options.push((option, value));
+            }
+        }
+
+        let mut config = match config_path {
+            Some(path) => try!(ServerConfig::from_file(&path)),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => try!(ServerConfig::from_file(DEFAULT_CONFIG_PATH)),
+            None => ServerConfig::default(),
+        };
+        for (option, value) in options {
+            try!(config.set_option(option, value));
+        }
+        Ok(config)
+    }
+
+    pub fn set_option(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match option {
+            "--address" => self.address = String::from(value),
+            "--threads" => self.threads = try!(value.parse().map_err(|_| format!("Threads {} is not a number", value))),
+            "--log-config" => self.log_config = String::from(value),
+            "--data-dir" => self.data_dir = String::from(value),
+            "--lock-mode" => self.lock_mode = try!(LockMode::from_str(value)),
+            "--binary-address" => self.binary_address = String::from(value),
+            "--memcached-address" => self.memcached_address = String::from(value),
+            "--memcached-table" => self.memcached_table = String::from(value),
+            "--resp-address" => self.resp_address = Some(String::from(value)),
+            "--resp-table" => self.resp_table = String::from(value),
+            _ => return Err(format!("Unknown option {}\n{}", option, USAGE)),
+        }
+        Ok(())
+    }
+
+    fn apply_protocols_toml(&mut self, table: &toml::Table) -> Result<(), String> {
+        for (name, value) in table.iter() {
+            let string = try!(toml_string(&format!("protocols.{}", name), value));
+            match name.as_str() {
+                "binary_address" => self.binary_address = string,
+                "memcached_address" => self.memcached_address = string,
+                "memcached_table" => self.memcached_table = string,
+                "resp_address" => self.resp_address = Some(string),
+                "resp_table" => self.resp_table = string,
+                _ => return Err(format!("Unknown option protocols.{}", name)),
+            }
+        }
+        Ok(())
+    }
+
+    /** Check configuration before start of server
+     * return - all found errors */
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut addresses = vec![("address", &self.address), ("binary_address", &self.binary_address), ("memcached_address", &self.memcached_address)];
+        if let Some(ref resp_address) = self.resp_address {
+            addresses.push(("resp_address", resp_address));
+        }
+        for (name, address) in addresses {
+            if let Err(err) = address.as_str().to_socket_addrs() {
+                errors.push(format!("Wrong {} {}: {}", name, address, err));
+            }
+        }
+        if self.threads == 0 {
+            errors.push(String::from("Threads must be greater than 0"));
+        }
+        if self.memcached_table.is_empty() {
+            errors.push(String::from("Table of memcached must not be empty"));
+        }
+        if self.resp_table.is_empty() {
+            errors.push(String::from("Table of RESP must not be empty"));
+        }
+        if !Path::new(&self.log_config).is_file() {
+            errors.push(format!("Log config {} not found", self.log_config));
+        }
+        let data_dir = Path::new(&self.data_dir);
+        if data_dir.exists() && !data_dir.is_dir() {
+            errors.push(format!("Data dir {} is not a directory", self.data_dir));
+        }
+        if self.timeouts.changes_secs > self.timeouts.max_changes_secs {
+            errors.push(format!("Timeout of changes {} is greater than max {}",
+                                self.timeouts.changes_secs,
+                                self.timeouts.max_changes_secs));
+        }
+
+        if let Some(ref auth) = self.auth {
+            if let Err(err) = auth.validate() {
+                errors.push(err);
+            }
+        }
+
+        if let Some(ref tls) = self.tls {
+            if !cfg!(feature = "tls") {
+                errors.push(String::from("TLS is set, but server is built without feature tls"));
+            }
+            let mut files = vec![("certificate", &tls.certificate), ("key", &tls.key)];
+            if let Some(ref client_ca) = tls.client_ca {
+                files.push(("client_ca", client_ca));
+            }
+            for (name, path) in files {
+                if !Path::new(path).is_file() {
+                    errors.push(format!("TLS {} {} not found", name, path));
+                }
+            }
+        }
+
+        // Tables are checked by data base, that is not used further
+        match DataBaseManager::new() {
+            Ok(data_base_manager) => {
+                for table in self.tables.iter() {
+                    if let Err(err) = data_base_manager.add_table(table.clone()) {
+                        errors.push(format!("Wrong table {}: {}", table.name, err));
+                    }
+                }
+            }
+            Err(err) => errors.push(err),
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    pub fn rest_settings(&self) -> RestSettings {
+        RestSettings {
+            host: self.address.clone(),
+            lock_mode: self.lock_mode.clone(),
+            changes_timeout_secs: self.timeouts.changes_secs,
+            max_changes_timeout_secs: self.timeouts.max_changes_secs,
+            auth: self.auth.clone(),
+        }
+    }
+
+    /** Security of auth, that is shared by REST api, listeners and TLS acceptor,
+     * so transaction started by one front-end belongs to its principal in others */
+    pub fn security(&self) -> Option<Arc<Security>> {
+        self.auth.as_ref().map(|auth| Arc::new(Security::from_config(auth)))
+    }
+
+    /** TLS handshake of binary, memcached and RESP listeners. Client certificates are mapped
+     * to principals of security, if both client CA and auth are set, and listeners check their permissions */
+    #[cfg(feature = "tls")]
+    pub fn connection_acceptor(&self, security: Option<Arc<Security>>) -> Result<Option<Arc<ConnectionAcceptor>>, String> {
+        match self.tls {
+            Some(ref tls) => {
+                let acceptor: Arc<ConnectionAcceptor> = Arc::new(try!(TlsAcceptor::new(tls, security)));
+                Ok(Some(acceptor))
+            }
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub fn connection_acceptor(&self, _security: Option<Arc<Security>>) -> Result<Option<Arc<ConnectionAcceptor>>, String> {
+        match self.tls {
+            Some(_) => Err(String::from("TLS is set, but server is built without feature tls")),
+            None => Ok(None),
+        }
+    }
+
+    /** HTTPS with certificate and key of TLS. Client certificates are not requested: Protocol::Https of iron 0.4
+     * has only certificate and key, so CA of clients cannot be set, and request of iron has no TLS session,
+     * so common name cannot be passed to authorize as Credentials::Certificate */
+    #[cfg(feature = "tls")]
+    fn iron_protocol(&self) -> Protocol {
+        match self.tls {
+            Some(ref tls) => {
+                Protocol::Https {
+                    certificate: Path::new(&tls.certificate).to_path_buf(),
+                    key: Path::new(&tls.key).to_path_buf(),
+                }
+            }
+            None => Protocol::Http,
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn iron_protocol(&self) -> Protocol {
+        Protocol::Http
+    }
+}
+
+fn duration(secs: u64) -> Option<Duration> {
+    if secs == 0 {
+        None
+    } else {
+        Some(Duration::from_secs(secs))
+    }
+}
+
+fn toml_string(name: &str, value: &toml::Value) -> Result<String, String> {
+    value.as_str().map(String::from).ok_or(format!("{} must be string, found {}", name, value.type_str()))
+}
+
+fn toml_u64(name: &str, value: &toml::Value) -> Result<u64, String> {
+    match value.as_integer() {
+        Some(integer) if integer >= 0 => Ok(integer as u64),
+        _ => Err(format!("{} must be not negative integer, found {}", name, value)),
+    }
+}
+
+fn toml_strings(name: &str, value: &toml::Value) -> Result<Vec<String>, String> {
+    let values = try!(value.as_slice().ok_or(format!("{} must be array of strings, found {}", name, value.type_str())));
+    values.iter().map(|value| toml_string(name, value)).collect()
+}
+
+fn toml_table<'a>(name: &str, value: &'a toml::Value) -> Result<&'a toml::Table, String> {
+    value.as_table().ok_or(format!("{} must be table, found {}", name, value.type_str()))
+}
+
+/** Table is set as
+ * [[tables]]
+ * name = "Client"
+ * lock_mode = "optimistic" (optional)
+ * key = { id = "u64" }
+ * value = { full_name = "string", age = "u64" } */
+fn table_from_toml(table: &toml::Table) -> Result<TableDescriptionView, String> {
+    let name = try!(table.get("name").ok_or(String::from("Table without name")).and_then(|name| toml_string("tables.name", name)));
+    let mut key = None;
+    let mut value = None;
+    let mut lock_mode = None;
+    for (option, option_value) in table.iter() {
+        match option.as_str() {
+            "name" => (),
+            "key" => key = Some(try!(fields_from_toml(&name, option, option_value))),
+            "value" => value = Some(try!(fields_from_toml(&name, option, option_value))),
+            "lock_mode" => {
+                let mode = try!(LockMode::from_str(&try!(toml_string("tables.lock_mode", option_value))));
+                if mode == LockMode::ReadOnly {
+                    // Implicit transactions of table must be able to write
+                    return Err(format!("Lock mode of table {} cannot be read_only", name));
+                }
+                lock_mode = Some(mode);
+            }
+            _ => return Err(format!("Unknown option {} of table {}", option, name)),
+        }
+    }
+    Ok(TableDescriptionView {
+        key: try!(key.ok_or(format!("Key of table {} not found", name))),
+        value: try!(value.ok_or(format!("Value of table {} not found", name))),
+        name: name,
+        lock_mode: lock_mode,
+    })
+}
+
+fn fields_from_toml(table_name: &str, entity: &str, value: &toml::Value) -> Result<EntityDescriptionView, String> {
+    let fields = try!(toml_table(&format!("{} of table {}", entity, table_name), value));
+    if fields.is_empty() {
+        return Err(format!("{} of table {} has no fields", entity, table_name));
+    }
+    let mut types = BTreeMap::new();
+    for (field, type_name) in fields.iter() {
+        types.insert(field.clone(),
+                     try!(toml_string(&format!("Type of {}.{} of table {}", entity, field, table_name), type_name)));
+    }
+    Ok(EntityDescriptionView { fields: types })
+}
+
+/** Authentication is set as
+ * [[auth.tokens]]
+ * name = "loader"
+ * token = "..."
+ * roles = ["writer"]
+ * [[auth.users]]
+ * name = "admin"
+ * password_hash = "$2y$..." (bcrypt)
+ * roles = ["admin"]
+ * [[auth.certificates]]
+ * common_name = "loader.cluster" (of client certificate of TLS listeners)
+ * name = "loader"
+ * roles = ["writer"]
+ * [auth.roles.writer]
+ * Client = ["read", "write"] (permissions for all tables are set for "*") */
+fn auth_from_toml(table: &toml::Table) -> Result<AuthConfig, String> {
+    let mut config = AuthConfig::default();
+    for (name, value) in table.iter() {
+        match name.as_str() {
+            "tokens" => {
+                for token in try!(value.as_slice().ok_or(String::from("auth.tokens must be array of tables"))) {
+                    let token = try!(toml_table("auth.tokens", token));
+                    config.tokens.push(TokenConfig {
+                        name: try!(toml_option(token, "auth.tokens", "name").and_then(|name| toml_string("auth.tokens.name", name))),
+                        token: try!(toml_option(token, "auth.tokens", "token").and_then(|token| toml_string("auth.tokens.token", token))),
+                        roles: try!(toml_option(token, "auth.tokens", "roles").and_then(|roles| toml_strings("auth.tokens.roles", roles))),
+                    });
+                }
+            }
+            "users" => {
+                for user in try!(value.as_slice().ok_or(String::from("auth.users must be array of tables"))) {
+                    let user = try!(toml_table("auth.users", user));
+                    config.users.push(UserConfig {
+                        name: try!(toml_option(user, "auth.users", "name").and_then(|name| toml_string("auth.users.name", name))),
+                        password_hash: try!(toml_option(user, "auth.users", "password_hash")
+                            .and_then(|hash| toml_string("auth.users.password_hash", hash))),
+                        roles: try!(toml_option(user, "auth.users", "roles").and_then(|roles| toml_strings("auth.users.roles", roles))),
+                    });
+                }
+            }
+            "certificates" => {
+                for certificate in try!(value.as_slice().ok_or(String::from("auth.certificates must be array of tables"))) {
+                    let certificate = try!(toml_table("auth.certificates", certificate));
+                    config.certificates.push(CertificateConfig {
+                        common_name: try!(toml_option(certificate, "auth.certificates", "common_name")
+                            .and_then(|name| toml_string("auth.certificates.common_name", name))),
+                        name: try!(toml_option(certificate, "auth.certificates", "name")
+                            .and_then(|name| toml_string("auth.certificates.name", name))),
+                        roles: try!(toml_option(certificate, "auth.certificates", "roles")
+                            .and_then(|roles| toml_strings("auth.certificates.roles", roles))),
+                    });
+                }
+            }
+            "roles" => {
+                for (role_name, tables) in try!(toml_table("auth.roles", value)).iter() {
+                    let mut role = Role::new();
+                    for (table_name, permissions) in try!(toml_table(&format!("auth.roles.{}", role_name), tables)).iter() {
+                        let permissions = try!(toml_strings(&format!("auth.roles.{}.{}", role_name, table_name), permissions));
+                        role.insert(table_name.clone(),
+                                    try!(permissions.iter()
+                                        .map(|permission| Permission::from_str(permission))
+                                        .collect::<Result<Vec<Permission>, String>>()));
+                    }
+                    config.roles.insert(role_name.clone(), role);
+                }
+            }
+            _ => return Err(format!("Unknown option auth.{}", name)),
+        }
+    }
+    Ok(config)
+}
+
+/** TLS is set as
+ * [tls]
+ * certificate = "tls/server.pem" (chain in PEM)
+ * key = "tls/server.key"
+ * client_ca = "tls/ca.pem" (optional, clients of binary, memcached and RESP listeners must have certificate) */
+fn tls_from_toml(table: &toml::Table) -> Result<TlsConfig, String> {
+    let mut certificate = None;
+    let mut key = None;
+    let mut client_ca = None;
+    for (name, value) in table.iter() {
+        let path = try!(toml_string(&format!("tls.{}", name), value));
+        match name.as_str() {
+            "certificate" => certificate = Some(path),
+            "key" => key = Some(path),
+            "client_ca" => client_ca = Some(path),
+            _ => return Err(format!("Unknown option tls.{}", name)),
+        }
+    }
+    Ok(TlsConfig {
+        certificate: try!(certificate.ok_or(String::from("Option certificate of tls not found"))),
+        key: try!(key.ok_or(String::from("Option key of tls not found"))),
+        client_ca: client_ca,
+    })
+}
+
+fn toml_option<'a>(table: &'a toml::Table, table_name: &str, option: &str) -> Result<&'a toml::Value, String> {
+    table.get(option).ok_or(format!("Option {} of {} not found", option, table_name))
+}
+
+/** Start all front-ends and serve REST api in current thread.
+ * Configuration is validated before anything is started */
+pub fn run(config: ServerConfig) -> Result<(), String> {
+    try!(config.validate());
+    try!(log4rs::init_file(&config.log_config, Default::default())
+        .map_err(|err| format!("Cannot init logging by {}: {}", config.log_config, err)));
+    info!("Starting server with {:?}", config);
+
+    // Data base is shared by REST api and protocol servers
+    let prepared_log_dir = Path::new(&config.data_dir).join(PREPARED_LOG_DIR);
+    let data_base_manager = Arc::new(try!(DataBaseManager::with_prepared_log_dir(&prepared_log_dir)));
+    data_base_manager.set_lock_wait(duration(config.timeouts.lock_wait_secs));
+    for table in config.tables.iter() {
+        try!(data_base_manager.add_table(table.clone()));
+    }
+
+    let security = config.security();
+    let acceptor = try!(config.connection_acceptor(security.clone()));
+    if acceptor.is_none() {
+        warn!("TLS is not set, all listeners are plaintext");
+    }
+    if config.auth.as_ref().map_or(false, |auth| !auth.certificates.is_empty()) {
+        warn!("Certificates of auth authenticate only clients of binary, memcached and RESP listeners, REST api needs token or user");
+    }
+    let mut binary_server = try!(BinaryServer::bind(config.binary_address.as_str(), data_base_manager.clone())
+        .map_err(|err| format!("Cannot bind binary protocol listener: {}", err)));
+    let mut memcached_server = try!(MemcachedServer::bind(config.memcached_address.as_str(), data_base_manager.clone(), &config.memcached_table));
+    if let Some(ref acceptor) = acceptor {
+        binary_server = binary_server.with_acceptor(acceptor.clone());
+        memcached_server = memcached_server.with_acceptor(acceptor.clone());
+    }
+    // Listeners have the same principals, permissions and owners of transactions as REST api
+    if let Some(ref security) = security {
+        binary_server = binary_server.with_access_control(security.clone());
+        memcached_server = memcached_server.with_access_control(security.clone());
+    }
+    binary_server.spawn();
+    memcached_server.spawn();
+    if let Some(ref resp_address) = config.resp_address {
+        let mut resp_server = try!(RespServer::bind(resp_address.as_str(), data_base_manager.clone(), &config.resp_table));
+        if let Some(ref acceptor) = acceptor {
+            resp_server = resp_server.with_acceptor(acceptor.clone());
+        }
+        if let Some(ref security) = security {
+            resp_server = resp_server.with_access_control(security.clone());
+        }
+        resp_server.spawn();
+    }
+
+    let app = create_application_with_security(data_base_manager, config.rest_settings(), security);
+    try!(Iron::new(app)
+        .listen_with(config.address.as_str(),
+                     config.threads,
+                     config.iron_protocol(),
+                     Some(config.timeouts.iron_timeouts()))
+        .map_err(|err| format!("Cannot listen on {}: {}", config.address, err)));
+    Ok(())
+}
+
+// Server with default configuration
+pub fn mount_api() {
+    run(ServerConfig::default()).unwrap();
+}
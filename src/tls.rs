@@ -0,0 +1,108 @@
+// TLS of binary, memcached and RESP listeners and of binary client, compiled with feature "tls".
+// REST api is served over HTTPS by iron with the same certificate and key, but without client certificates:
+// iron 0.4 creates TLS context only by certificate and key and doesn't give TLS session to handlers,
+// so REST api authenticates only by header Authorization
+use std;
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+
+use openssl::nid::Nid;
+use openssl::ssl::{SslContext, SslMethod, SslStream, SSL_VERIFY_FAIL_IF_NO_PEER_CERT, SSL_VERIFY_PEER};
+use openssl::x509::{X509, X509FileType};
+
+use net::{peer_name, Connection, ConnectionAcceptor, Principal, TlsConfig};
+use rest::auth::{Credentials, Security};
+
+/** TLS handshake of accepted streams. If client CA is set, client must have certificate signed by it.
+ * If security is set, common name of client certificate must be known principal, that is given to listener */
+pub struct TlsAcceptor {
+    context: SslContext,
+    verify_clients: bool,
+    security: Option<Arc<Security>>,
+}
+
+/** TLS of client connections, certificate of server must be signed by CA
+ * and have common name of server domain */
+pub struct TlsConnector {
+    context: SslContext,
+}
+
+fn read_error<E: std::fmt::Display>(what: &str, path: &str, err: E) -> String {
+    format!("Cannot read {} {}: {}", what, path, err)
+}
+
+// Certificate chain and key of own side
+fn set_certificate(context: &mut SslContext, certificate: &str, key: &str) -> Result<(), String> {
+    try!(context.set_certificate_chain_file(Path::new(certificate), X509FileType::PEM)
+        .map_err(|err| read_error("certificate", certificate, err)));
+    try!(context.set_private_key_file(Path::new(key), X509FileType::PEM).map_err(|err| read_error("key", key, err)));
+    context.check_private_key().map_err(|err| format!("Key {} doesn't match certificate {}: {}", key, certificate, err))
+}
+
+pub fn common_name(certificate: &X509) -> Option<String> {
+    certificate.subject_name().text_by_nid(Nid::CN).map(|name| String::from(&name[..]))
+}
+
+impl TlsAcceptor {
+    pub fn new(config: &TlsConfig, security: Option<Arc<Security>>) -> Result<TlsAcceptor, String> {
+        let mut context = try!(SslContext::new(SslMethod::Sslv23).map_err(|err| format!("Cannot create TLS context: {}", err)));
+        try!(set_certificate(&mut context, &config.certificate, &config.key));
+        if let Some(ref client_ca) = config.client_ca {
+            try!(context.set_CA_file(Path::new(client_ca)).map_err(|err| read_error("client CA", client_ca, err)));
+            context.set_verify(SSL_VERIFY_PEER | SSL_VERIFY_FAIL_IF_NO_PEER_CERT, None);
+        }
+        Ok(TlsAcceptor {
+            context: context,
+            verify_clients: config.client_ca.is_some(),
+            security: security,
+        })
+    }
+}
+
+impl ConnectionAcceptor for TlsAcceptor {
+    fn accept(&self, stream: TcpStream) -> Result<(Box<Connection>, Option<Principal>), String> {
+        let peer = peer_name(&stream);
+        let stream = try!(SslStream::accept(&self.context, stream).map_err(|err| format!("TLS handshake failed: {}", err)));
+        let mut principal = None;
+        if self.verify_clients {
+            let name = try!(stream.ssl()
+                .peer_certificate()
+                .and_then(|certificate| common_name(&certificate))
+                .ok_or(String::from("Client certificate without common name")));
+            if let Some(ref security) = self.security {
+                let authenticated = try!(security.authenticate(&Credentials::Certificate(name.clone()))
+                    .ok_or(format!("Certificate {} is not mapped to principal", name)));
+                info!("Connection from {} is authenticated as {}", peer, authenticated.name);
+                principal = Some(authenticated);
+            } else {
+                info!("Connection from {} with certificate {}", peer, name);
+            }
+        }
+        Ok((Box::new(stream), principal))
+    }
+}
+
+impl TlsConnector {
+    /** ca - CA of server certificates, certificate and key - client certificate for server with client CA */
+    pub fn new(ca: &str, client_certificate: Option<(&str, &str)>) -> Result<TlsConnector, String> {
+        let mut context = try!(SslContext::new(SslMethod::Sslv23).map_err(|err| format!("Cannot create TLS context: {}", err)));
+        try!(context.set_CA_file(Path::new(ca)).map_err(|err| read_error("CA", ca, err)));
+        context.set_verify(SSL_VERIFY_PEER, None);
+        if let Some((certificate, key)) = client_certificate {
+            try!(set_certificate(&mut context, certificate, key));
+        }
+        Ok(TlsConnector { context: context })
+    }
+
+    pub fn connect(&self, address: &str, domain: &str) -> Result<Box<Connection>, String> {
+        let stream = try!(TcpStream::connect(address).map_err(|err| format!("Cannot connect to {}: {}", address, err)));
+        let stream = try!(SslStream::connect(&self.context, stream).map_err(|err| format!("TLS handshake with {} failed: {}", address, err)));
+        // Chain is verified by handshake, name of server is checked here
+        let name = stream.ssl().peer_certificate().and_then(|certificate| common_name(&certificate));
+        if name.as_ref().map(|name| name.as_str()) != Some(domain) {
+            return Err(format!("Certificate of {} is issued for {:?}, expected {}", address, name, domain));
+        }
+        Ok(Box::new(stream))
+    }
+}
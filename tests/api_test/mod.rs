@@ -0,0 +1,85 @@
+extern crate log4rs;
+
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+use nimdg::api::*;
+
+fn c_string(string: &str) -> CString {
+    CString::new(string).unwrap()
+}
+
+const CLIENT_TABLE: &'static str = "{ \"name\": \"Client\",
+    \"key\": { \"fields\": { \"id\": \"u64\" } },
+    \"value\": { \"fields\": { \"full_name\": \"string\", \"age\": \"u64\" } } }";
+
+#[test]
+fn c_api_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let table_name = c_string("Client");
+    let key = c_string("{\"id\": 1}");
+    unsafe {
+        let db = nimdg_db_new();
+        assert!(!db.is_null());
+        assert_eq!(nimdg_table_create(db, c_string(CLIENT_TABLE).as_ptr()), NIMDG_OK);
+
+        let mut tx_id = 0;
+        assert_eq!(nimdg_tx_start(db, c_string("pessimistic").as_ptr(), &mut tx_id), NIMDG_OK);
+        assert_eq!(nimdg_put(db,
+                             tx_id,
+                             table_name.as_ptr(),
+                             key.as_ptr(),
+                             c_string("{\"full_name\": \"John Doe\", \"age\": 23}").as_ptr()),
+                   NIMDG_OK);
+        assert_eq!(nimdg_tx_commit(db, tx_id), NIMDG_OK);
+
+        let mut value = ptr::null_mut();
+        assert_eq!(nimdg_get(db, 0, table_name.as_ptr(), key.as_ptr(), &mut value), NIMDG_OK);
+        assert!(CStr::from_ptr(value).to_str().unwrap().contains("John Doe"));
+        nimdg_string_free(value);
+
+        let mut removed = false;
+        assert_eq!(nimdg_tx_start(db, ptr::null(), &mut tx_id), NIMDG_OK);
+        assert_eq!(nimdg_remove(db, tx_id, table_name.as_ptr(), key.as_ptr(), &mut removed), NIMDG_OK);
+        assert!(removed);
+        assert_eq!(nimdg_tx_commit(db, tx_id), NIMDG_OK);
+        assert_eq!(nimdg_get(db, 0, table_name.as_ptr(), key.as_ptr(), &mut value), NIMDG_OK);
+        assert!(value.is_null());
+        assert!(nimdg_last_error().is_null());
+
+        nimdg_db_free(db);
+    }
+}
+
+#[test]
+fn c_api_errors_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let key = c_string("{\"id\": 1}");
+    unsafe {
+        let db = nimdg_db_new();
+        assert_eq!(nimdg_table_create(db, c_string(CLIENT_TABLE).as_ptr()), NIMDG_OK);
+        assert_eq!(nimdg_table_create(db, c_string(CLIENT_TABLE).as_ptr()), NIMDG_TABLE_NOT_CREATED);
+        assert!(!nimdg_last_error().is_null());
+
+        let mut value = ptr::null_mut();
+        assert_eq!(nimdg_get(db, 0, c_string("Unknown").as_ptr(), key.as_ptr(), &mut value),
+                   NIMDG_TABLE_NOT_FOUND);
+        assert_eq!(nimdg_put(db,
+                             0,
+                             c_string("Client").as_ptr(),
+                             key.as_ptr(),
+                             c_string("{\"age\": \"old\"}").as_ptr()),
+                   NIMDG_WRONG_ENTITY);
+        assert_eq!(nimdg_put(db, 0, c_string("Client").as_ptr(), key.as_ptr(), ptr::null()),
+                   NIMDG_INVALID_ARGUMENT);
+        assert_eq!(nimdg_tx_commit(db, 12345), NIMDG_UNDEFINED_TRANSACTION);
+        assert_eq!(nimdg_tx_commit(ptr::null(), 1), NIMDG_INVALID_ARGUMENT);
+        let mut tx_id = 0;
+        assert_eq!(nimdg_tx_start(db, c_string("unknown").as_ptr(), &mut tx_id), NIMDG_INVALID_ARGUMENT);
+        assert!(CStr::from_ptr(nimdg_last_error()).to_str().unwrap().contains("unknown"));
+
+        nimdg_db_free(db);
+    }
+}
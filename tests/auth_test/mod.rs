@@ -0,0 +1,329 @@
+extern crate log4rs;
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem;
+use std::net::TcpStream;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use iron::Iron;
+
+use nimdg::data_base::json::JsonValue;
+use nimdg::data_base::meta::TableDescriptionView;
+use nimdg::data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+use nimdg::memcached::{MemcachedServer, DEFAULT_MEMCACHED_TABLE_NAME};
+use nimdg::protocol::client::BinaryClient;
+use nimdg::protocol::server::BinaryServer;
+use nimdg::resp::{Reply, read_reply};
+use nimdg::resp::server::{RespServer, DEFAULT_RESP_TABLE_NAME};
+use nimdg::rest::{create_application_with_security, RestSettings};
+use nimdg::rest::auth::{hash_password, AuthConfig, CertificateConfig, Credentials, Permission, Principal, Role, Security, TokenAuthenticator,
+                        TokenConfig, UserConfig};
+
+use data_base_test::{create_test_data_base, CLIENT_TABLE_NAME};
+
+fn role(tables: &[(&str, Vec<Permission>)]) -> Role {
+    tables.iter().map(|&(ref table, ref permissions)| (String::from(*table), permissions.clone())).collect()
+}
+
+fn principal(name: &str, roles: &[&str]) -> Principal {
+    Principal {
+        name: String::from(name),
+        roles: roles.iter().map(|role| String::from(*role)).collect(),
+    }
+}
+
+fn security() -> Security {
+    let mut roles = BTreeMap::new();
+    roles.insert(String::from("reader"), role(&[("*", vec![Permission::Read])]));
+    roles.insert(String::from("writer"), role(&[("Client", vec![Permission::Read, Permission::Write])]));
+    roles.insert(String::from("owner"), role(&[("Client", vec![Permission::Admin])]));
+    roles.insert(String::from("admin"), role(&[("*", vec![Permission::Admin])]));
+    Security::new(roles)
+}
+
+#[test]
+fn credentials_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    assert_eq!(Credentials::from_header("Bearer secret"), Some(Credentials::Token(String::from("secret"))));
+    // alice:pass:word
+    assert_eq!(Credentials::from_header("Basic YWxpY2U6cGFzczp3b3Jk"),
+               Some(Credentials::Basic(String::from("alice"), String::from("pass:word"))));
+    // Password is missed
+    assert_eq!(Credentials::from_header("Basic YWxpY2U="), None);
+    assert_eq!(Credentials::from_header("Digest username=alice"), None);
+    assert_eq!(Credentials::from_header("Bearer"), None);
+    assert!(!format!("{:?}", Credentials::Token(String::from("secret"))).contains("secret"));
+}
+
+#[test]
+fn permissions_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let security = security();
+    let reader = principal("reader", &["reader"]);
+    assert!(security.allows(&reader, "Client", Permission::Read));
+    assert!(security.allows(&reader, "Order", Permission::Read));
+    assert!(!security.allows(&reader, "Client", Permission::Write));
+
+    let writer = principal("writer", &["writer", "unknown"]);
+    assert!(security.allows(&writer, "Client", Permission::Write));
+    assert!(!security.allows(&writer, "Order", Permission::Read));
+    assert!(!security.allows(&writer, "Client", Permission::Ddl));
+
+    // Admin of table has all permissions for it, but is not admin of server
+    let owner = principal("owner", &["owner"]);
+    assert!(security.allows(&owner, "Client", Permission::Ddl));
+    assert!(!security.allows(&owner, "Order", Permission::Read));
+    assert!(!security.is_admin(&owner));
+
+    let admin = principal("admin", &["admin"]);
+    assert!(security.allows(&admin, "Order", Permission::Ddl));
+    assert!(security.is_admin(&admin));
+    assert!(!security.is_admin(&reader));
+}
+
+#[test]
+fn authenticate_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let config = AuthConfig {
+        tokens: vec![TokenConfig {
+                         name: String::from("loader"),
+                         token: String::from("secret"),
+                         roles: vec![String::from("writer")],
+                     }],
+        users: vec![UserConfig {
+                        name: String::from("alice"),
+                        password_hash: hash_password("password", 4).unwrap(),
+                        roles: vec![String::from("reader")],
+                    }],
+        certificates: vec![CertificateConfig {
+                               common_name: String::from("importer.cluster"),
+                               name: String::from("importer"),
+                               roles: vec![String::from("writer")],
+                           }],
+        roles: BTreeMap::new(),
+    };
+    let security = Security::from_config(&config);
+    assert_eq!(security.authenticate(&Credentials::Token(String::from("secret"))),
+               Some(principal("loader", &["writer"])));
+    assert_eq!(security.authenticate(&Credentials::Token(String::from("wrong"))), None);
+    assert_eq!(security.authenticate(&Credentials::Basic(String::from("alice"), String::from("password"))),
+               Some(principal("alice", &["reader"])));
+    assert_eq!(security.authenticate(&Credentials::Basic(String::from("alice"), String::from("wrong"))), None);
+    assert_eq!(security.authenticate(&Credentials::Basic(String::from("loader"), String::from("secret"))), None);
+    assert_eq!(security.authenticate(&Credentials::Certificate(String::from("importer.cluster"))),
+               Some(principal("importer", &["writer"])));
+    assert_eq!(security.authenticate(&Credentials::Certificate(String::from("importer"))), None);
+
+    // Roles of principals must be defined
+    let errors = config.validate().unwrap_err();
+    assert!(errors.contains("writer"));
+    assert!(errors.contains("reader"));
+}
+
+#[test]
+fn transaction_owner_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let security = security();
+    let writer = principal("writer", &["writer"]);
+    let reader = principal("reader", &["reader"]);
+    let admin = principal("admin", &["admin"]);
+
+    security.set_owner(5, &writer);
+    assert!(security.can_use_transaction(&writer, 5));
+    assert!(!security.can_use_transaction(&reader, 5));
+    assert!(security.can_use_transaction(&admin, 5));
+
+    // Transaction without owner is available only to admin
+    assert!(!security.can_use_transaction(&writer, 6));
+    assert!(security.can_use_transaction(&admin, 6));
+
+    security.release_owner(5);
+    assert!(!security.can_use_transaction(&writer, 5));
+}
+
+// Token secret is given to loader with role writer, token audit - to auditor with role reader
+fn token_security() -> Security {
+    let token = |name: &str, token: &str, role: &str| {
+        TokenConfig {
+            name: String::from(name),
+            token: String::from(token),
+            roles: vec![String::from(role)],
+        }
+    };
+    let mut security = security();
+    security.add_authenticator(Box::new(TokenAuthenticator::new(&[token("loader", "secret", "writer"), token("auditor", "audit", "reader")])));
+    security
+}
+
+fn listener_security() -> Arc<Security> {
+    Arc::new(token_security())
+}
+
+#[test]
+fn binary_protocol_auth_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let server = BinaryServer::bind("127.0.0.1:0", Arc::new(create_test_data_base())).unwrap().with_access_control(listener_security());
+    let address = server.local_addr().unwrap();
+    server.spawn();
+    let key = JsonValue::from_str("{\"id\": 1}").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23}").unwrap();
+
+    // Requests of client without login are rejected
+    let mut anonymous = BinaryClient::connect(address).unwrap();
+    assert!(anonymous.put(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key, &value).unwrap_err().contains("Authentication required"));
+    assert!(anonymous.get(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key).is_err());
+    assert!(anonymous.tx_start(LockMode::Pessimistic, IsolationLevel::RepeatableRead).is_err());
+    assert!(anonymous.auth(None, "wrong").is_err());
+    assert!(anonymous.auth(Some("loader"), "secret").is_err());
+    assert!(anonymous.scan(DEFAULT_TX_ID, CLIENT_TABLE_NAME, 0, 10).is_err());
+
+    let mut loader = BinaryClient::connect(address).unwrap();
+    assert_eq!(loader.auth(None, "secret").unwrap(), "loader");
+    let tx_id = loader.tx_start(LockMode::Pessimistic, IsolationLevel::RepeatableRead).unwrap();
+    loader.put(tx_id, CLIENT_TABLE_NAME, &key, &value).unwrap();
+
+    // Reader cannot write and cannot use transaction of other principal
+    let mut auditor = BinaryClient::connect(address).unwrap();
+    assert_eq!(auditor.auth(None, "audit").unwrap(), "auditor");
+    assert!(auditor.put(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key, &value).unwrap_err().contains("no permission write"));
+    assert!(auditor.tx_rollback(tx_id).unwrap_err().contains("not owned"));
+    loader.tx_commit(tx_id).unwrap();
+    assert_eq!(auditor.get(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key).unwrap(), Some(value));
+}
+
+#[test]
+fn resp_and_memcached_auth_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = Arc::new(create_test_data_base());
+    let server = RespServer::bind("127.0.0.1:0", data_base_manager.clone(), DEFAULT_RESP_TABLE_NAME).unwrap();
+    let mut stream = BufReader::new(TcpStream::connect(server.local_addr().unwrap()).unwrap());
+    server.with_access_control(listener_security()).spawn();
+    let mut command = |args: &[&str]| {
+        Reply::Array(args.iter().map(|arg| Reply::Bulk(Some(String::from(*arg)))).collect()).write(stream.get_mut()).unwrap();
+        read_reply(&mut stream).unwrap().unwrap()
+    };
+    assert_eq!(command(&["GET", "name"]), Reply::Error(String::from("NOAUTH Authentication required.")));
+    assert!(match command(&["AUTH", "wrong"]) {
+        Reply::Error(error) => error.starts_with("WRONGPASS"),
+        _ => false,
+    });
+    assert_eq!(command(&["AUTH", "audit"]), Reply::ok());
+    assert_eq!(command(&["GET", "name"]), Reply::nil());
+    // Reader has no permission write, writer has permissions only for table Client
+    assert!(match command(&["SET", "name", "John"]) {
+        Reply::Error(error) => error.starts_with("NOPERM"),
+        _ => false,
+    });
+    assert_eq!(command(&["AUTH", "secret"]), Reply::ok());
+    assert!(match command(&["GET", "name"]) {
+        Reply::Error(error) => error.starts_with("NOPERM"),
+        _ => false,
+    });
+
+    let server = MemcachedServer::bind("127.0.0.1:0", data_base_manager, DEFAULT_MEMCACHED_TABLE_NAME).unwrap();
+    let mut stream = BufReader::new(TcpStream::connect(server.local_addr().unwrap()).unwrap());
+    server.with_access_control(listener_security()).spawn();
+    let mut request = |request: &str| {
+        stream.get_mut().write_all(request.as_bytes()).unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).unwrap();
+        line
+    };
+    assert_eq!(request("get greeting\r\n"), "CLIENT_ERROR unauthenticated\r\n");
+    assert_eq!(request("set auth 0 0 5\r\nwrong\r\n"), "CLIENT_ERROR authentication failure\r\n");
+    assert_eq!(request("set auth 0 0 5\r\naudit\r\n"), "STORED\r\n");
+    assert_eq!(request("get greeting\r\n"), "END\r\n");
+    assert!(request("set greeting 0 0 5\r\nhello\r\n").starts_with("CLIENT_ERROR Principal auditor has no permission write"));
+}
+
+// Status line of response to GET with token
+// Status line and headers with body
+fn get_response(address: &str, path: &str, token: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream,
+           "GET {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nConnection: close\r\n\r\n",
+           path,
+           token)
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn get_status(address: &str, path: &str, token: &str) -> String {
+    String::from(get_response(address, path, token).lines().next().unwrap_or(""))
+}
+
+#[test]
+fn rest_table_parameter_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let app = create_application_with_security(Arc::new(create_test_data_base()), RestSettings::default(), Some(listener_security()));
+    let listening = Iron::new(app).http("127.0.0.1:0").unwrap();
+    let address = listening.socket.to_string();
+    // Server works till end of tests
+    mem::forget(listening);
+
+    // Writer may read only table Client, other parameter with table name doesn't replace table of route
+    assert!(get_status(&address, "/api/v1/meta/table/Client/schema", "secret").starts_with("HTTP/1.1 200"));
+    assert!(get_status(&address, "/api/v1/meta/table/Secret/schema?table_name=Client", "secret").starts_with("HTTP/1.1 403"));
+    assert!(get_status(&address, "/api/v1/queries/subscribe?table=Secret&table_name=Client", "secret").starts_with("HTTP/1.1 403"));
+    assert!(get_status(&address, "/api/v1/metrics?table_name=Client", "secret").starts_with("HTTP/1.1 403"));
+    assert!(get_status(&address, "/api/v1/metrics", "audit").starts_with("HTTP/1.1 200"));
+}
+
+#[test]
+fn rest_readable_tables_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let secret_table = JsonValue::from_str("{\"name\": \"Secret\", \"key\": {\"fields\": {\"id\": \"u64\"}}, \"value\": {\"fields\": {\"code\": \"string\"}}}")
+        .unwrap();
+    data_base_manager.add_table(TableDescriptionView::from_json(&secret_table).unwrap()).unwrap();
+    let app = create_application_with_security(Arc::new(data_base_manager), RestSettings::default(), Some(listener_security()));
+    let listening = Iron::new(app).http("127.0.0.1:0").unwrap();
+    let address = listening.socket.to_string();
+    mem::forget(listening);
+
+    // Writer sees only tables, that it may read
+    for path in &["/api/v1/info", "/api/v1/openapi"] {
+        let response = get_response(&address, path, "secret");
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("Client"));
+        assert!(!response.contains("Secret"));
+        let response = get_response(&address, path, "audit");
+        assert!(response.contains("Client"));
+        assert!(response.contains("Secret"));
+    }
+}
+
+#[test]
+fn shared_owner_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    // REST api and listener share security, so transaction of listener belongs to its principal in REST api
+    let data_base_manager = Arc::new(create_test_data_base());
+    let security = listener_security();
+    let server = BinaryServer::bind("127.0.0.1:0", data_base_manager.clone()).unwrap().with_access_control(security.clone());
+    let binary_address = server.local_addr().unwrap();
+    server.spawn();
+    let app = create_application_with_security(data_base_manager, RestSettings::default(), Some(security));
+    let listening = Iron::new(app).http("127.0.0.1:0").unwrap();
+    let address = listening.socket.to_string();
+    mem::forget(listening);
+
+    let mut loader = BinaryClient::connect(binary_address).unwrap();
+    loader.auth(None, "secret").unwrap();
+    let tx_id = loader.tx_start(LockMode::Pessimistic, IsolationLevel::RepeatableRead).unwrap();
+    assert!(get_status(&address, &format!("/api/v1/meta/tx/{}", tx_id), "secret").starts_with("HTTP/1.1 200"));
+    assert!(get_status(&address, &format!("/api/v1/meta/tx/{}", tx_id), "audit").starts_with("HTTP/1.1 403"));
+    loader.tx_commit(tx_id).unwrap();
+}
@@ -0,0 +1,65 @@
+/* Test of C api, run by make in this directory after cargo build */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "nimdg.h"
+
+#define CHECK(condition)                                                        \
+    do {                                                                        \
+        if (!(condition)) {                                                     \
+            const char *error = nimdg_last_error();                             \
+            fprintf(stderr, "%s:%d: check failed: %s, last error: %s\n",        \
+                    __FILE__, __LINE__, #condition, error ? error : "none");    \
+            exit(1);                                                            \
+        }                                                                       \
+    } while (0)
+
+static const char *CLIENT_TABLE =
+    "{ \"name\": \"Client\","
+    "  \"key\": { \"fields\": { \"id\": \"u64\" } },"
+    "  \"value\": { \"fields\": { \"full_name\": \"string\", \"age\": \"u64\" } } }";
+
+int main(void) {
+    nimdg_db *db = nimdg_db_new();
+    CHECK(db != NULL);
+    CHECK(nimdg_table_create(db, CLIENT_TABLE) == NIMDG_OK);
+    CHECK(nimdg_table_create(db, CLIENT_TABLE) == NIMDG_TABLE_NOT_CREATED);
+    CHECK(nimdg_last_error() != NULL);
+
+    uint64_t tx_id = 0;
+    CHECK(nimdg_tx_start(db, "pessimistic", &tx_id) == NIMDG_OK);
+    CHECK(nimdg_put(db, tx_id, "Client", "{\"id\": 1}", "{\"full_name\": \"John Doe\", \"age\": 23}") == NIMDG_OK);
+    CHECK(nimdg_tx_commit(db, tx_id) == NIMDG_OK);
+    CHECK(nimdg_last_error() == NULL);
+
+    char *value = NULL;
+    CHECK(nimdg_get(db, 0, "Client", "{\"id\": 1}", &value) == NIMDG_OK);
+    CHECK(value != NULL);
+    CHECK(strstr(value, "\"John Doe\"") != NULL);
+    nimdg_string_free(value);
+
+    /* Rolled back removal keeps value */
+    bool removed = false;
+    CHECK(nimdg_tx_start(db, NULL, &tx_id) == NIMDG_OK);
+    CHECK(nimdg_remove(db, tx_id, "Client", "{\"id\": 1}", &removed) == NIMDG_OK);
+    CHECK(removed);
+    CHECK(nimdg_tx_rollback(db, tx_id) == NIMDG_OK);
+    CHECK(nimdg_get(db, 0, "Client", "{\"id\": 1}", &value) == NIMDG_OK);
+    CHECK(value != NULL);
+    nimdg_string_free(value);
+
+    CHECK(nimdg_get(db, 0, "Client", "{\"id\": 2}", &value) == NIMDG_OK);
+    CHECK(value == NULL);
+
+    /* Errors */
+    CHECK(nimdg_get(db, 0, "Unknown", "{\"id\": 1}", &value) == NIMDG_TABLE_NOT_FOUND);
+    CHECK(nimdg_put(db, 0, "Client", "{\"id\": 1}", "{\"age\": \"old\"}") == NIMDG_WRONG_ENTITY);
+    CHECK(nimdg_put(db, 0, "Client", "{\"id\": ", "{}") == NIMDG_INVALID_ARGUMENT);
+    CHECK(nimdg_tx_commit(db, 12345) == NIMDG_UNDEFINED_TRANSACTION);
+    CHECK(nimdg_tx_start(db, "unknown", &tx_id) == NIMDG_INVALID_ARGUMENT);
+
+    nimdg_db_free(db);
+    printf("nimdg C api test passed\n");
+    return 0;
+}
@@ -0,0 +1,141 @@
+extern crate log4rs;
+
+use std::str::FromStr;
+
+use nimdg::cli::{Command, file_format, format_entities, format_import_report, format_table, split_json};
+use nimdg::cli::completion::complete_line;
+use nimdg::data_base::bulk::BulkFormat;
+use nimdg::data_base::json::JsonValue;
+use nimdg::data_base::transaction::LockMode;
+
+fn json(text: &str) -> JsonValue {
+    JsonValue::from_str(text).unwrap()
+}
+
+fn strings(strings: &[&str]) -> Vec<String> {
+    strings.iter().map(|string| String::from(*string)).collect()
+}
+
+fn complete(line: &str) -> (usize, Vec<String>) {
+    complete_line(line,
+                  line.len(),
+                  || strings(&["Client", "Times"]),
+                  |table_name| if table_name == "Client" {
+                      strings(&["id", "full_name", "age"])
+                  } else {
+                      Vec::new()
+                  })
+}
+
+#[test]
+fn parse_command_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    assert_eq!(Command::parse("  ").unwrap(), None);
+    assert_eq!(Command::parse("begin").unwrap(),
+               Some(Command::Begin(LockMode::Pessimistic)));
+    assert_eq!(Command::parse("begin optimistic").unwrap(),
+               Some(Command::Begin(LockMode::Optimistic)));
+    assert_eq!(Command::parse("commit").unwrap(), Some(Command::Commit));
+    assert_eq!(Command::parse("rollback").unwrap(), Some(Command::Rollback));
+    assert_eq!(Command::parse("get Client {\"id\": 1}").unwrap(),
+               Some(Command::Get(String::from("Client"), json("{\"id\": 1}"))));
+    assert_eq!(Command::parse("put Client {\"id\": 1} {\"full_name\": \"John Doe\", \"age\": 23}").unwrap(),
+               Some(Command::Put(String::from("Client"),
+                                 json("{\"id\": 1}"),
+                                 json("{\"full_name\": \"John Doe\", \"age\": 23}"))));
+    assert_eq!(Command::parse("delete Client {\"id\": 1}").unwrap(),
+               Some(Command::Delete(String::from("Client"), json("{\"id\": 1}"))));
+    assert_eq!(Command::parse("scan Client").unwrap(),
+               Some(Command::Scan(String::from("Client"), 0, 20)));
+    assert_eq!(Command::parse("scan Client 10 5").unwrap(),
+               Some(Command::Scan(String::from("Client"), 10, 5)));
+    assert_eq!(Command::parse("import Client clients.csv 500").unwrap(),
+               Some(Command::Import(String::from("Client"), String::from("clients.csv"), Some(500))));
+    assert_eq!(Command::parse("export Client clients.jsonl").unwrap(),
+               Some(Command::Export(String::from("Client"), String::from("clients.jsonl"))));
+    assert_eq!(Command::parse("tables").unwrap(), Some(Command::Tables));
+    assert_eq!(Command::parse("describe Client").unwrap(),
+               Some(Command::Describe(String::from("Client"))));
+    assert_eq!(Command::parse("create table {\"name\": \"Times\", \"key\": {\"id\": \"u64\"}}").unwrap(),
+               Some(Command::CreateTable(json("{\"name\": \"Times\", \"key\": {\"id\": \"u64\"}}"))));
+    assert_eq!(Command::parse("quit").unwrap(), Some(Command::Exit));
+}
+
+#[test]
+fn parse_command_errors_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    assert!(Command::parse("select * from Client").is_err());
+    assert!(Command::parse("begin slowly").is_err());
+    assert!(Command::parse("commit now").is_err());
+    assert!(Command::parse("get").is_err());
+    assert!(Command::parse("get Client {\"id\": }").is_err());
+    assert!(Command::parse("get Client {\"id\": 1} 2").is_err());
+    assert!(Command::parse("put Client {\"id\": 1}").is_err());
+    assert!(Command::parse("scan Client first").is_err());
+    assert!(Command::parse("import Client").is_err());
+    assert!(Command::parse("import Client clients.csv all").is_err());
+    assert!(Command::parse("describe").is_err());
+    assert!(Command::parse("create index {}").is_err());
+}
+
+#[test]
+fn split_json_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let (key, rest) = split_json("{\"name\": \"a } b\"} [1, 2] 3").unwrap();
+    assert_eq!(key, json("{\"name\": \"a } b\"}"));
+    let (array, rest) = split_json(rest).unwrap();
+    assert_eq!(array, json("[1, 2]"));
+    let (number, rest) = split_json(rest).unwrap();
+    assert_eq!(number, json("3"));
+    assert_eq!(rest, "");
+    let (string, rest) = split_json("\"John \\\" Doe\" 4").unwrap();
+    assert_eq!(string, json("\"John \\\" Doe\""));
+    assert_eq!(rest, "4");
+    assert!(split_json("  ").is_err());
+}
+
+#[test]
+fn format_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let table = format_table(&strings(&["id", "full_name"]),
+                             &[strings(&["1", "John Doe"]), strings(&["10", "Jo"])]);
+    assert_eq!(table, "id | full_name\n---+----------\n1  | John Doe\n10 | Jo");
+
+    let entities = vec![(json("{\"id\": 1}"), json("{\"id\": 7, \"full_name\": \"John Doe\"}")),
+                        (json("{\"id\": 2}"), json("{\"age\": 45}"))];
+    assert_eq!(format_entities(&entities),
+               "id | full_name | value.id | age\n\
+                ---+-----------+----------+----\n\
+                1  | John Doe  | 7\n\
+                2  |           |          | 45\n\
+                (2 entities)");
+
+    assert_eq!(file_format("clients.csv"), BulkFormat::Csv);
+    assert_eq!(file_format("clients.jsonl"), BulkFormat::JsonLines);
+    let report = json("{\"imported\": 2, \"batches\": 1, \"rejected\": [{\"line\": 3, \"error\": \"Read(wrong)\"}]}");
+    assert_eq!(format_import_report(&report),
+               "Imported 2 rows in 1 batches, rejected 1 rows:\n\
+                line | error\n\
+                -----+------------\n\
+                3    | Read(wrong)");
+}
+
+#[test]
+fn completion_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    assert_eq!(complete("de"), (0, strings(&["delete", "describe"])));
+    assert_eq!(complete("begin o"), (6, strings(&["optimistic"])));
+    assert_eq!(complete("create "), (7, strings(&["table"])));
+    assert_eq!(complete("scan "), (5, strings(&["Client", "Times"])));
+    assert_eq!(complete("get C"), (4, strings(&["Client"])));
+    assert_eq!(complete("get Client {\"i"), (12, strings(&["\"id\": "])));
+    assert_eq!(complete("put Client {\"id\": 1} {\"full_name\": \"a\", a"),
+               (40, strings(&["\"age\": "])));
+    assert_eq!(complete("get Times {"), (11, Vec::new()));
+    assert_eq!(complete("commit "), (7, Vec::new()));
+}
@@ -0,0 +1,94 @@
+extern crate log4rs;
+
+use std::sync::Arc;
+
+use nimdg::client::{Client as NimdgClient, ClientError};
+use nimdg::protocol::server::BinaryServer;
+
+use data_base_test::create_test_data_base;
+use data_base_test::{IdKey, Client, CLIENT_TABLE_NAME};
+
+// Server is started in process on ephemeral port, every test has own data base
+fn start_server() -> NimdgClient {
+    let server = BinaryServer::bind("127.0.0.1:0", Arc::new(create_test_data_base())).unwrap();
+    let address = server.local_addr().unwrap();
+    server.spawn();
+    NimdgClient::connect(address).unwrap()
+}
+
+fn client(id: u64) -> Client {
+    Client {
+        full_name: format!("TestName{}", id),
+        age: 20 + id,
+    }
+}
+
+fn get_age(nimdg_client: &NimdgClient, id: u64) -> Option<u64> {
+    nimdg_client.get::<IdKey, Client>(CLIENT_TABLE_NAME, &IdKey { id: id }).unwrap().map(|client| client.age)
+}
+
+#[test]
+fn typed_client_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let nimdg_client = start_server();
+    assert_eq!(get_age(&nimdg_client, 1), None);
+    nimdg_client.put(CLIENT_TABLE_NAME, &IdKey { id: 1 }, &client(1)).unwrap();
+    let value: Client = nimdg_client.get(CLIENT_TABLE_NAME, &IdKey { id: 1 }).unwrap().unwrap();
+    assert_eq!(value.full_name, "TestName1");
+    assert_eq!(value.age, 21);
+
+    assert!(nimdg_client.remove(CLIENT_TABLE_NAME, &IdKey { id: 1 }).unwrap());
+    assert!(!nimdg_client.remove(CLIENT_TABLE_NAME, &IdKey { id: 1 }).unwrap());
+
+    // Value of other type is not accepted by table
+    match nimdg_client.put(CLIENT_TABLE_NAME, &IdKey { id: 2 }, &IdKey { id: 2 }) {
+        Err(ClientError::Request(_)) => {}
+        res => panic!("Wrong value is put {:?}", res),
+    }
+}
+
+#[test]
+fn transaction_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let nimdg_client = start_server();
+    {
+        let tx = nimdg_client.begin().unwrap();
+        tx.put(CLIENT_TABLE_NAME, &IdKey { id: 1 }, &client(1)).unwrap();
+        tx.put(CLIENT_TABLE_NAME, &IdKey { id: 2 }, &client(2)).unwrap();
+        assert_eq!(tx.get::<IdKey, Client>(CLIENT_TABLE_NAME, &IdKey { id: 1 }).unwrap().map(|client| client.age),
+                   Some(21));
+        tx.commit().unwrap();
+    }
+    assert_eq!(get_age(&nimdg_client, 2), Some(22));
+
+    // Dropped transaction is rolled back
+    {
+        let tx = nimdg_client.begin().unwrap();
+        tx.put(CLIENT_TABLE_NAME, &IdKey { id: 3 }, &client(3)).unwrap();
+        assert!(tx.remove(CLIENT_TABLE_NAME, &IdKey { id: 1 }).unwrap());
+    }
+    assert_eq!(get_age(&nimdg_client, 3), None);
+    assert_eq!(get_age(&nimdg_client, 1), Some(21));
+
+    let tx = nimdg_client.begin().unwrap();
+    tx.put(CLIENT_TABLE_NAME, &IdKey { id: 1 }, &client(10)).unwrap();
+    tx.rollback().unwrap();
+    assert_eq!(get_age(&nimdg_client, 1), Some(21));
+}
+
+#[test]
+fn scan_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let nimdg_client = start_server();
+    for id in 1..11 {
+        nimdg_client.put(CLIENT_TABLE_NAME, &IdKey { id: id }, &client(id)).unwrap();
+    }
+    let tx = nimdg_client.begin().unwrap();
+    let entities: Vec<(IdKey, Client)> = tx.scan(CLIENT_TABLE_NAME, 0, 100).unwrap();
+    tx.commit().unwrap();
+    assert_eq!(entities.len(), 10);
+    assert!(entities.iter().all(|&(ref key, ref value)| value.age == 20 + key.id));
+}
@@ -0,0 +1,63 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::create_test_data_base;
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn auto_commit_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23 }").unwrap();
+
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key, &value).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap(),
+               Some(value.clone()));
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 10).unwrap().len(),
+               1);
+
+    // Failed operation is rolled back and leaves no transaction
+    let wrong_value = JsonValue::from_str("{\"full_name\": \"John Doe\" }").unwrap();
+    assert!(data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key, &wrong_value).is_err());
+    assert_eq!(data_base_manager.get_transactions_list(), JsonValue::Array(vec![]));
+
+    assert!(data_base_manager.remove_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap());
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap().is_none());
+    assert!(!data_base_manager.remove_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap());
+}
+
+#[test]
+fn remove_in_transaction_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 2 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"David K\", \"age\": 45 }").unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key, &value).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(data_base_manager.remove_data(&tx_id, &client_table_name, &key).unwrap());
+    assert!(data_base_manager.get_data(&tx_id, &client_table_name, &key).unwrap().is_none());
+    data_base_manager.tx_rollback(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap(),
+               Some(value.clone()));
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(data_base_manager.remove_data(&tx_id, &client_table_name, &key).unwrap());
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap().is_none());
+
+    let changes = data_base_manager.get_changes(0, 100).unwrap();
+    let last_change = changes.last().unwrap();
+    assert_eq!(last_change.old_value, Some(value));
+    assert!(last_change.new_value.is_none());
+}
@@ -0,0 +1,83 @@
+extern crate log4rs;
+
+extern crate serde;
+extern crate serde_json;
+
+use nimdg::data_base::PersistenceError;
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::create_test_data_base;
+use data_base_test::{IdKey, Client, CLIENT_TABLE_NAME};
+
+fn client_entry(id: u64) -> (JsonValue, JsonValue) {
+    (serde_json::to_value(IdKey { id: id }),
+     serde_json::to_value(Client {
+        full_name: format!("TestName{}", id),
+        age: 20 + id,
+    }))
+}
+
+#[test]
+fn batch_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    // Keys in reverse order - locks are taken in order of keys anyway
+    let entries: Vec<(JsonValue, JsonValue)> = (1..11).rev().map(client_entry).collect();
+    let results = data_base_manager.put_all(&DEFAULT_TX_ID, &client_table_name, &entries, true).unwrap();
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|result| result.is_ok()));
+
+    let keys = vec![serde_json::to_value(IdKey { id: 3 }), serde_json::to_value(IdKey { id: 100 })];
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    let values = data_base_manager.get_all(&tx_id, &client_table_name, &keys, false).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert_eq!(values[0].as_ref().unwrap(), &Some(client_entry(3).1));
+    assert_eq!(values[1].as_ref().unwrap(), &None);
+
+    let removed = data_base_manager.remove_all(&DEFAULT_TX_ID, &client_table_name, &keys, false).unwrap();
+    assert!(*removed[0].as_ref().unwrap());
+    assert!(!*removed[1].as_ref().unwrap());
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 100).unwrap().len(),
+               9);
+}
+
+#[test]
+fn atomic_batch_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let wrong_entry = (serde_json::to_value(IdKey { id: 2 }), JsonValue::from_str("{\"age\": 1 }").unwrap());
+    let entries = vec![client_entry(1), wrong_entry, client_entry(3)];
+
+    // Nothing is written by failed atomic batch
+    assert!(data_base_manager.put_all(&DEFAULT_TX_ID, &client_table_name, &entries, true).is_err());
+    assert!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 100).unwrap().is_empty());
+
+    // Failed atomic batch is undone in explicit transaction, previous changes are kept
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &client_entry(4).0, &client_entry(4).1).unwrap();
+    assert!(data_base_manager.put_all(&tx_id, &client_table_name, &entries, true).is_err());
+    // Savepoint of batch is not available for user
+    match data_base_manager.tx_savepoint(&tx_id, &String::from("__nimdg_batch")) {
+        Err(PersistenceError::ReservedSavepoint(_)) => (),
+        res => panic!("Reserved savepoint is set: {:?}", res),
+    }
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 100).unwrap().len(),
+               1);
+
+    // Not atomic batch writes valid entries
+    let results = data_base_manager.put_all(&DEFAULT_TX_ID, &client_table_name, &entries, false).unwrap();
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 100).unwrap().len(),
+               3);
+}
@@ -0,0 +1,146 @@
+extern crate log4rs;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use nimdg::data_base::DataBaseManager;
+use nimdg::data_base::PersistenceError;
+use nimdg::data_base::bulk::BulkFormat;
+use nimdg::data_base::json::JsonValue;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::{create_test_data_base, CLIENT_TABLE_NAME};
+
+const JSON_LINES: &'static str = "{\"key\": {\"id\": 1}, \"value\": {\"full_name\": \"John Doe\", \"age\": 23}}
+{\"key\": {\"id\": 2}, \"value\": {\"full_name\": \"David K\", \"age\": \"old\"}}
+
+{\"key\": {\"id\": 3}, \"value\": {\"full_name\": \"Ann\", \"age\": 30}}
+not json
+{\"key\": {\"id\": 4}}
+";
+
+const CSV: &'static str = "id,full_name,age
+1,\"Doe, John\",23
+2,David K
+3,Ann,thirty
+4,\"Smith \"\"Jr\"\"\",40
+";
+
+fn get_client(data_base_manager: &DataBaseManager, id: u64) -> Option<JsonValue> {
+    get_client_in_tx(data_base_manager, &DEFAULT_TX_ID, id)
+}
+
+fn get_client_in_tx(data_base_manager: &DataBaseManager, tx_id: &u64, id: u64) -> Option<JsonValue> {
+    let key = JsonValue::from_str(&format!("{{\"id\": {}}}", id)).unwrap();
+    data_base_manager.get_data(tx_id, &String::from(CLIENT_TABLE_NAME), &key).unwrap()
+}
+
+#[test]
+fn import_json_lines_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let report = data_base_manager.import(&String::from(CLIENT_TABLE_NAME),
+                &BulkFormat::JsonLines,
+                JSON_LINES.as_bytes(),
+                2)
+        .unwrap();
+
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.batches, 2);
+    let lines: Vec<usize> = report.rejected.iter().map(|row| row.line).collect();
+    assert_eq!(lines, vec![2, 5, 6]);
+    assert!(report.rejected[0].error.to_string().contains("Expected type u64"));
+
+    assert_eq!(get_client(&data_base_manager, 1),
+               Some(JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23}").unwrap()));
+    assert!(get_client(&data_base_manager, 2).is_none());
+    assert!(get_client(&data_base_manager, 3).is_some());
+}
+
+#[test]
+fn import_export_csv_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let table_name = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    let report = data_base_manager.import(&table_name, &BulkFormat::Csv, CSV.as_bytes(), 100).unwrap();
+    assert_eq!(report.imported, 2);
+    assert_eq!(report.batches, 1);
+    let lines: Vec<usize> = report.rejected.iter().map(|row| row.line).collect();
+    assert_eq!(lines, vec![3, 4]);
+    assert_eq!(get_client(&data_base_manager, 4),
+               Some(JsonValue::from_str("{\"full_name\": \"Smith \\\"Jr\\\"\", \"age\": 40}").unwrap()));
+
+    let mut csv = Vec::new();
+    assert_eq!(data_base_manager.export(&table_name, &BulkFormat::Csv, &mut csv).unwrap(), 2);
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv,
+               "id,age,full_name\n1,23,\"Doe, John\"\n4,40,\"Smith \"\"Jr\"\"\"\n");
+
+    let mut json_lines = Vec::new();
+    data_base_manager.export(&table_name, &BulkFormat::JsonLines, &mut json_lines).unwrap();
+    let json_lines = String::from_utf8(json_lines).unwrap();
+    assert_eq!(json_lines.lines().next(),
+               Some("{\"key\":{\"id\":1},\"value\":{\"age\":23,\"full_name\":\"Doe, John\"}}"));
+
+    // Exported data is imported without rejected rows
+    for &(ref format, data) in &[(BulkFormat::Csv, &csv), (BulkFormat::JsonLines, &json_lines)] {
+        let copy = create_test_data_base();
+        let report = copy.import(&table_name, format, data.as_bytes(), 1).unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.batches, 2);
+        assert!(report.rejected.is_empty());
+        assert_eq!(get_client(&copy, 4), get_client(&data_base_manager, 4));
+    }
+}
+
+#[test]
+fn import_errors_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let table_name = String::from(CLIENT_TABLE_NAME);
+    match data_base_manager.import(&table_name, &BulkFormat::Csv, "id,name,age\n1,John,23\n".as_bytes(), 10) {
+        Err(PersistenceError::IoEntity(error)) => assert!(error.to_string().contains("Column name is not field")),
+        res => panic!("Unexpected result of import {:?}", res),
+    }
+    match data_base_manager.import(&table_name, &BulkFormat::Csv, "id,age\n1,23\n".as_bytes(), 10) {
+        Err(PersistenceError::IoEntity(error)) => assert!(error.to_string().contains("Column of field full_name not found")),
+        res => panic!("Unexpected result of import {:?}", res),
+    }
+    match data_base_manager.import(&String::from("Unknown"), &BulkFormat::JsonLines, "".as_bytes(), 10) {
+        Err(PersistenceError::TableNotFound(name)) => assert_eq!(name, "Unknown"),
+        res => panic!("Unexpected result of import {:?}", res),
+    }
+    assert!(BulkFormat::from_str("xml").is_err());
+    assert_eq!(BulkFormat::from_str("csv").unwrap(), BulkFormat::Csv);
+}
+
+#[test]
+fn import_failed_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let table_name = String::from(CLIENT_TABLE_NAME);
+    data_base_manager.set_lock_wait(Some(Duration::from_millis(50)));
+    data_base_manager.import(&table_name, &BulkFormat::Csv, "id,full_name,age\n3,Ann,30\n".as_bytes(), 10).unwrap();
+    // Other transaction holds lock of key of line 4
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(get_client_in_tx(&data_base_manager, &tx_id, 3).is_some());
+
+    let csv = "id,full_name,age\n1,John,23\n2,David,old\n3,Ann,31\n4,Bob,50\n";
+    match data_base_manager.import(&table_name, &BulkFormat::Csv, csv.as_bytes(), 1) {
+        Err(PersistenceError::ImportFailed(line, report, _)) => {
+            assert_eq!(line, 4);
+            assert_eq!(report.imported, 1);
+            assert_eq!(report.batches, 1);
+            let lines: Vec<usize> = report.rejected.iter().map(|row| row.line).collect();
+            assert_eq!(lines, vec![3]);
+        }
+        res => panic!("Unexpected result of import {:?}", res),
+    }
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert!(get_client(&data_base_manager, 1).is_some());
+    assert!(get_client(&data_base_manager, 4).is_none());
+}
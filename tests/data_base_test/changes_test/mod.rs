@@ -0,0 +1,102 @@
+extern crate log4rs;
+
+extern crate serde;
+extern crate serde_json;
+
+use nimdg::data_base::PersistenceError;
+use nimdg::data_base::changes::{ChangeEventStream, ChangeLog};
+use nimdg::data_base::json::JsonValue;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::Arc;
+use nimdg::data_base::transaction::LockMode;
+
+use data_base_test::create_test_data_base;
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn commit_changes_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 7 }").unwrap();
+    let value_one = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23 }").unwrap();
+    let value_two = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 24 }").unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key, &value_one).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let changes = data_base_manager.get_changes(0, 100).unwrap();
+    info!("Changes after insert = {:?}", changes);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].tx_id, tx_id);
+    assert_eq!(changes[0].table_name, client_table_name);
+    assert_eq!(changes[0].key, key);
+    assert!(changes[0].old_value.is_none());
+    assert_eq!(changes[0].new_value, Some(value_one.clone()));
+    let first_seq = changes[0].seq;
+
+    // Only read - nothing changed
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.get_data(&tx_id, &client_table_name, &key).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert!(data_base_manager.get_changes(first_seq, 100).unwrap().is_empty());
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key, &value_two).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let changes = data_base_manager.get_changes(first_seq, 100).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].seq > first_seq);
+    assert_eq!(changes[0].old_value, Some(value_one));
+    assert_eq!(changes[0].new_value, Some(value_two));
+}
+
+#[test]
+fn rollback_changes_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 8 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"David K\", \"age\": 45 }").unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key, &value).unwrap();
+    data_base_manager.tx_rollback(&tx_id).unwrap();
+
+    assert!(data_base_manager.get_changes(0, 100).unwrap().is_empty());
+    assert_eq!(data_base_manager.get_change_log().last_seq(), 0);
+}
+
+#[test]
+fn evicted_changes_test() {
+    let change_log = Arc::new(ChangeLog::new(2));
+    for id in 1..5 {
+        let key = JsonValue::from_str(&format!("{{\"id\": {} }}", id)).unwrap();
+        change_log.append(id, vec![(String::from(CLIENT_TABLE_NAME), key, None, None)]);
+    }
+    assert_eq!(change_log.first_seq(), 3);
+    assert_eq!(change_log.since(2, 100).unwrap().len(), 2);
+    match change_log.since(1, 100) {
+        Err(PersistenceError::ChangesEvicted(first_seq)) => assert_eq!(first_seq, 3),
+        other => panic!("Expected evicted changes, found {:?}", other),
+    }
+
+    // Stream tells about missed changes and continues from oldest kept record
+    let mut stream = ChangeEventStream::new(change_log.clone(), 0);
+    let mut buffer = [0; 4096];
+    let size = stream.read(&mut buffer).unwrap();
+    let event = String::from_utf8(buffer[..size].to_vec()).unwrap();
+    assert!(event.starts_with("id: 2\nevent: reset\n"));
+    assert!(event.contains("\"first_seq\":3"));
+    let size = stream.read(&mut buffer).unwrap();
+    let events = String::from_utf8(buffer[..size].to_vec()).unwrap();
+    assert!(events.starts_with("id: 3\nevent: change\n"));
+    assert!(events.contains("id: 4\nevent: change\n"));
+}
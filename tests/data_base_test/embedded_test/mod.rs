@@ -0,0 +1,75 @@
+extern crate log4rs;
+
+use nimdg::data_base::DataBaseManager;
+use nimdg::data_base::meta::TableDescriptionView;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+use nimdg::data_base::PersistenceError;
+
+use data_base_test::{IdKey, Client, CLIENT_TABLE_NAME};
+
+fn create_embedded_data_base() -> DataBaseManager {
+    let data_base_manager = DataBaseManager::new().unwrap();
+    data_base_manager.add_table(TableDescriptionView::new(CLIENT_TABLE_NAME,
+                                                 &[("id", "u64")],
+                                                 &[("full_name", "string"), ("age", "u64")]))
+        .unwrap();
+    data_base_manager
+}
+
+#[test]
+fn typed_table_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_embedded_data_base();
+    let clients = data_base_manager.typed_table::<IdKey, Client>(CLIENT_TABLE_NAME);
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    for id in 1..4 {
+        clients.put(&tx_id,
+                 &IdKey { id: id },
+                 &Client {
+                     full_name: format!("Client {}", id),
+                     age: 20 + id,
+                 })
+            .unwrap();
+    }
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let client = clients.get(&DEFAULT_TX_ID, &IdKey { id: 2 }).unwrap().unwrap();
+    assert_eq!(client.full_name, "Client 2");
+    assert_eq!(client.age, 22);
+    assert_eq!(clients.list(&DEFAULT_TX_ID, 0, 10).unwrap().len(), 3);
+
+    // Removal is not visible outside until commit
+    let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(clients.remove(&tx_id, &IdKey { id: 2 }).unwrap());
+    data_base_manager.tx_rollback(&tx_id).unwrap();
+    assert!(clients.get(&DEFAULT_TX_ID, &IdKey { id: 2 }).unwrap().is_some());
+
+    assert!(clients.remove(&DEFAULT_TX_ID, &IdKey { id: 2 }).unwrap());
+    assert!(clients.get(&DEFAULT_TX_ID, &IdKey { id: 2 }).unwrap().is_none());
+}
+
+#[derive(Serialize, Deserialize)]
+struct Order {
+    number: String,
+}
+
+#[test]
+fn typed_table_errors_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_embedded_data_base();
+    let orders = data_base_manager.typed_table::<IdKey, Order>("Order");
+    match orders.get(&DEFAULT_TX_ID, &IdKey { id: 1 }) {
+        Err(PersistenceError::TableNotFound(name)) => assert_eq!(name, "Order"),
+        other => panic!("Unexpected result {:?}", other.map(|order| order.map(|order| order.number))),
+    }
+
+    // Value type doesn't match description of table
+    let wrong_clients = data_base_manager.typed_table::<IdKey, Order>(CLIENT_TABLE_NAME);
+    match wrong_clients.put(&DEFAULT_TX_ID, &IdKey { id: 1 }, &Order { number: String::from("A-1") }) {
+        Err(PersistenceError::IoEntity(_)) => (),
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
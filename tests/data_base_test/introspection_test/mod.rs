@@ -0,0 +1,91 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use nimdg::data_base::{DataBaseManager, PersistenceError};
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::{add_client_table, client, create_test_data_base, key};
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn transaction_info_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(23)).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(2), &client(30)).unwrap();
+
+    let list = data_base_manager.get_transactions_list();
+    let list = list.as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].find("tx_id"), Some(&JsonValue::U64(tx_id)));
+    assert_eq!(list[0].find("lock_mode"),
+               Some(&JsonValue::String(String::from("optimistic"))));
+    assert_eq!(list[0].find("locked_keys"),
+               Some(&JsonValue::from_str("{\"Client\": 2}").unwrap()));
+    assert_eq!(list[0].find("waiting_for"), Some(&JsonValue::Null));
+    assert!(list[0].find("keys").is_none());
+
+    let tx_json = data_base_manager.get_transaction_json(&tx_id).unwrap();
+    let keys = tx_json.find("keys").and_then(|keys| keys.find(CLIENT_TABLE_NAME)).and_then(|keys| keys.as_array()).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&key(1)));
+    assert!(keys.contains(&key(2)));
+
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert!(data_base_manager.get_transaction_json(&tx_id).is_none());
+    assert_eq!(data_base_manager.get_transactions_list(), JsonValue::Array(vec![]));
+}
+
+#[test]
+fn kill_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(23)).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(1), &client(24)).unwrap();
+    data_base_manager.tx_kill(&tx_id).unwrap();
+
+    // Killed transaction is rolled back and its locks are released
+    assert!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).is_err());
+    assert!(data_base_manager.tx_kill(&tx_id).is_err());
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&other_tx_id, &client_table_name, &key(1), &client(25)).unwrap();
+    data_base_manager.tx_stop(&other_tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(1)).unwrap(),
+               Some(client(25)));
+}
+
+#[test]
+fn kill_prepared_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let dir = env::temp_dir().join("nimdg_prepared_kill");
+    fs::remove_dir_all(&dir).ok();
+    let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+    add_client_table(&data_base_manager);
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(1), &client(23)).unwrap();
+    data_base_manager.tx_prepare(&tx_id).unwrap();
+
+    // Prepared transaction waits for coordinator, kill doesn't touch it
+    match data_base_manager.tx_kill(&tx_id) {
+        Err(PersistenceError::TransactionPrepared(id)) => assert_eq!(id, tx_id),
+        res => panic!("Prepared tx must not be killed, result = {:?}", res),
+    }
+    data_base_manager.tx_commit_prepared(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+}
@@ -0,0 +1,145 @@
+extern crate log4rs;
+
+use nimdg::data_base::DataBaseManager;
+use nimdg::data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+
+use data_base_test::{client, create_test_data_base, key};
+use data_base_test::CLIENT_TABLE_NAME;
+
+fn prepare_data_base() -> DataBaseManager {
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(23)).unwrap();
+    data_base_manager
+}
+
+#[test]
+fn dirty_read_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = prepare_data_base();
+
+    // No level sees uncommitted value of other transaction
+    let write_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&write_tx_id, &client_table_name, &key(1), &client(24)).unwrap();
+
+    let read_tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::ReadCommitted).unwrap();
+    assert_eq!(data_base_manager.get_data(&read_tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+    data_base_manager.tx_stop(&read_tx_id).unwrap();
+
+    let read_tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::RepeatableRead).unwrap();
+    assert!(data_base_manager.get_data(&read_tx_id, &client_table_name, &key(1)).is_err());
+    data_base_manager.tx_rollback(&read_tx_id).unwrap();
+
+    data_base_manager.tx_rollback(&write_tx_id).unwrap();
+}
+
+#[test]
+fn read_committed_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = prepare_data_base();
+
+    let tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::ReadCommitted).unwrap();
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+
+    // Non-repeatable read: read didn't lock key, so other transaction changes it
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(24)).unwrap();
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(24)));
+
+    // Own changes are visible and locked until commit
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(1), &client(25)).unwrap();
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(25)));
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(data_base_manager.add_data(&other_tx_id, &client_table_name, &key(1), &client(26)).is_err());
+    data_base_manager.tx_rollback(&other_tx_id).unwrap();
+
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(1)).unwrap(),
+               Some(client(25)));
+}
+
+#[test]
+fn repeatable_read_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = prepare_data_base();
+
+    let tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::RepeatableRead).unwrap();
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+
+    // Read key is locked, so its value can't change until end of transaction
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(data_base_manager.add_data(&other_tx_id, &client_table_name, &key(1), &client(24)).is_err());
+    data_base_manager.tx_rollback(&other_tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+
+    // Phantom: new key inserted by other transaction appears in list
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               1);
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client(30)).unwrap();
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               2);
+
+    data_base_manager.tx_stop(&tx_id).unwrap();
+}
+
+#[test]
+fn serializable_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = prepare_data_base();
+
+    let tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::Serializable).unwrap();
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               1);
+
+    // No phantoms: insert of new key fails until end of scanning transaction
+    assert!(data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client(30)).is_err());
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               1);
+
+    // Update of existing key is not phantom, but key is locked by read
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(data_base_manager.add_data(&other_tx_id, &client_table_name, &key(1), &client(24)).is_err());
+    data_base_manager.tx_rollback(&other_tx_id).unwrap();
+
+    // Scanning transaction itself can insert
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(3), &client(40)).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client(30)).unwrap();
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 10).unwrap().len(),
+               3);
+}
+
+#[test]
+fn serializable_with_uncommitted_insert_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = prepare_data_base();
+
+    // Scan would miss key, that is inserted but not committed yet, so range lock fails
+    let insert_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&insert_tx_id, &client_table_name, &key(2), &client(30)).unwrap();
+
+    let tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::Serializable).unwrap();
+    assert!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).is_err());
+    assert!(data_base_manager.get_data(&tx_id, &client_table_name, &key(5)).is_err());
+
+    data_base_manager.tx_stop(&insert_tx_id).unwrap();
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               2);
+    data_base_manager.tx_stop(&tx_id).unwrap();
+}
@@ -0,0 +1,111 @@
+extern crate log4rs;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use nimdg::data_base::json::JsonValue;
+use nimdg::data_base::metrics::Operation;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::{client, create_test_data_base, key};
+use data_base_test::CLIENT_TABLE_NAME;
+
+// Value of metric without labels or with labels in braces
+fn metric(text: &str, name: &str) -> Option<String> {
+    text.lines()
+        .find(|line| line.starts_with(name) && line[name.len()..].starts_with(' '))
+        .map(|line| String::from(line[name.len() + 1..].trim()))
+}
+
+#[test]
+fn counters_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(23)).unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client(24)).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(1)).unwrap(),
+               Some(client(23)));
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &String::from("Unknown"), &key(1)).is_err());
+    assert_eq!(data_base_manager.get_list(DEFAULT_TX_ID, &client_table_name, 0, 10).unwrap().len(), 2);
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(data_base_manager.remove_data(&tx_id, &client_table_name, &key(2)).unwrap());
+    data_base_manager.tx_rollback(&tx_id).unwrap();
+    // Unknown transaction is not counted as failed
+    assert!(data_base_manager.tx_rollback(&tx_id).is_err());
+
+    let metrics = data_base_manager.get_metrics();
+    assert_eq!(metrics.operation_count(Operation::Put), 2);
+    assert_eq!(metrics.operation_count(Operation::Get), 2);
+    assert_eq!(metrics.operation_count(Operation::List), 1);
+    assert_eq!(metrics.operation_count(Operation::Remove), 1);
+
+    let stats = data_base_manager.get_tables_stats();
+    assert_eq!(stats.len(), 1);
+    assert_eq!((stats[0].name.as_str(), stats[0].entries), (CLIENT_TABLE_NAME, 2));
+    assert!(stats[0].bytes > 0);
+
+    let text = data_base_manager.get_metrics_text();
+    assert_eq!(metric(&text, "nimdg_table_entries{table=\"Client\"}"), Some(String::from("2")));
+    assert_eq!(metric(&text, "nimdg_table_bytes{table=\"Client\"}"), Some(stats[0].bytes.to_string()));
+    assert_eq!(metric(&text, "nimdg_operations_total{operation=\"get\"}"), Some(String::from("2")));
+    assert_eq!(metric(&text, "nimdg_operation_errors_total{operation=\"get\"}"), Some(String::from("1")));
+    assert_eq!(metric(&text, "nimdg_operation_errors_total{operation=\"put\"}"), Some(String::from("0")));
+    assert_eq!(metric(&text, "nimdg_operation_duration_seconds_count{operation=\"put\"}"), Some(String::from("2")));
+    assert_eq!(metric(&text, "nimdg_operation_duration_seconds_bucket{operation=\"put\",le=\"+Inf\"}"),
+               Some(String::from("2")));
+    // Implicit transactions of put, get and list, get of unknown table fails before start
+    assert_eq!(metric(&text, "nimdg_transactions_started_total"), Some(String::from("5")));
+    assert_eq!(metric(&text, "nimdg_transactions_committed_total"), Some(String::from("4")));
+    assert_eq!(metric(&text, "nimdg_transactions_rolled_back_total"), Some(String::from("1")));
+    assert_eq!(metric(&text, "nimdg_transactions_failed_total"), Some(String::from("0")));
+    assert_eq!(metric(&text, "nimdg_active_transactions"), Some(String::from("0")));
+    assert!(text.contains("# TYPE nimdg_operation_duration_seconds histogram\n"));
+}
+
+#[test]
+fn locks_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = Arc::new(create_test_data_base());
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(23)).unwrap();
+
+    let owner_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&owner_tx_id, &client_table_name, &key(1), &client(24)).unwrap();
+
+    // Optimistic transaction fails on locked key
+    let optimistic_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(data_base_manager.add_data(&optimistic_tx_id, &client_table_name, &key(1), &client(25)).is_err());
+    data_base_manager.tx_rollback(&optimistic_tx_id).unwrap();
+
+    // Pessimistic transaction waits till owner commits
+    let waiting_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    let waiting_data_base_manager = data_base_manager.clone();
+    let waiting = thread::spawn(move || {
+        waiting_data_base_manager.add_data(&waiting_tx_id, &String::from(CLIENT_TABLE_NAME), &key(1), &client(26)).unwrap();
+        waiting_data_base_manager.tx_stop(&waiting_tx_id).unwrap();
+    });
+    while data_base_manager.get_transaction_json(&waiting_tx_id)
+        .and_then(|tx| tx.find("waiting_for").cloned()) == Some(JsonValue::Null) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(metric(&data_base_manager.get_metrics_text(), "nimdg_active_transactions"),
+               Some(String::from("2")));
+    thread::sleep(Duration::from_millis(50));
+    data_base_manager.tx_stop(&owner_tx_id).unwrap();
+    waiting.join().unwrap();
+
+    let metrics = data_base_manager.get_metrics();
+    assert_eq!(metrics.lock_wait_count(), 1);
+    let text = data_base_manager.get_metrics_text();
+    assert_eq!(metric(&text, "nimdg_lock_failures_total"), Some(String::from("1")));
+    assert_eq!(metric(&text, "nimdg_lock_wait_seconds_count"), Some(String::from("1")));
+    assert_eq!(metric(&text, "nimdg_lock_wait_seconds_bucket{le=\"0.001\"}"), Some(String::from("0")));
+    assert!(metric(&text, "nimdg_lock_wait_seconds_sum").unwrap().parse::<f64>().unwrap() >= 0.04);
+    assert_eq!(metric(&text, "nimdg_active_transactions"), Some(String::from("0")));
+    assert_eq!(data_base_manager.get_tables_stats()[0].entries, 1);
+}
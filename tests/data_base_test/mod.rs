@@ -1,10 +1,24 @@
 
 use nimdg::data_base::DataBaseManager;
 use nimdg::data_base::meta::TableDescriptionView;
-use rustless::json::JsonValue;
+use nimdg::data_base::json::JsonValue;
 use std::str::FromStr;
 
+mod auto_commit_test;
+mod batch_test;
+mod bulk_test;
+mod changes_test;
+mod embedded_test;
+mod introspection_test;
+mod isolation_test;
+mod metrics_test;
+mod processor_test;
+mod query_test;
+mod read_only_test;
+mod savepoint_test;
+mod schema_test;
 mod transaction_test;
+mod two_phase_commit_test;
 
 #[derive(Serialize, Deserialize)]
 pub struct IdKey {
@@ -19,9 +33,23 @@ pub struct Client {
 
 pub static CLIENT_TABLE_NAME: &'static str = "Client";
 
+// Key and value of table Client as json
+pub fn key(id: u64) -> JsonValue {
+    JsonValue::from_str(&format!("{{\"id\": {} }}", id)).unwrap()
+}
+
+pub fn client(age: u64) -> JsonValue {
+    JsonValue::from_str(&format!("{{\"full_name\": \"John Doe\", \"age\": {} }}", age)).unwrap()
+}
+
 pub fn create_test_data_base() -> DataBaseManager {
-    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
     let data_base_manager: DataBaseManager = DataBaseManager::new().unwrap();
+    add_client_table(&data_base_manager);
+    data_base_manager
+}
+
+pub fn add_client_table(data_base_manager: &DataBaseManager) {
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
     let table_desc = JsonValue::from_str("{
         \"name\": \"Client\",
         \"key\": {
@@ -45,6 +73,4 @@ pub fn create_test_data_base() -> DataBaseManager {
     data_base_manager.add_table(table_desc_view);
     info!("Added table {}",
           data_base_manager.get_table_json(&client_table_name).unwrap());
-
-    data_base_manager
 }
@@ -0,0 +1,89 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+use nimdg::data_base::processor::EntryProcessor;
+use nimdg::data_base::meta::TableDescriptionView;
+
+use data_base_test::create_test_data_base;
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn invoke_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &key,
+                  &JsonValue::from_str("{\"full_name\": \"John\", \"age\": 20 }").unwrap())
+        .unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    // Implicit transaction
+    let increment = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"increment\", \"field\": \"age\", \"delta\": 2 }").unwrap()).unwrap();
+    let (value, applied) = data_base_manager.invoke(&DEFAULT_TX_ID, &client_table_name, &key, &increment).unwrap();
+    assert!(applied);
+    assert_eq!(value.find("age"), Some(&JsonValue::U64(22)));
+
+    // Explicit transaction sees own changes
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    let append = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"append\", \"field\": \"full_name\", \"value\": \" Doe\" }").unwrap()).unwrap();
+    data_base_manager.invoke(&tx_id, &client_table_name, &key, &append).unwrap();
+    let set_if_equal = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"set-if-equal\", \"field\": \"age\", \"expected\": 20, \"value\": 30 }")
+            .unwrap())
+        .unwrap();
+    let (_, applied) = data_base_manager.invoke(&tx_id, &client_table_name, &key, &set_if_equal).unwrap();
+    assert!(!applied);
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    let value = data_base_manager.get_data(&tx_id, &client_table_name, &key).unwrap().unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert_eq!(value,
+               JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 22 }").unwrap());
+
+    // Value must stay valid for table
+    let set_wrong_type = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"set-field\", \"field\": \"age\", \"value\": \"old\" }").unwrap())
+        .unwrap();
+    assert!(data_base_manager.invoke(&DEFAULT_TX_ID, &client_table_name, &key, &set_wrong_type).is_err());
+    let missing_key = JsonValue::from_str("{\"id\": 100 }").unwrap();
+    assert!(data_base_manager.invoke(&DEFAULT_TX_ID, &client_table_name, &missing_key, &increment).is_err());
+}
+
+#[test]
+fn set_if_equal_i64_test() {
+    let data_base_manager = create_test_data_base();
+    let table_desc = JsonValue::from_str("{
+        \"name\": \"Account\",
+        \"key\": { \"fields\": { \"id\": \"u64\" } },
+        \"value\": { \"fields\": { \"balance\": \"i64\" } }
+    }").unwrap();
+    data_base_manager.add_table(TableDescriptionView::from_json(&table_desc).unwrap()).unwrap();
+    let table_name = String::from("Account");
+
+    let key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &table_name, &key, &JsonValue::from_str("{\"balance\": 23 }").unwrap())
+        .unwrap();
+    let stored = data_base_manager.get_data(&DEFAULT_TX_ID, &table_name, &key).unwrap().unwrap();
+    assert_eq!(stored.find("balance"), Some(&JsonValue::I64(23)));
+
+    // Literal 23 of request is U64, but it is equal to I64 field
+    let set_if_equal = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"set-if-equal\", \"field\": \"balance\", \"expected\": 23, \"value\": -5 }")
+            .unwrap())
+        .unwrap();
+    let (value, applied) = data_base_manager.invoke(&DEFAULT_TX_ID, &table_name, &key, &set_if_equal).unwrap();
+    assert!(applied);
+    assert_eq!(value.find("balance").and_then(|balance| balance.as_i64()), Some(-5));
+
+    let set_if_equal = EntryProcessor::from_json(&JsonValue::from_str("{\"op\": \"set-if-equal\", \"field\": \"balance\", \"expected\": 23, \"value\": 0 }")
+            .unwrap())
+        .unwrap();
+    let (_, applied) = data_base_manager.invoke(&DEFAULT_TX_ID, &table_name, &key, &set_if_equal).unwrap();
+    assert!(!applied);
+}
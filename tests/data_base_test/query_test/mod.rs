@@ -0,0 +1,119 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+use nimdg::data_base::query::{ContinuousQuery, QueryEventType};
+
+use data_base_test::{client, create_test_data_base, key};
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn continuous_query_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let young_key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let old_key = JsonValue::from_str("{\"id\": 2 }").unwrap();
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &young_key,
+                  &JsonValue::from_str("{\"full_name\": \"Young\", \"age\": 20 }").unwrap())
+        .unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &old_key,
+                  &JsonValue::from_str("{\"full_name\": \"Old\", \"age\": 60 }").unwrap())
+        .unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let query = ContinuousQuery::from_json(&JsonValue::from_str("{
+        \"table\": \"Client\",
+        \"conditions\": [ { \"field\": \"age\", \"op\": \"ge\", \"value\": 30 } ]
+    }")
+            .unwrap())
+        .unwrap();
+    let (query_id, initial, receiver) = data_base_manager.register_query(query).unwrap();
+    info!("Initial entries of query {} = {:?}", query_id, initial);
+    assert_eq!(initial.len(), 1);
+    assert_eq!(initial[0].event_type, QueryEventType::Initial);
+    assert_eq!(initial[0].key, old_key);
+
+    // Young client becomes old - added to query
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &young_key,
+                  &JsonValue::from_str("{\"full_name\": \"Young\", \"age\": 40 }").unwrap())
+        .unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(event.event_type, QueryEventType::Added);
+    assert_eq!(event.key, young_key);
+
+    // Old client becomes young - removed from query
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &old_key,
+                  &JsonValue::from_str("{\"full_name\": \"Old\", \"age\": 25 }").unwrap())
+        .unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    let event = receiver.try_recv().unwrap();
+    assert_eq!(event.event_type, QueryEventType::Removed);
+    assert_eq!(event.key, old_key);
+    assert!(receiver.try_recv().is_err());
+
+    // Subscriber is gone - query is dropped on next change
+    drop(receiver);
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id,
+                  &client_table_name,
+                  &young_key,
+                  &JsonValue::from_str("{\"full_name\": \"Young\", \"age\": 41 }").unwrap())
+        .unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    assert!(!data_base_manager.get_query_manager().cancel(query_id));
+}
+
+#[test]
+fn continuous_query_concurrent_commits_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = Arc::new(create_test_data_base());
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(0)).unwrap();
+    let writer = {
+        let data_base_manager = data_base_manager.clone();
+        let client_table_name = client_table_name.clone();
+        thread::spawn(move || for age in 1..301 {
+            data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client(age)).unwrap();
+        })
+    };
+    let mut subscriptions = Vec::new();
+    for _ in 0..30 {
+        let query = ContinuousQuery::from_json(&JsonValue::from_str("{\"table\": \"Client\"}").unwrap()).unwrap();
+        let (_, initial, receiver) = data_base_manager.register_query(query).unwrap();
+        subscriptions.push((initial, receiver));
+        thread::yield_now();
+    }
+    writer.join().unwrap();
+
+    // Next event after snapshot is next commit, change is neither lost nor repeated
+    for (initial, receiver) in subscriptions {
+        assert_eq!(initial.len(), 1);
+        let age = initial[0].value.find("age").and_then(|age| age.as_u64()).unwrap();
+        match receiver.try_recv() {
+            Ok(event) => {
+                assert!(event.seq > initial[0].seq);
+                assert_eq!(event.value.find("age").and_then(|age| age.as_u64()), Some(age + 1));
+            }
+            Err(_) => assert_eq!(age, 300),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::PersistenceError;
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::create_test_data_base;
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn read_only_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23 }").unwrap();
+    let new_value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 24 }").unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key, &value).unwrap();
+
+    // Writer holds lock on key, read only transaction sees committed value and doesn't wait
+    let write_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&write_tx_id, &client_table_name, &key, &new_value).unwrap();
+
+    let read_tx_id = data_base_manager.tx_start(LockMode::ReadOnly).unwrap();
+    assert_eq!(data_base_manager.get_data(&read_tx_id, &client_table_name, &key).unwrap(),
+               Some(value.clone()));
+    assert_eq!(data_base_manager.get_list(read_tx_id, &client_table_name, 0, 10).unwrap().len(),
+               1);
+
+    // Read didn't take lock, so writer commits without conflict
+    data_base_manager.tx_stop(&write_tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&read_tx_id, &client_table_name, &key).unwrap(),
+               Some(new_value.clone()));
+    data_base_manager.tx_stop(&read_tx_id).unwrap();
+}
+
+#[test]
+fn read_only_rejects_changes_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+
+    let key = JsonValue::from_str("{\"id\": 2 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"David K\", \"age\": 45 }").unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key, &value).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::ReadOnly).unwrap();
+    match data_base_manager.add_data(&tx_id, &client_table_name, &key, &value) {
+        Err(PersistenceError::ReadOnlyTransaction(id)) => assert_eq!(id, tx_id),
+        res => panic!("Put in read only tx must be rejected, result = {:?}", res),
+    }
+    match data_base_manager.remove_data(&tx_id, &client_table_name, &key) {
+        Err(PersistenceError::ReadOnlyTransaction(id)) => assert_eq!(id, tx_id),
+        res => panic!("Remove in read only tx must be rejected, result = {:?}", res),
+    }
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap(),
+               Some(value));
+    assert!(data_base_manager.get_changes(0, 100).unwrap().iter().all(|change| change.tx_id != tx_id));
+}
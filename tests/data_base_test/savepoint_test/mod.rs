@@ -0,0 +1,136 @@
+extern crate log4rs;
+
+extern crate serde;
+extern crate serde_json;
+
+use nimdg::data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+
+use data_base_test::create_test_data_base;
+use data_base_test::{IdKey, Client, CLIENT_TABLE_NAME};
+
+fn key(id: u64) -> ::nimdg::data_base::json::JsonValue {
+    serde_json::to_value(IdKey { id: id })
+}
+
+fn client(name: &str, age: u64) -> ::nimdg::data_base::json::JsonValue {
+    serde_json::to_value(Client {
+        full_name: String::from(name),
+        age: age,
+    })
+}
+
+#[test]
+fn rollback_to_savepoint_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    let savepoint = String::from("first");
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(1), &client("John", 20)).unwrap();
+    data_base_manager.tx_savepoint(&tx_id, &savepoint).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(1), &client("John", 21)).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(2), &client("David", 30)).unwrap();
+    data_base_manager.tx_rollback_to_savepoint(&tx_id, &savepoint, false).unwrap();
+
+    assert_eq!(data_base_manager.get_data(&tx_id, &client_table_name, &key(1)).unwrap(),
+               Some(client("John", 20)));
+    assert!(data_base_manager.get_data(&tx_id, &client_table_name, &key(2)).unwrap().is_none());
+    assert!(data_base_manager.tx_rollback_to_savepoint(&tx_id, &String::from("unknown"), false).is_err());
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(1)).unwrap(),
+               Some(client("John", 20)));
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(2)).unwrap().is_none());
+}
+
+#[test]
+fn release_locks_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    let savepoint = String::from("before_update");
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(3), &client("Alice", 40)).unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.tx_savepoint(&tx_id, &savepoint).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(3), &client("Alice", 41)).unwrap();
+
+    // Key stays locked
+    data_base_manager.tx_rollback_to_savepoint(&tx_id, &savepoint, false).unwrap();
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(data_base_manager.add_data(&other_tx_id, &client_table_name, &key(3), &client("Alice", 42)).is_err());
+    data_base_manager.tx_rollback(&other_tx_id).unwrap();
+
+    // Key is unlocked
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(3), &client("Alice", 41)).unwrap();
+    data_base_manager.tx_rollback_to_savepoint(&tx_id, &savepoint, true).unwrap();
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&other_tx_id, &client_table_name, &key(3), &client("Alice", 42)).unwrap();
+    data_base_manager.tx_stop(&other_tx_id).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(3)).unwrap(),
+               Some(client("Alice", 42)));
+}
+
+#[test]
+fn atomic_batch_lock_failure_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(4), &client("Bob", 50)).unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(5), &client("Eve", 60)).unwrap();
+
+    let locking_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&locking_tx_id, &client_table_name, &key(5), &client("Eve", 61)).unwrap();
+
+    // Key 4 is locked first, then batch fails on key 5 and key 4 is released
+    let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    let entries = vec![(key(5), client("Eve", 62)), (key(4), client("Bob", 52))];
+    assert!(data_base_manager.put_all(&tx_id, &client_table_name, &entries, true).is_err());
+
+    let other_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.add_data(&other_tx_id, &client_table_name, &key(4), &client("Bob", 53)).unwrap();
+    data_base_manager.tx_stop(&other_tx_id).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+    data_base_manager.tx_stop(&locking_tx_id).unwrap();
+
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key(4)).unwrap(),
+               Some(client("Bob", 53)));
+}
+
+#[test]
+fn release_range_locks_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let data_base_manager = create_test_data_base();
+    let savepoint = String::from("before_scan");
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(1), &client("John", 20)).unwrap();
+
+    // Scan after savepoint takes range lock, rollback releases it and other transaction can insert
+    let tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::Serializable).unwrap();
+    data_base_manager.tx_savepoint(&tx_id, &savepoint).unwrap();
+    assert_eq!(data_base_manager.get_list(tx_id, &client_table_name, 0, 10).unwrap().len(),
+               1);
+    assert!(data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client("David", 30)).is_err());
+    data_base_manager.tx_rollback_to_savepoint(&tx_id, &savepoint, true).unwrap();
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key(2), &client("David", 30)).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    // Insert after savepoint takes range lock, rollback releases it and serializable transaction can scan
+    let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    data_base_manager.tx_savepoint(&tx_id, &savepoint).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key(3), &client("Alice", 40)).unwrap();
+    let scan_tx_id = data_base_manager.tx_start_with_isolation(LockMode::Optimistic, IsolationLevel::Serializable).unwrap();
+    assert!(data_base_manager.get_list(scan_tx_id, &client_table_name, 0, 10).is_err());
+    data_base_manager.tx_rollback_to_savepoint(&tx_id, &savepoint, true).unwrap();
+    assert_eq!(data_base_manager.get_list(scan_tx_id, &client_table_name, 0, 10).unwrap().len(),
+               2);
+    data_base_manager.tx_stop(&scan_tx_id).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+}
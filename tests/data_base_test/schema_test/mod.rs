@@ -0,0 +1,41 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+
+use data_base_test::create_test_data_base;
+use data_base_test::CLIENT_TABLE_NAME;
+
+#[test]
+fn table_schema_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let schema = data_base_manager.get_table_schema(&String::from(CLIENT_TABLE_NAME)).unwrap();
+    assert_eq!(schema.find("title"), Some(&JsonValue::String(String::from(CLIENT_TABLE_NAME))));
+
+    let properties = schema.find("properties").unwrap();
+    assert_eq!(properties.find("key").unwrap(),
+               &JsonValue::from_str("{
+                    \"type\": \"object\",
+                    \"properties\": {\"id\": {\"type\": \"integer\", \"minimum\": 0}},
+                    \"required\": [\"id\"],
+                    \"additionalProperties\": false
+               }")
+                   .unwrap());
+    assert_eq!(properties.find("value").unwrap(),
+               &JsonValue::from_str("{
+                    \"type\": \"object\",
+                    \"properties\": {
+                        \"age\": {\"type\": \"integer\", \"minimum\": 0},
+                        \"full_name\": {\"type\": \"string\"}
+                    },
+                    \"required\": [\"age\", \"full_name\"],
+                    \"additionalProperties\": false
+               }")
+                   .unwrap());
+
+    assert!(data_base_manager.get_table_schema(&String::from("Order")).is_none());
+    let schemas = data_base_manager.get_tables_schemas();
+    assert_eq!(schemas.keys().collect::<Vec<&String>>(), vec![&String::from(CLIENT_TABLE_NAME)]);
+}
@@ -4,9 +4,10 @@ extern crate log4rs;
 extern crate serde;
 extern crate serde_json;
 
-use nimdg::data_base::DataBaseManager;
-use rustless::json::JsonValue;
+use nimdg::data_base::{DataBaseManager, PersistenceError};
+use nimdg::data_base::json::JsonValue;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use nimdg::data_base::meta::TableDescriptionView;
 use nimdg::data_base::transaction::LockMode;
 
@@ -113,3 +114,33 @@ fn optimistic_tx_fail_test() {
     info!("optimistic fail result = {:?}", fail_result);
     assert!(!fail_result.is_ok());
 }
+
+#[test]
+fn pessimistic_lock_timeout_test() {
+    let client_table_name: String = String::from("Client");
+    let data_base_manager = create_test_data_base();
+    data_base_manager.set_lock_wait(Some(Duration::from_millis(100)));
+    let key_json = serde_json::to_value(IdKey { id: 7 });
+    let client = Client {
+        full_name: String::from("John Waiting"),
+        age: 40,
+    };
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key_json, &serde_json::to_value(client)).unwrap();
+    data_base_manager.tx_stop(&tx_id).unwrap();
+
+    let holder_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(data_base_manager.get_data(&holder_tx_id, &client_table_name, &key_json).unwrap().is_some());
+    let waiting_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    let started = Instant::now();
+    match data_base_manager.get_data(&waiting_tx_id, &client_table_name, &key_json) {
+        Err(PersistenceError::LockTimeout(tx_id, lock_tx_id)) => {
+            assert_eq!(tx_id, waiting_tx_id);
+            assert_eq!(lock_tx_id, holder_tx_id);
+        }
+        other => panic!("Expected lock timeout, found {:?}", other),
+    }
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    data_base_manager.tx_rollback(&waiting_tx_id).unwrap();
+    data_base_manager.tx_stop(&holder_tx_id).unwrap();
+}
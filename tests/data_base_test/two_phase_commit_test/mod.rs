@@ -0,0 +1,139 @@
+extern crate log4rs;
+
+use nimdg::data_base::json::JsonValue;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use nimdg::data_base::{DataBaseManager, PersistenceError};
+use nimdg::data_base::transaction::{LockMode, DEFAULT_TX_ID};
+
+use data_base_test::add_client_table;
+use data_base_test::CLIENT_TABLE_NAME;
+
+fn prepared_log_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("nimdg_prepared_{}", name));
+    fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+fn in_doubt(tx_id: u64) -> JsonValue {
+    JsonValue::from_str(&format!("{{\"tx_id\": {}, \"state\": \"in_doubt\"}}", tx_id)).unwrap()
+}
+
+#[test]
+fn prepare_and_commit_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let dir = prepared_log_dir("commit");
+    let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+    add_client_table(&data_base_manager);
+
+    let key = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23 }").unwrap();
+
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    data_base_manager.add_data(&tx_id, &client_table_name, &key, &value).unwrap();
+    data_base_manager.tx_prepare(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_prepared_list(),
+               JsonValue::from_str(&format!("[{{\"tx_id\": {}, \"state\": \"prepared\"}}]", tx_id)).unwrap());
+
+    // Prepared transaction waits for coordinator
+    match data_base_manager.add_data(&tx_id, &client_table_name, &key, &value) {
+        Err(PersistenceError::TransactionPrepared(id)) => assert_eq!(id, tx_id),
+        res => panic!("Change of prepared tx must be rejected, result = {:?}", res),
+    }
+    assert!(data_base_manager.tx_stop(&tx_id).is_err());
+    assert!(data_base_manager.tx_rollback(&tx_id).is_err());
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap().is_none());
+
+    data_base_manager.tx_commit_prepared(&tx_id).unwrap();
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key).unwrap(),
+               Some(value));
+    assert_eq!(data_base_manager.get_prepared_list(), JsonValue::Array(vec![]));
+    assert!(fs::read_dir(&dir).unwrap().all(|entry| entry.unwrap().path().extension().is_none()));
+
+    // Not prepared transaction can't be committed as prepared
+    let tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(data_base_manager.tx_commit_prepared(&tx_id).is_err());
+    data_base_manager.tx_stop(&tx_id).unwrap();
+}
+
+#[test]
+fn recovery_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let client_table_name: String = String::from(CLIENT_TABLE_NAME);
+    let dir = prepared_log_dir("recovery");
+    let key_one = JsonValue::from_str("{\"id\": 1 }").unwrap();
+    let key_two = JsonValue::from_str("{\"id\": 2 }").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"David K\", \"age\": 45 }").unwrap();
+
+    let (commit_tx_id, rollback_tx_id) = {
+        let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+        add_client_table(&data_base_manager);
+        let commit_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+        data_base_manager.add_data(&commit_tx_id, &client_table_name, &key_one, &value).unwrap();
+        data_base_manager.tx_prepare(&commit_tx_id).unwrap();
+        let rollback_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+        data_base_manager.add_data(&rollback_tx_id, &client_table_name, &key_two, &value).unwrap();
+        data_base_manager.tx_prepare(&rollback_tx_id).unwrap();
+        (commit_tx_id, rollback_tx_id)
+    };
+
+    // Restart: prepared transactions are in doubt
+    let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+    assert_eq!(data_base_manager.get_prepared_list(),
+               JsonValue::Array(vec![in_doubt(commit_tx_id), in_doubt(rollback_tx_id)]));
+    let new_tx_id = data_base_manager.tx_start(LockMode::Pessimistic).unwrap();
+    assert!(new_tx_id > rollback_tx_id);
+    data_base_manager.tx_stop(&new_tx_id).unwrap();
+
+    // Table is not created yet, so transaction stays in doubt
+    assert!(data_base_manager.tx_commit_prepared(&commit_tx_id).is_err());
+    assert_eq!(data_base_manager.get_prepared_list(),
+               JsonValue::Array(vec![in_doubt(commit_tx_id), in_doubt(rollback_tx_id)]));
+
+    add_client_table(&data_base_manager);
+    // Keys of in doubt transactions are locked again after restart
+    let other_value = JsonValue::from_str("{\"full_name\": \"Other\", \"age\": 30 }").unwrap();
+    match data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key_one, &other_value) {
+        Err(PersistenceError::LockFailed(_)) => (),
+        res => panic!("Key of in doubt tx must be locked, result = {:?}", res),
+    }
+    assert!(data_base_manager.remove_data(&DEFAULT_TX_ID, &client_table_name, &key_two).is_err());
+
+    data_base_manager.tx_commit_prepared(&commit_tx_id).unwrap();
+    data_base_manager.tx_rollback_prepared(&rollback_tx_id).unwrap();
+    assert_eq!(data_base_manager.get_prepared_list(), JsonValue::Array(vec![]));
+    assert_eq!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key_one).unwrap(),
+               Some(value));
+    assert!(data_base_manager.get_data(&DEFAULT_TX_ID, &client_table_name, &key_two).unwrap().is_none());
+
+    // Keys are unlocked by decision of coordinator
+    data_base_manager.add_data(&DEFAULT_TX_ID, &client_table_name, &key_two, &other_value).unwrap();
+
+    let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+    assert_eq!(data_base_manager.get_prepared_list(), JsonValue::Array(vec![]));
+}
+
+#[test]
+fn tx_id_after_restart_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let dir = prepared_log_dir("tx_id");
+    let tx_id = {
+        let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+        let tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+        data_base_manager.tx_stop(&tx_id).unwrap();
+        tx_id
+    };
+
+    // Ids, that could be given before restart, are not reused
+    let data_base_manager = DataBaseManager::with_prepared_log_dir(&dir).unwrap();
+    let new_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert!(new_tx_id > tx_id);
+    let next_tx_id = data_base_manager.tx_start(LockMode::Optimistic).unwrap();
+    assert_eq!(next_tx_id, new_tx_id + 1);
+}
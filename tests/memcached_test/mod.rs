@@ -0,0 +1,146 @@
+extern crate log4rs;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use nimdg::data_base::DataBaseManager;
+use nimdg::memcached::{MemcachedServer, DEFAULT_MEMCACHED_TABLE_NAME};
+
+struct MemcachedClient {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl MemcachedClient {
+    fn send(&mut self, request: &str) {
+        self.writer.write_all(request.as_bytes()).unwrap();
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        line
+    }
+
+    // Response of single line
+    fn command(&mut self, request: &str) -> String {
+        self.send(request);
+        self.read_line()
+    }
+
+    // Response of get and gets till END
+    fn get(&mut self, request: &str) -> Vec<String> {
+        self.send(request);
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line();
+            if line == "END\r\n" {
+                return lines;
+            }
+            lines.push(line);
+        }
+    }
+}
+
+fn start_server() -> MemcachedClient {
+    let data_base_manager = Arc::new(DataBaseManager::new().unwrap());
+    let server = MemcachedServer::bind("127.0.0.1:0", data_base_manager, DEFAULT_MEMCACHED_TABLE_NAME).unwrap();
+    let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    server.spawn();
+    MemcachedClient {
+        writer: stream.try_clone().unwrap(),
+        reader: BufReader::new(stream),
+    }
+}
+
+#[test]
+fn memcached_storage_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert!(client.get("get name\r\n").is_empty());
+    assert_eq!(client.command("set name 5 0 4\r\nJohn\r\n"), "STORED\r\n");
+    assert_eq!(client.get("get name other\r\n"),
+               vec![String::from("VALUE name 5 4\r\n"), String::from("John\r\n")]);
+    assert_eq!(client.command("add name 0 0 5\r\nDavid\r\n"), "NOT_STORED\r\n");
+    assert_eq!(client.command("replace other 0 0 5\r\nDavid\r\n"), "NOT_STORED\r\n");
+    assert_eq!(client.command("replace name 0 0 5\r\nDavid\r\n"), "STORED\r\n");
+    assert_eq!(client.command("add other 0 0 3\r\nBob\r\n"), "STORED\r\n");
+
+    assert_eq!(client.command("delete other\r\n"), "DELETED\r\n");
+    assert_eq!(client.command("delete other\r\n"), "NOT_FOUND\r\n");
+    // Nothing is answered for noreply, so next response is of version
+    client.send("set quiet 0 0 1 noreply\r\nq\r\n");
+    assert!(client.command("version\r\n").starts_with("VERSION"));
+    assert_eq!(client.command("unknown\r\n"), "ERROR\r\n");
+}
+
+#[test]
+fn memcached_cas_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command("cas name 0 0 4 1\r\nJohn\r\n"), "NOT_FOUND\r\n");
+    assert_eq!(client.command("set name 0 0 4\r\nJohn\r\n"), "STORED\r\n");
+    let lines = client.get("gets name\r\n");
+    let version = lines[0].trim().split(' ').last().unwrap().to_string();
+
+    assert_eq!(client.command(&format!("cas name 0 0 5 {}\r\nDavid\r\n", version)),
+               "STORED\r\n");
+    // Version was changed by previous cas
+    assert_eq!(client.command(&format!("cas name 0 0 3 {}\r\nBob\r\n", version)),
+               "EXISTS\r\n");
+    let lines = client.get("gets name\r\n");
+    assert!(lines[0].starts_with("VALUE name 0 5 "));
+    assert_eq!(lines[1], "David\r\n");
+
+    // Token of removed entity doesn't match new entity with the same key
+    assert_eq!(client.command("set city 0 0 5\r\nParis\r\n"), "STORED\r\n");
+    let lines = client.get("gets city\r\n");
+    let version = lines[0].trim().split(' ').last().unwrap().to_string();
+    assert_eq!(client.command("delete city\r\n"), "DELETED\r\n");
+    assert_eq!(client.command("set city 0 0 5\r\nParis\r\n"), "STORED\r\n");
+    assert_eq!(client.command(&format!("cas city 0 0 4 {}\r\nRome\r\n", version)),
+               "EXISTS\r\n");
+}
+
+#[test]
+fn memcached_incr_decr_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command("incr counter 1\r\n"), "NOT_FOUND\r\n");
+    assert_eq!(client.command("set counter 0 0 2\r\n10\r\n"), "STORED\r\n");
+    assert_eq!(client.command("incr counter 5\r\n"), "15\r\n");
+    assert_eq!(client.command("decr counter 20\r\n"), "0\r\n");
+    assert_eq!(client.command("set name 0 0 4\r\nJohn\r\n"), "STORED\r\n");
+    assert!(client.command("incr name 1\r\n").starts_with("CLIENT_ERROR"));
+}
+
+#[test]
+fn memcached_expiration_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command("set session 0 1 1\r\n1\r\n"), "STORED\r\n");
+    assert_eq!(client.command("set user 0 1 1\r\n2\r\n"), "STORED\r\n");
+    assert_eq!(client.command("set expired 0 -1 1\r\n3\r\n"), "STORED\r\n");
+    assert!(client.get("get expired\r\n").is_empty());
+    assert_eq!(client.command("touch user 100\r\n"), "TOUCHED\r\n");
+    assert_eq!(client.command("touch missing 100\r\n"), "NOT_FOUND\r\n");
+    assert_eq!(client.command("set huge 0 9223372036854775807 1\r\n"), "CLIENT_ERROR bad command line format\r\n");
+    assert_eq!(client.command("touch user 9223372036854775807\r\n"), "CLIENT_ERROR invalid exptime argument\r\n");
+
+    thread::sleep(Duration::from_millis(1100));
+    assert!(client.get("get session\r\n").is_empty());
+    assert_eq!(client.get("get user\r\n").len(), 2);
+
+    // Server closes connection by quit without response
+    client.send("quit\r\n");
+    let mut rest = String::new();
+    client.reader.read_to_string(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
@@ -0,0 +1,203 @@
+extern crate log4rs;
+
+extern crate serde;
+extern crate serde_json;
+
+use std::sync::Arc;
+
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::DataBaseManager;
+use nimdg::data_base::transaction::{IsolationLevel, LockMode, DEFAULT_TX_ID};
+use nimdg::protocol;
+use nimdg::protocol::client::BinaryClient;
+use nimdg::protocol::server::BinaryServer;
+
+use data_base_test::create_test_data_base;
+use data_base_test::{IdKey, Client, CLIENT_TABLE_NAME};
+use add_times_table;
+
+// Operations of scenarios, that are run the same way over data base manager and binary protocol
+pub trait Transport {
+    fn tx_start(&mut self, lock_mode: LockMode) -> Result<u64, String>;
+    fn tx_commit(&mut self, tx_id: u64) -> Result<(), String>;
+    fn tx_rollback(&mut self, tx_id: u64) -> Result<(), String>;
+    fn get(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<Option<JsonValue>, String>;
+    fn put(&mut self, tx_id: u64, table_name: &str, key: &JsonValue, value: &JsonValue) -> Result<(), String>;
+    fn remove(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<bool, String>;
+    fn put_all(&mut self, tx_id: u64, table_name: &str, entries: &Vec<(JsonValue, JsonValue)>, atomic: bool) -> Result<Vec<bool>, String>;
+    fn get_all(&mut self, tx_id: u64, table_name: &str, keys: &Vec<JsonValue>) -> Result<Vec<Option<JsonValue>>, String>;
+    fn scan(&mut self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<JsonValue>, String>;
+}
+
+struct DirectTransport {
+    data_base_manager: DataBaseManager,
+}
+
+impl Transport for DirectTransport {
+    fn tx_start(&mut self, lock_mode: LockMode) -> Result<u64, String> {
+        self.data_base_manager.tx_start_with_isolation(lock_mode, IsolationLevel::RepeatableRead).map_err(|err| err.to_string())
+    }
+
+    fn tx_commit(&mut self, tx_id: u64) -> Result<(), String> {
+        self.data_base_manager.tx_stop(&tx_id).map_err(|err| err.to_string())
+    }
+
+    fn tx_rollback(&mut self, tx_id: u64) -> Result<(), String> {
+        self.data_base_manager.tx_rollback(&tx_id).map_err(|err| err.to_string())
+    }
+
+    fn get(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<Option<JsonValue>, String> {
+        self.data_base_manager.get_data(&tx_id, &String::from(table_name), key).map_err(|err| err.to_string())
+    }
+
+    fn put(&mut self, tx_id: u64, table_name: &str, key: &JsonValue, value: &JsonValue) -> Result<(), String> {
+        self.data_base_manager.add_data(&tx_id, &String::from(table_name), key, value).map_err(|err| err.to_string())
+    }
+
+    fn remove(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<bool, String> {
+        self.data_base_manager.remove_data(&tx_id, &String::from(table_name), key).map_err(|err| err.to_string())
+    }
+
+    fn put_all(&mut self, tx_id: u64, table_name: &str, entries: &Vec<(JsonValue, JsonValue)>, atomic: bool) -> Result<Vec<bool>, String> {
+        self.data_base_manager
+            .put_all(&tx_id, &String::from(table_name), entries, atomic)
+            .map(|results| results.iter().map(|result| result.is_ok()).collect())
+            .map_err(|err| err.to_string())
+    }
+
+    fn get_all(&mut self, tx_id: u64, table_name: &str, keys: &Vec<JsonValue>) -> Result<Vec<Option<JsonValue>>, String> {
+        let results = try!(self.data_base_manager
+            .get_all(&tx_id, &String::from(table_name), keys, true)
+            .map_err(|err| err.to_string()));
+        results.into_iter().map(|result| result.map_err(|err| err.to_string())).collect()
+    }
+
+    fn scan(&mut self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<JsonValue>, String> {
+        self.data_base_manager.get_list(tx_id, &String::from(table_name), start, count).map_err(|err| err.to_string())
+    }
+}
+
+impl Transport for BinaryClient {
+    fn tx_start(&mut self, lock_mode: LockMode) -> Result<u64, String> {
+        BinaryClient::tx_start(self, lock_mode, IsolationLevel::RepeatableRead)
+    }
+
+    fn tx_commit(&mut self, tx_id: u64) -> Result<(), String> {
+        BinaryClient::tx_commit(self, tx_id)
+    }
+
+    fn tx_rollback(&mut self, tx_id: u64) -> Result<(), String> {
+        BinaryClient::tx_rollback(self, tx_id)
+    }
+
+    fn get(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<Option<JsonValue>, String> {
+        BinaryClient::get(self, tx_id, table_name, key)
+    }
+
+    fn put(&mut self, tx_id: u64, table_name: &str, key: &JsonValue, value: &JsonValue) -> Result<(), String> {
+        BinaryClient::put(self, tx_id, table_name, key, value)
+    }
+
+    fn remove(&mut self, tx_id: u64, table_name: &str, key: &JsonValue) -> Result<bool, String> {
+        BinaryClient::remove(self, tx_id, table_name, key)
+    }
+
+    fn put_all(&mut self, tx_id: u64, table_name: &str, entries: &Vec<(JsonValue, JsonValue)>, atomic: bool) -> Result<Vec<bool>, String> {
+        BinaryClient::put_all(self, tx_id, table_name, entries, atomic)
+            .map(|results| results.iter().map(|result| result.is_ok()).collect())
+    }
+
+    fn get_all(&mut self, tx_id: u64, table_name: &str, keys: &Vec<JsonValue>) -> Result<Vec<Option<JsonValue>>, String> {
+        let results = try!(BinaryClient::get_all(self, tx_id, table_name, keys, true));
+        results.into_iter().collect()
+    }
+
+    fn scan(&mut self, tx_id: u64, table_name: &str, start: u32, count: u32) -> Result<Vec<JsonValue>, String> {
+        BinaryClient::scan(self, tx_id, table_name, start, count)
+    }
+}
+
+// Data base of scenarios contains tables of tests.rs
+fn create_transport_data_base() -> DataBaseManager {
+    let data_base_manager = create_test_data_base();
+    add_times_table(&data_base_manager);
+    data_base_manager
+}
+
+fn start_server() -> BinaryClient {
+    let data_base_manager = Arc::new(create_transport_data_base());
+    let server = BinaryServer::bind("127.0.0.1:0", data_base_manager).unwrap();
+    let address = server.local_addr().unwrap();
+    server.spawn();
+    BinaryClient::connect(address).unwrap()
+}
+
+// Every scenario is run on own data base over both transports
+pub fn run_over_both_transports(scenario: fn(&mut Transport)) {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    scenario(&mut DirectTransport { data_base_manager: create_transport_data_base() });
+    scenario(&mut start_server());
+}
+
+fn key(id: u64) -> JsonValue {
+    serde_json::to_value(IdKey { id: id })
+}
+
+fn client(name: &str, age: u64) -> JsonValue {
+    serde_json::to_value(Client {
+        full_name: String::from(name),
+        age: age,
+    })
+}
+
+fn batch_scenario(transport: &mut Transport) {
+    let wrong_value = JsonValue::from_str("{\"age\": 1 }").unwrap();
+    let entries = vec![(key(1), client("John", 20)), (key(2), wrong_value), (key(3), client("David", 30))];
+
+    assert!(transport.put_all(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &entries, true).is_err());
+    assert_eq!(transport.put_all(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &entries, false).unwrap(),
+               vec![true, false, true]);
+    assert_eq!(transport.get_all(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &vec![key(1), key(2), key(3)]).unwrap(),
+               vec![Some(client("John", 20)), None, Some(client("David", 30))]);
+
+    let tx_id = transport.tx_start(LockMode::Optimistic).unwrap();
+    assert!(transport.remove(tx_id, CLIENT_TABLE_NAME, &key(3)).unwrap());
+    transport.tx_rollback(tx_id).unwrap();
+    assert_eq!(transport.get(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key(3)).unwrap(),
+               Some(client("David", 30)));
+}
+
+#[test]
+fn batch_compatibility_test() {
+    run_over_both_transports(batch_scenario);
+}
+
+#[test]
+fn pipelining_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut binary_client = start_server();
+    let mut request_ids = Vec::new();
+    for i in 1..11 {
+        let request = protocol::PutRequest {
+            tx_id: DEFAULT_TX_ID,
+            table_name: String::from(CLIENT_TABLE_NAME),
+            key: key(i).to_string(),
+            value: client(&format!("TestName{}", i), 20 + i).to_string(),
+        };
+        request_ids.push(binary_client.send(protocol::OP_PUT, &request).unwrap());
+    }
+    // Failed request does not break following ones
+    let wrong_request = protocol::TxRequest { tx_id: 12345 };
+    request_ids.push(binary_client.send(protocol::OP_TX_COMMIT, &wrong_request).unwrap());
+
+    for (index, request_id) in request_ids.iter().enumerate() {
+        let (response_id, result) = binary_client.receive().unwrap();
+        assert_eq!(response_id, *request_id);
+        assert_eq!(result.is_ok(), index < 10);
+    }
+    assert_eq!(binary_client.scan(DEFAULT_TX_ID, CLIENT_TABLE_NAME, 0, 100).unwrap().len(),
+               10);
+}
@@ -0,0 +1,162 @@
+extern crate log4rs;
+
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use nimdg::data_base::DataBaseManager;
+use nimdg::resp::{Reply, read_reply};
+use nimdg::resp::server::{RespServer, DEFAULT_RESP_TABLE_NAME};
+
+struct RespClient {
+    writer: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl RespClient {
+    fn command(&mut self, args: &[&str]) -> Reply {
+        let command = Reply::Array(args.iter().map(|arg| Reply::Bulk(Some(String::from(*arg)))).collect());
+        command.write(&mut self.writer).unwrap();
+        read_reply(&mut self.reader).unwrap().unwrap()
+    }
+}
+
+fn start_server() -> RespClient {
+    let data_base_manager = Arc::new(DataBaseManager::new().unwrap());
+    let server = RespServer::bind("127.0.0.1:0", data_base_manager, DEFAULT_RESP_TABLE_NAME).unwrap();
+    let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    server.spawn();
+    RespClient {
+        writer: stream.try_clone().unwrap(),
+        reader: BufReader::new(stream),
+    }
+}
+
+fn bulk(value: &str) -> Reply {
+    Reply::Bulk(Some(String::from(value)))
+}
+
+#[test]
+fn resp_commands_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command(&["PING"]), Reply::Status(String::from("PONG")));
+    assert_eq!(client.command(&["GET", "name"]), Reply::nil());
+    assert_eq!(client.command(&["SET", "name", "{\"first\": \"John\"}"]), Reply::ok());
+    assert_eq!(client.command(&["GET", "name"]), bulk("{\"first\": \"John\"}"));
+    assert_eq!(client.command(&["SET", "name", "David", "NX"]), Reply::nil());
+    assert_eq!(client.command(&["SET", "other", "David", "XX"]), Reply::nil());
+    assert_eq!(client.command(&["EXISTS", "name", "other"]), Reply::Integer(1));
+
+    assert_eq!(client.command(&["INCR", "counter"]), Reply::Integer(1));
+    assert_eq!(client.command(&["INCR", "counter"]), Reply::Integer(2));
+    assert!(match client.command(&["INCR", "name"]) {
+        Reply::Error(_) => true,
+        _ => false,
+    });
+
+    assert_eq!(client.command(&["DEL", "name", "other"]), Reply::Integer(1));
+    assert_eq!(client.command(&["GET", "name"]), Reply::nil());
+    assert!(match client.command(&["UNKNOWN"]) {
+        Reply::Error(_) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn resp_expire_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command(&["SET", "session", "1", "PX", "100"]), Reply::ok());
+    assert_eq!(client.command(&["SET", "user", "2"]), Reply::ok());
+    assert_eq!(client.command(&["TTL", "user"]), Reply::Integer(-1));
+    assert_eq!(client.command(&["EXPIRE", "user", "100"]), Reply::Integer(1));
+    assert_eq!(client.command(&["TTL", "user"]), Reply::Integer(100));
+    assert_eq!(client.command(&["EXPIRE", "missing", "100"]), Reply::Integer(0));
+    assert_eq!(client.command(&["EXPIRE", "user", "9223372036854775807"]),
+               Reply::error("invalid expire time in 'expire' command"));
+    assert_eq!(client.command(&["SET", "user", "2", "EX", "18446744073709551615"]),
+               Reply::error("invalid expire time in 'set' command"));
+    assert_eq!(client.command(&["TTL", "user"]), Reply::Integer(100));
+
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(client.command(&["GET", "session"]), Reply::nil());
+    assert_eq!(client.command(&["TTL", "session"]), Reply::Integer(-2));
+    assert_eq!(client.command(&["GET", "user"]), bulk("2"));
+}
+
+#[test]
+fn resp_multi_exec_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    assert_eq!(client.command(&["MULTI"]), Reply::ok());
+    assert_eq!(client.command(&["SET", "a", "1"]), Reply::Status(String::from("QUEUED")));
+    assert_eq!(client.command(&["INCR", "a"]), Reply::Status(String::from("QUEUED")));
+    assert_eq!(client.command(&["GET", "a"]), Reply::Status(String::from("QUEUED")));
+    assert_eq!(client.command(&["EXEC"]),
+               Reply::Array(vec![Reply::ok(), Reply::Integer(2), bulk("2")]));
+
+    assert_eq!(client.command(&["MULTI"]), Reply::ok());
+    assert_eq!(client.command(&["SET", "a", "10"]), Reply::Status(String::from("QUEUED")));
+    assert_eq!(client.command(&["DISCARD"]), Reply::ok());
+    assert_eq!(client.command(&["GET", "a"]), bulk("2"));
+
+    // Wrong command in MULTI discards whole transaction
+    assert_eq!(client.command(&["MULTI"]), Reply::ok());
+    assert_eq!(client.command(&["SET", "a", "20"]), Reply::Status(String::from("QUEUED")));
+    assert!(match client.command(&["GET"]) {
+        Reply::Error(_) => true,
+        _ => false,
+    });
+    assert!(match client.command(&["EXEC"]) {
+        Reply::Error(ref error) => error.starts_with("EXECABORT"),
+        _ => false,
+    });
+    assert_eq!(client.command(&["GET", "a"]), bulk("2"));
+    assert!(match client.command(&["EXEC"]) {
+        Reply::Error(_) => true,
+        _ => false,
+    });
+}
+
+#[test]
+fn resp_scan_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let mut client = start_server();
+    for i in 0..25 {
+        assert_eq!(client.command(&["SET", &format!("user:{}", i), "1"]), Reply::ok());
+        assert_eq!(client.command(&["SET", &format!("order:{}", i), "1"]), Reply::ok());
+    }
+
+    let mut cursor = String::from("0");
+    let mut keys = Vec::new();
+    loop {
+        match client.command(&["SCAN", &cursor, "MATCH", "user:*", "COUNT", "7"]) {
+            Reply::Array(ref items) if items.len() == 2 => {
+                cursor = match items[0] {
+                    Reply::Bulk(Some(ref cursor)) => cursor.clone(),
+                    ref reply => panic!("Wrong cursor {:?}", reply),
+                };
+                match items[1] {
+                    Reply::Array(ref found) => keys.extend(found.iter().cloned()),
+                    ref reply => panic!("Wrong keys {:?}", reply),
+                }
+            }
+            reply => panic!("Wrong scan reply {:?}", reply),
+        }
+        if cursor == "0" {
+            break;
+        }
+    }
+    assert_eq!(keys.len(), 25);
+    assert!(keys.iter().all(|key| match *key {
+        Reply::Bulk(Some(ref key)) => key.starts_with("user:"),
+        _ => false,
+    }));
+}
@@ -0,0 +1,102 @@
+extern crate log4rs;
+
+use std::str::FromStr;
+
+use nimdg::data_base::{IoEntityError, PersistenceError};
+use nimdg::data_base::json::{JsonValue, ToJson};
+use nimdg::rest::error::{ClientError, ClientErrorType, ErrorCode, ERROR_CODES, errors_description};
+use nimdg::rest::openapi::{openapi_spec, API_BASE_PATH};
+
+use data_base_test::create_test_data_base;
+
+#[test]
+fn persistence_error_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let error = ClientError::from(PersistenceError::TableNotFound(String::from("Order")));
+    assert_eq!(error.code, ErrorCode::TableNotFound);
+    assert_eq!(error.code.status().to_u16(), 404);
+    assert_eq!(error.table, Some(String::from("Order")));
+
+    let error = ClientError::from(PersistenceError::UndefinedTransaction(7));
+    assert_eq!(error.code.name(), "transaction_not_found");
+    assert_eq!(error.code.status().to_u16(), 404);
+    assert_eq!(error.tx_id, Some(7));
+
+    let error = ClientError::from(PersistenceError::WrongTransaction(3, 5));
+    assert_eq!(error.code, ErrorCode::TransactionConflict);
+    assert_eq!(error.code.status().to_u16(), 409);
+    assert_eq!(error.tx_id, Some(5));
+    assert_eq!(error.details, JsonValue::from_str("{\"lock_tx_id\": 3}").unwrap());
+
+    let error = ClientError::from(PersistenceError::LockFailed(String::from("lock failed")));
+    assert_eq!(error.code.status().to_u16(), 423);
+
+    let error = ClientError::from(PersistenceError::BatchFailed(2, Box::new(PersistenceError::TableNotFound(String::from("Order")))));
+    assert_eq!(error.code.status().to_u16(), 409);
+    assert_eq!(error.table, Some(String::from("Order")));
+    assert_eq!(error.details,
+               JsonValue::from_str("{\"index\": 2, \"cause\": {\"code\": \"table_not_found\", \"status\": 404, \"details\": null}}").unwrap());
+
+    let error = ClientError::from(PersistenceError::IoEntity(IoEntityError::Read(String::from("Expected type u64"))));
+    assert_eq!(error.code, ErrorCode::WrongEntity);
+    assert_eq!(error.code.status().to_u16(), 400);
+    assert!(error.description.contains("Expected type u64"));
+
+    let error = ClientError::from(PersistenceError::PreparedLogFailed(String::from("disk is full")));
+    assert_eq!(error.code.status().to_u16(), 500);
+}
+
+#[test]
+fn error_body_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let params = JsonValue::from_str("{\"table_name\": \"Client\", \"tx_id\": 4, \"key\": \"{}\"}").unwrap();
+    let error = ClientError::from(PersistenceError::ReadOnlyTransaction(4)).with_params(&params);
+    assert_eq!(error.to_json(),
+               JsonValue::from_str("{
+                    \"code\": \"read_only_transaction\",
+                    \"message\": \"ReadOnlyTransaction(4)\",
+                    \"details\": null,
+                    \"tx_id\": 4,
+                    \"table\": \"Client\"
+               }")
+                   .unwrap());
+
+    // Implicit transaction is not reported
+    let params = JsonValue::from_str("{\"tx_id\": 0}").unwrap();
+    let error = ClientError::new(ClientErrorType::GettingParamsError(vec![String::from("key")])).with_params(&params);
+    assert_eq!(error.code, ErrorCode::InvalidParams);
+    assert_eq!(error.code.status().to_u16(), 400);
+    assert_eq!(error.tx_id, None);
+    assert_eq!(error.table, None);
+    assert_eq!(error.details, JsonValue::from_str("[\"key\"]").unwrap());
+
+    let description = errors_description();
+    for code in ERROR_CODES {
+        assert!(description.contains(code.name()));
+    }
+}
+
+#[test]
+fn openapi_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let data_base_manager = create_test_data_base();
+    let spec = openapi_spec(&data_base_manager.get_tables_schemas(), "localhost:4300");
+    assert_eq!(spec.find("basePath"), Some(&JsonValue::String(String::from(API_BASE_PATH))));
+
+    let paths = spec.find("paths").unwrap();
+    let get = paths.find("/get/Client/{tx_id}/{key}").and_then(|path| path.find("get")).unwrap();
+    assert_eq!(get.find_path(&["responses", "200", "schema", "$ref"]),
+               Some(&JsonValue::String(String::from("#/definitions/ClientValue"))));
+    assert!(paths.find("/put/Client").and_then(|path| path.find("post")).is_some());
+    assert!(paths.find("/delete/Client/{tx_id}/{key}").and_then(|path| path.find("delete")).is_some());
+    assert!(paths.find("/get/Client/{tx_id}/{start}/{count}").is_some());
+
+    let definitions = spec.find("definitions").unwrap();
+    let schema = data_base_manager.get_table_schema(&String::from("Client")).unwrap();
+    assert_eq!(definitions.find("ClientKey"), schema.find_path(&["properties", "key"]));
+    assert_eq!(definitions.find("ClientValue"), schema.find_path(&["properties", "value"]));
+    assert!(definitions.find("Error").is_some());
+}
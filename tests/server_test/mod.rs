@@ -0,0 +1,183 @@
+use nimdg::data_base::transaction::LockMode;
+use nimdg::rest::auth::Permission;
+use nimdg::server::ServerConfig;
+
+const CONFIG: &'static str = "
+address = \"127.0.0.1:4400\"
+threads = 2
+data_dir = \"data/test\"
+lock_mode = \"optimistic\"
+
+[timeouts]
+read_secs = 10
+changes_secs = 5
+lock_wait_secs = 0
+
+[protocols]
+binary_address = \"127.0.0.1:4401\"
+resp_address = \"127.0.0.1:6380\"
+
+[[tables]]
+name = \"Client\"
+lock_mode = \"optimistic\"
+key = { id = \"u64\" }
+value = { full_name = \"string\", age = \"u64\" }
+";
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| String::from(*arg)).collect()
+}
+
+#[test]
+fn config_from_toml_test() {
+    let config = ServerConfig::from_toml(CONFIG).unwrap();
+    assert_eq!(config.address, "127.0.0.1:4400");
+    assert_eq!(config.threads, 2);
+    assert_eq!(config.log_config, "config/log4rs.yml");
+    assert_eq!(config.data_dir, "data/test");
+    assert_eq!(config.lock_mode, LockMode::Optimistic);
+    assert_eq!(config.timeouts.read_secs, Some(10));
+    assert_eq!(config.timeouts.write_secs, None);
+    assert_eq!(config.timeouts.changes_secs, 5);
+    assert_eq!(config.timeouts.lock_wait_secs, 0);
+    assert_eq!(config.binary_address, "127.0.0.1:4401");
+    assert_eq!(config.resp_address, Some(String::from("127.0.0.1:6380")));
+
+    assert_eq!(config.tables.len(), 1);
+    let table = &config.tables[0];
+    assert_eq!(table.name, "Client");
+    assert_eq!(table.lock_mode, Some(LockMode::Optimistic));
+    assert_eq!(table.key.fields.get("id"), Some(&String::from("u64")));
+    assert_eq!(table.value.fields.len(), 2);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn config_toml_errors_test() {
+    assert!(ServerConfig::from_toml("address = ").is_err());
+    assert!(ServerConfig::from_toml("port = 4300").unwrap_err().contains("port"));
+    assert!(ServerConfig::from_toml("threads = \"many\"").unwrap_err().contains("threads"));
+    assert!(ServerConfig::from_toml("threads = -1").is_err());
+    assert!(ServerConfig::from_toml("lock_mode = \"unknown\"").is_err());
+    assert!(ServerConfig::from_toml("[timeouts]\nidle_secs = 1").unwrap_err().contains("idle_secs"));
+    assert!(ServerConfig::from_toml("[[tables]]\nname = \"Client\"\nkey = { id = \"u64\" }").unwrap_err().contains("Value"));
+    assert!(ServerConfig::from_toml("[[tables]]\nname = \"Client\"\nlock_mode = \"read_only\"\nkey = { id = \"u64\" }\nvalue = { age = \"u64\" }")
+        .unwrap_err()
+        .contains("read_only"));
+}
+
+#[test]
+fn config_from_args_test() {
+    let config = ServerConfig::from_args(&args(&["--config", "config/nimdg.toml", "--threads", "4", "--lock-mode", "optimistic"])).unwrap();
+    assert_eq!(config.address, "localhost:4300");
+    assert_eq!(config.threads, 4);
+    assert_eq!(config.lock_mode, LockMode::Optimistic);
+
+    // Options override file independent of order
+    let config = ServerConfig::from_args(&args(&["--address", "127.0.0.1:4500", "--config", "config/nimdg.toml"])).unwrap();
+    assert_eq!(config.address, "127.0.0.1:4500");
+
+    let config = ServerConfig::from_args(&args(&["--config", "config/nimdg.toml", "--resp-address", "127.0.0.1:6381", "--memcached-table", "cache"]))
+        .unwrap();
+    assert_eq!(config.resp_address, Some(String::from("127.0.0.1:6381")));
+    assert_eq!(config.resp_table, "redis");
+    assert_eq!(config.memcached_table, "cache");
+
+    assert!(ServerConfig::from_args(&args(&["--port", "4300"])).unwrap_err().contains("--port"));
+    assert!(ServerConfig::from_args(&args(&["--threads"])).is_err());
+    assert!(ServerConfig::from_args(&args(&["--threads", "many"])).is_err());
+    assert!(ServerConfig::from_args(&args(&["--config", "config/unknown.toml"])).unwrap_err().contains("unknown.toml"));
+}
+
+#[test]
+fn config_validate_test() {
+    let mut config = ServerConfig::from_toml(CONFIG).unwrap();
+    config.threads = 0;
+    config.log_config = String::from("config/unknown.yml");
+    config.address = String::from("wrong address");
+    config.timeouts.changes_secs = 1000;
+    config.resp_table = String::new();
+    config.tables[0].value.fields.insert(String::from("birthday"), String::from("unknown_type"));
+
+    let errors = config.validate().unwrap_err();
+    assert!(errors.contains("Threads"));
+    assert!(errors.contains("config/unknown.yml"));
+    assert!(errors.contains("wrong address"));
+    assert!(errors.contains("1000"));
+    assert!(errors.contains("RESP"));
+    assert!(errors.contains("Client"));
+}
+
+#[test]
+fn config_auth_test() {
+    let config = ServerConfig::from_toml("
+[[auth.tokens]]
+name = \"loader\"
+token = \"secret\"
+roles = [\"writer\"]
+
+[[auth.users]]
+name = \"admin\"
+password_hash = \"$2y$04$L7Fe3Bv2cWVpQTvXz9ukcO5LNvY8xOXrEvHoTXjIt5a6F8Kp1RZ2y\"
+roles = [\"admin\"]
+
+[auth.roles.writer]
+Client = [\"read\", \"write\"]
+
+[auth.roles.admin]
+\"*\" = [\"admin\"]
+")
+        .unwrap();
+    let auth = config.auth.clone().unwrap();
+    assert_eq!(auth.tokens.len(), 1);
+    assert_eq!(auth.tokens[0].roles, vec![String::from("writer")]);
+    assert_eq!(auth.users[0].name, "admin");
+    assert_eq!(auth.roles.get("writer").and_then(|role| role.get("Client")),
+               Some(&vec![Permission::Read, Permission::Write]));
+    assert_eq!(auth.roles.get("admin").and_then(|role| role.get("*")), Some(&vec![Permission::Admin]));
+    assert!(config.rest_settings().auth.is_some());
+    // Token is hidden in log of configuration
+    assert!(!format!("{:?}", config).contains("secret"));
+
+    assert!(ServerConfig::from_toml("[auth.roles.reader]\nClient = [\"select\"]").unwrap_err().contains("select"));
+    assert!(ServerConfig::from_toml("[[auth.tokens]]\nname = \"loader\"\nroles = []").unwrap_err().contains("token"));
+
+    let mut config = ServerConfig::from_toml(CONFIG).unwrap();
+    config.auth = Some(auth);
+    config.auth.as_mut().unwrap().users[0].roles.push(String::from("operator"));
+    assert!(config.validate().unwrap_err().contains("operator"));
+}
+
+#[test]
+#[cfg(feature = "tls")]
+fn config_tls_test() {
+    let config = ServerConfig::from_toml("
+[tls]
+certificate = \"tests/tls/server.pem\"
+key = \"tests/tls/server.key\"
+client_ca = \"tests/tls/ca.pem\"
+
+[[auth.certificates]]
+common_name = \"loader\"
+name = \"loader\"
+roles = []
+")
+        .unwrap();
+    let tls = config.tls.clone().unwrap();
+    assert_eq!(tls.certificate, "tests/tls/server.pem");
+    assert_eq!(tls.client_ca, Some(String::from("tests/tls/ca.pem")));
+    assert_eq!(config.auth.as_ref().unwrap().certificates[0].common_name, "loader");
+    assert!(config.validate().is_ok());
+    assert!(config.connection_acceptor(config.security()).unwrap().is_some());
+    assert!(ServerConfig::from_toml(CONFIG).unwrap().connection_acceptor(None).unwrap().is_none());
+
+    assert!(ServerConfig::from_toml("[tls]\ncertificate = \"server.pem\"").unwrap_err().contains("key"));
+    assert!(ServerConfig::from_toml("[tls]\ncertificate = \"server.pem\"\nkey = \"server.key\"\nca = \"ca.pem\"")
+        .unwrap_err()
+        .contains("tls.ca"));
+
+    let mut config = ServerConfig::from_toml(CONFIG).unwrap();
+    config.tls = Some(tls);
+    config.tls.as_mut().unwrap().key = String::from("tests/tls/unknown.key");
+    assert!(config.validate().unwrap_err().contains("tests/tls/unknown.key"));
+}
@@ -0,0 +1,20 @@
+#!/bin/sh
+# Self-signed CA, certificates of server (localhost) and client (loader), signed by it,
+# and untrusted self-signed client certificate for tests of TLS. Certificates are valid for 100 years
+set -e
+cd "$(dirname "$0")"
+DAYS=36500
+
+openssl req -x509 -newkey rsa:2048 -nodes -sha256 -days $DAYS -subj "/CN=nimdg test CA" \
+    -keyout ca.key -out ca.pem
+
+sign() {
+    openssl req -newkey rsa:2048 -nodes -sha256 -subj "/CN=$1" -keyout $2.key -out $2.csr
+    openssl x509 -req -sha256 -days $DAYS -in $2.csr -CA ca.pem -CAkey ca.key -set_serial $3 -out $2.pem
+    rm $2.csr
+}
+sign localhost server 1
+sign loader client 2
+
+openssl req -x509 -newkey rsa:2048 -nodes -sha256 -days $DAYS -subj "/CN=loader" \
+    -keyout untrusted.key -out untrusted.pem
@@ -0,0 +1,190 @@
+extern crate log4rs;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::mem;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use iron::Iron;
+use nimdg::data_base::json::JsonValue;
+use std::str::FromStr;
+use nimdg::data_base::transaction::DEFAULT_TX_ID;
+use nimdg::memcached::{MemcachedServer, DEFAULT_MEMCACHED_TABLE_NAME};
+use nimdg::net::{Connection, TlsConfig};
+use nimdg::protocol::client::BinaryClient;
+use nimdg::protocol::server::BinaryServer;
+use nimdg::rest::{create_application, RestSettings};
+use nimdg::rest::auth::{AuthConfig, CertificateConfig, Permission, Role, Security};
+use nimdg::tls::{TlsAcceptor, TlsConnector};
+
+use data_base_test::{create_test_data_base, CLIENT_TABLE_NAME};
+
+// Certificates are generated by tests/tls/generate.sh
+const CA: &'static str = "tests/tls/ca.pem";
+const SERVER_CERTIFICATE: &'static str = "tests/tls/server.pem";
+const SERVER_KEY: &'static str = "tests/tls/server.key";
+const CLIENT_CERTIFICATE: &'static str = "tests/tls/client.pem"; // common name loader
+const CLIENT_KEY: &'static str = "tests/tls/client.key";
+const UNTRUSTED_CERTIFICATE: &'static str = "tests/tls/untrusted.pem"; // self-signed, common name loader
+const UNTRUSTED_KEY: &'static str = "tests/tls/untrusted.key";
+
+fn tls_config(client_ca: Option<&str>) -> TlsConfig {
+    TlsConfig {
+        certificate: String::from(SERVER_CERTIFICATE),
+        key: String::from(SERVER_KEY),
+        client_ca: client_ca.map(String::from),
+    }
+}
+
+// Certificate loader is mapped to principal, if mapped is set
+fn security(mapped: bool) -> Arc<Security> {
+    let mut config = AuthConfig::default();
+    if mapped {
+        config.certificates.push(CertificateConfig {
+            common_name: String::from("loader"),
+            name: String::from("loader"),
+            roles: Vec::new(),
+        });
+    }
+    Arc::new(Security::from_config(&config))
+}
+
+// Certificate loader is mapped to principal with permissions read and write for table
+fn writer_security(table_name: &str) -> Arc<Security> {
+    let mut config = AuthConfig::default();
+    config.certificates.push(CertificateConfig {
+        common_name: String::from("loader"),
+        name: String::from("loader"),
+        roles: vec![String::from("writer")],
+    });
+    let mut role = Role::new();
+    role.insert(String::from(table_name), vec![Permission::Read, Permission::Write]);
+    config.roles.insert(String::from("writer"), role);
+    Arc::new(Security::from_config(&config))
+}
+
+// Address of binary protocol listener with TLS
+fn start_binary_server(config: &TlsConfig, security: Option<Arc<Security>>) -> String {
+    let server = BinaryServer::bind("127.0.0.1:0", Arc::new(create_test_data_base())).unwrap();
+    let address = server.local_addr().unwrap();
+    server.with_acceptor(Arc::new(TlsAcceptor::new(config, security).unwrap())).spawn();
+    address.to_string()
+}
+
+// Address of binary protocol listener, that checks permissions of principals of client certificates
+fn start_protected_server(security: Arc<Security>) -> String {
+    let server = BinaryServer::bind("127.0.0.1:0", Arc::new(create_test_data_base())).unwrap();
+    let address = server.local_addr().unwrap();
+    server.with_acceptor(Arc::new(TlsAcceptor::new(&tls_config(Some(CA)), Some(security.clone())).unwrap()))
+        .with_access_control(security)
+        .spawn();
+    address.to_string()
+}
+
+// Request is sent after handshake, because client of TLS 1.3 finishes handshake before server verifies its certificate
+fn put_over(connection: Result<Box<Connection>, String>) -> Result<(), String> {
+    let mut client = BinaryClient::with_connection(try!(connection));
+    let key = JsonValue::from_str("{\"id\": 1}").unwrap();
+    let value = JsonValue::from_str("{\"full_name\": \"John Doe\", \"age\": 23}").unwrap();
+    try!(client.put(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key, &value));
+    assert_eq!(try!(client.get(DEFAULT_TX_ID, CLIENT_TABLE_NAME, &key)), Some(value));
+    Ok(())
+}
+
+#[test]
+fn binary_protocol_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    // Server without client certificates
+    let address = start_binary_server(&tls_config(None), None);
+    let connector = TlsConnector::new(CA, None).unwrap();
+    put_over(connector.connect(&address, "localhost")).unwrap();
+    // Name of server is checked
+    assert!(connector.connect(&address, "nimdg.cluster").is_err());
+    // Certificate of server is not signed by trusted CA
+    let untrusted = TlsConnector::new(UNTRUSTED_CERTIFICATE, None).unwrap();
+    assert!(untrusted.connect(&address, "localhost").is_err());
+}
+
+#[test]
+fn client_certificate_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let address = start_binary_server(&tls_config(Some(CA)), Some(security(true)));
+    let connector = TlsConnector::new(CA, Some((CLIENT_CERTIFICATE, CLIENT_KEY))).unwrap();
+    put_over(connector.connect(&address, "localhost")).unwrap();
+
+    // Without certificate and with certificate of other CA
+    let anonymous = TlsConnector::new(CA, None).unwrap();
+    assert!(put_over(anonymous.connect(&address, "localhost")).is_err());
+    let untrusted = TlsConnector::new(CA, Some((UNTRUSTED_CERTIFICATE, UNTRUSTED_KEY))).unwrap();
+    assert!(put_over(untrusted.connect(&address, "localhost")).is_err());
+
+    // Certificate is signed by CA, but it is not mapped to principal
+    let address = start_binary_server(&tls_config(Some(CA)), Some(security(false)));
+    assert!(put_over(connector.connect(&address, "localhost")).is_err());
+}
+
+#[test]
+fn certificate_principal_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    // Principal of certificate is not asked for login
+    let connector = TlsConnector::new(CA, Some((CLIENT_CERTIFICATE, CLIENT_KEY))).unwrap();
+    let address = start_protected_server(writer_security(CLIENT_TABLE_NAME));
+    put_over(connector.connect(&address, "localhost")).unwrap();
+
+    // Role of principal has no permissions for table Client
+    let address = start_protected_server(writer_security("Other"));
+    assert!(put_over(connector.connect(&address, "localhost")).unwrap_err().contains("no permission write"));
+}
+
+#[test]
+fn memcached_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    // Connection with certificate of principal is authenticated without set of credentials
+    let security = writer_security(DEFAULT_MEMCACHED_TABLE_NAME);
+    let server = MemcachedServer::bind("127.0.0.1:0", Arc::new(create_test_data_base()), DEFAULT_MEMCACHED_TABLE_NAME).unwrap();
+    let address = server.local_addr().unwrap().to_string();
+    server.with_acceptor(Arc::new(TlsAcceptor::new(&tls_config(Some(CA)), Some(security.clone())).unwrap()))
+        .with_access_control(security)
+        .spawn();
+
+    let connector = TlsConnector::new(CA, Some((CLIENT_CERTIFICATE, CLIENT_KEY))).unwrap();
+    let mut reader = BufReader::new(connector.connect(&address, "localhost").unwrap());
+    reader.get_mut().write_all(b"set greeting 0 0 5\r\nhello\r\nget greeting\r\n").unwrap();
+    let mut lines = Vec::new();
+    for _ in 0..4 {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        lines.push(line);
+    }
+    assert_eq!(lines, vec!["STORED\r\n", "VALUE greeting 0 5\r\n", "hello\r\n", "END\r\n"]);
+}
+
+#[test]
+fn https_test() {
+    log4rs::init_file("config/log4rs.yml", Default::default());
+
+    let app = create_application(Arc::new(create_test_data_base()), RestSettings::default());
+    let listening = Iron::new(app).https("127.0.0.1:0", SERVER_CERTIFICATE.into(), SERVER_KEY.into()).unwrap();
+    let address = listening.socket.to_string();
+    // Server works till end of tests
+    mem::forget(listening);
+
+    let connector = TlsConnector::new(CA, None).unwrap();
+    let mut connection = connector.connect(&address, "localhost").unwrap();
+    connection.write_all(b"GET /api/v1/info HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+    let mut response = String::new();
+    connection.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "Response {}", response);
+    assert!(response.contains("Client"));
+
+    // Plaintext HTTP is not served
+    let mut plain = TcpStream::connect(&address[..]).unwrap();
+    let _ = plain.write_all(b"GET /api/v1/info HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+    let mut response = Vec::new();
+    let _ = plain.read_to_end(&mut response);
+    assert!(!String::from_utf8_lossy(&response).starts_with("HTTP/1.1 200"));
+}